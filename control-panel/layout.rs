@@ -0,0 +1,108 @@
+// The layout of the key buttons, which is saved per project so each game can have buttons for the keys it uses.
+// It's a text file with a line for each button: the key's name as in shared::input::Key, the button's position,
+// and optionally a label to show on it instead of the default one. Lines starting with # are ignored.
+
+use shared::input::Key;
+use std::{fmt::Write, fs, io, path::Path};
+
+pub const FILENAME: &str = "keys.txt";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyLayout {
+    pub key: Key,
+    pub x: i32,
+    pub y: i32,
+    pub label: Option<String>,
+}
+
+impl KeyLayout {
+    fn new(key: Key, x: i32, y: i32) -> Self {
+        Self { key, x, y, label: None }
+    }
+}
+
+/// The layout used when a project doesn't have one.
+pub fn default() -> Vec<KeyLayout> {
+    vec![
+        KeyLayout::new(Key::Left, 103, 150),
+        KeyLayout::new(Key::Down, 151, 150),
+        KeyLayout::new(Key::Right, 199, 150),
+        KeyLayout::new(Key::Up, 151, 102),
+        KeyLayout::new(Key::R, 32, 90),
+        KeyLayout::new(Key::Shift, 32, 150),
+        KeyLayout::new(Key::F2, 270, 90),
+        KeyLayout::new(Key::Z, 270, 150),
+    ]
+}
+
+/// Loads the layout from a project directory, or gives the default one if the project doesn't have one yet.
+pub fn load(project_dir: &Path) -> Result<Vec<KeyLayout>, String> {
+    let path = project_dir.join(FILENAME);
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Saves the layout to a project directory.
+pub fn save(project_dir: &Path, layout: &[KeyLayout]) -> io::Result<()> {
+    fs::create_dir_all(project_dir)?;
+    fs::write(project_dir.join(FILENAME), to_text(layout))
+}
+
+pub fn parse(text: &str) -> Result<Vec<KeyLayout>, String> {
+    let mut layout = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let mut rest = line;
+        let (key, x, y) = match (next_word(&mut rest), next_word(&mut rest), next_word(&mut rest)) {
+            (Some(key), Some(x), Some(y)) => (key, x, y),
+            _ => return Err(format!("line {}: expected KEY X Y [LABEL]", i + 1)),
+        };
+        layout.push(KeyLayout {
+            key: Key::from_name(key).ok_or_else(|| format!("line {}: unknown key {}", i + 1, key))?,
+            x: x.parse().map_err(|_| format!("line {}: invalid x {}", i + 1, x))?,
+            y: y.parse().map_err(|_| format!("line {}: invalid y {}", i + 1, y))?,
+            label: Some(rest).filter(|label| !label.is_empty()).map(String::from),
+        });
+    }
+    Ok(layout)
+}
+
+// Takes the first word off some text, along with the whitespace after it
+fn next_word<'a>(text: &mut &'a str) -> Option<&'a str> {
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let word = &text[..end];
+    *text = text[end..].trim_start();
+    Some(word).filter(|word| !word.is_empty())
+}
+
+pub fn to_text(layout: &[KeyLayout]) -> String {
+    let mut text = String::from("# KEY X Y [LABEL]\n");
+    for button in layout {
+        let _ = write!(text, "{:?} {} {}", button.key, button.x, button.y);
+        if let Some(label) = &button.label {
+            let _ = write!(text, " {}", label);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut layout = default();
+        layout[0].label = Some(String::from("jump left"));
+        assert_eq!(parse(&to_text(&layout)), Ok(layout));
+        assert!(parse("NotAKey 1 2").is_err());
+        assert!(parse("Left 1").is_err());
+    }
+}
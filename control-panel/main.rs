@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
 mod font;
+mod layout;
 mod panel;
+mod piano_roll;
 
 use shared::message::{Message, MessageStream};
 use std::{env, path::Path, process};
@@ -1,4 +1,8 @@
-use crate::font::{self, Font};
+use crate::{
+    font::{self, Font},
+    layout::{self, KeyLayout},
+    piano_roll::PianoRoll,
+};
 use gmio::{
     atlas::{AtlasBuilder, AtlasRef},
     render::{Renderer, RendererOptions},
@@ -6,17 +10,33 @@ use gmio::{
 };
 use shared::{
     input,
-    message::{self, Information, InstanceDetails, MessageStream},
+    message::{
+        self, Information, InputPattern, InstanceDetails, MessageStream, RandomCall, SearchOutcome, WatchedVariable,
+        Watchpoint, WatchpointHit,
+    },
     types::{Colour, ID},
 };
-use std::{net::TcpStream, path::PathBuf};
+use std::{
+    io::{self, BufRead},
+    net::TcpStream,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+};
 
 const WINDOW_WIDTH: u32 = 350;
-const WINDOW_HEIGHT: u32 = 750;
+pub const WINDOW_HEIGHT: u32 = 750;
+
+// The most random results which fit in the preview under the seed
+const RANDOM_PREVIEW_MAX: usize = 12;
 
 const KEY_BUTTON_SIZE: usize = 48;
 const SAVE_BUTTON_SIZE: usize = 32;
 
+// The part of the window where key buttons can be added, from top to bottom
+const KEYBOARD_AREA: (i32, i32) = (86, 230);
+
 pub struct ControlPanel {
     pub window: Window,
     pub renderer: Renderer,
@@ -28,6 +48,10 @@ pub struct ControlPanel {
     pub mouse_buttons: Vec<MouseButton>,
     pub mouse_position_button: MousePositionButton,
     pub big_save_button: BigSaveButton,
+    pub rewind_button: RewindButton,
+    pub piano_roll_button: PianoRollButton,
+    pub watch_list_button: WatchListButton,
+    pub overlay_button: OverlayButton,
     pub save_buttons: Vec<SaveButton>,
     pub seed_changer: SeedChanger,
     pub stream: TcpStream,
@@ -56,9 +80,36 @@ pub struct ControlPanel {
     save_button_active: AtlasRef,
     save_button_inactive: AtlasRef,
     button_outline: AtlasRef,
+    key_label_images: Vec<(input::Key, AtlasRef)>,
 
     menu_context: Option<MenuContext>,
 
+    // Where to put a new key button once the key for it has been pressed
+    adding_key_at: Option<(i32, i32)>,
+
+    // Shown instead of most of the panel while it's open
+    piano_roll: Option<PianoRoll>,
+
+    // Variables whose values the game sends with every update, shown instead of most of the panel when open
+    watch_list: Vec<WatchedVariable>,
+    watch_values: Vec<String>,
+    showing_watch_list: bool,
+
+    // Whether the game is drawing collision masks, bboxes, paths and views over its frames
+    showing_overlay: bool,
+
+    // Which random function to preview the next few results of, how many, and the results for the current seed
+    random_preview: Option<(RandomCall, usize)>,
+    random_values: Vec<f64>,
+
+    // The seeds being tried by a seed search which hasn't finished yet
+    seed_search: Option<RangeInclusive<i32>>,
+
+    // Lines of GML typed into the terminal, to be run by the game
+    console_lines: mpsc::Receiver<String>,
+    watchpoints: Vec<Watchpoint>,
+    paused_by_watchpoint: bool,
+
     pub read_buffer: Vec<u8>,
     pub project_dir: PathBuf,
 }
@@ -69,6 +120,7 @@ pub enum MenuContext {
     MouseButton(input::MouseButton),
     SaveButton(String),
     BigSaveButton,
+    AddKeyButton(i32, i32),
 }
 
 #[derive(Clone, Copy)]
@@ -77,13 +129,15 @@ pub struct AdvanceButton {
     pub y: i32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct KeyButton {
     pub x: i32,
     pub y: i32,
     pub key: input::Key,
     pub state: ButtonState,
-    pub label: AtlasRef,
+    pub label: Option<String>,
+    pub label_image: Option<AtlasRef>,
+    pub pattern: Option<InputPattern>,
 }
 
 #[derive(Clone, Copy)]
@@ -92,6 +146,7 @@ pub struct MouseButton {
     pub y: i32,
     pub button: input::MouseButton,
     pub state: ButtonState,
+    pub pattern: Option<InputPattern>,
 }
 
 #[derive(Clone, Copy)]
@@ -107,6 +162,30 @@ pub struct BigSaveButton {
     pub y: i32,
 }
 
+#[derive(Clone, Copy)]
+pub struct RewindButton {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy)]
+pub struct PianoRollButton {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy)]
+pub struct WatchListButton {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy)]
+pub struct OverlayButton {
+    pub x: i32,
+    pub y: i32,
+}
+
 #[derive(Clone, Copy)]
 pub struct SeedChanger {
     pub x: i32,
@@ -146,6 +225,30 @@ impl BigSaveButton {
     }
 }
 
+impl RewindButton {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < (self.x + 90) && y >= (self.y - 14) && y < (self.y + 3)
+    }
+}
+
+impl PianoRollButton {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < (self.x + 110) && y >= (self.y - 14) && y < (self.y + 3)
+    }
+}
+
+impl WatchListButton {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < (self.x + 90) && y >= (self.y - 14) && y < (self.y + 3)
+    }
+}
+
+impl OverlayButton {
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < (self.x + 70) && y >= (self.y - 14) && y < (self.y + 3)
+    }
+}
+
 impl SeedChanger {
     pub fn contains_point(&self, x: i32, y: i32) -> bool {
         x >= self.x && x < (self.x + 180) && y >= (self.y - 14) && y < (self.y + 3)
@@ -153,6 +256,23 @@ impl SeedChanger {
 }
 
 impl KeyButton {
+    // Makes a button from its layout, using the built-in label image for its key if it has one and no other label
+    fn new(layout: KeyLayout, label_images: &[(input::Key, AtlasRef)]) -> Self {
+        let label_image = match layout.label {
+            Some(_) => None,
+            None => label_images.iter().find(|(key, _)| *key == layout.key).map(|(_, image)| *image),
+        };
+        Self {
+            x: layout.x,
+            y: layout.y,
+            key: layout.key,
+            state: ButtonState::Neutral,
+            label: layout.label,
+            label_image,
+            pattern: None,
+        }
+    }
+
     pub fn contains_point(&self, x: i32, y: i32) -> bool {
         x >= self.x && x < (self.x + KEY_BUTTON_SIZE as i32) && y >= self.y && y < (self.y + KEY_BUTTON_SIZE as i32)
     }
@@ -207,14 +327,16 @@ impl ControlPanel {
         let save_button_inactive = Self::upload_bmp(&mut atlases, include_bytes!("images/save_inactive.bmp"));
         let button_outline = Self::upload_bmp(&mut atlases, include_bytes!("images/outline.bmp"));
 
-        let label_up = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelUp.bmp"));
-        let label_down = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelDown.bmp"));
-        let label_left = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelLeft.bmp"));
-        let label_right = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelRight.bmp"));
-        let label_r = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelR.bmp"));
-        let label_z = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelZ.bmp"));
-        let label_f2 = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelF2.bmp"));
-        let label_shift = Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelShift.bmp"));
+        let key_label_images = vec![
+            (input::Key::Up, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelUp.bmp"))),
+            (input::Key::Down, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelDown.bmp"))),
+            (input::Key::Left, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelLeft.bmp"))),
+            (input::Key::Right, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelRight.bmp"))),
+            (input::Key::R, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelR.bmp"))),
+            (input::Key::Z, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelZ.bmp"))),
+            (input::Key::F2, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelF2.bmp"))),
+            (input::Key::Shift, Self::upload_bmp(&mut atlases, include_bytes!("images/KeyLabelShift.bmp"))),
+        ];
 
         // Helper fn: create a Font
         fn make_font(
@@ -255,6 +377,9 @@ impl ControlPanel {
 
         renderer.push_atlases(atlases)?;
 
+        let key_buttons =
+            layout::load(&project_dir)?.into_iter().map(|layout| KeyButton::new(layout, &key_label_images)).collect();
+
         let mut save_buttons = Vec::with_capacity(2 * 8);
         for y in 0..2 {
             for x in 0..8 {
@@ -273,6 +398,17 @@ impl ControlPanel {
             }
         }
 
+        // Anything typed into the terminal gets sent to the game as GML
+        let (console_sender, console_lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) if console_sender.send(line).is_ok() => (),
+                    _ => break,
+                }
+            }
+        });
+
         window.set_visible(true);
         renderer.finish(WINDOW_WIDTH, WINDOW_HEIGHT, clear_colour);
         Ok(Self {
@@ -282,23 +418,36 @@ impl ControlPanel {
             font,
             font_small,
             advance_button: AdvanceButton { x: 240, y: 8 },
-            key_buttons: vec![
-                KeyButton { x: 103, y: 150, key: input::Key::Left, state: ButtonState::Neutral, label: label_left },
-                KeyButton { x: 151, y: 150, key: input::Key::Down, state: ButtonState::Neutral, label: label_down },
-                KeyButton { x: 199, y: 150, key: input::Key::Right, state: ButtonState::Neutral, label: label_right },
-                KeyButton { x: 151, y: 102, key: input::Key::Up, state: ButtonState::Neutral, label: label_up },
-                KeyButton { x: 32, y: 90, key: input::Key::R, state: ButtonState::Neutral, label: label_r },
-                KeyButton { x: 32, y: 150, key: input::Key::Shift, state: ButtonState::Neutral, label: label_shift },
-                KeyButton { x: 270, y: 90, key: input::Key::F2, state: ButtonState::Neutral, label: label_f2 },
-                KeyButton { x: 270, y: 150, key: input::Key::Z, state: ButtonState::Neutral, label: label_z },
-            ],
+            key_buttons,
             mouse_buttons: vec![
-                MouseButton { x: 4, y: 248, button: input::MouseButton::Left, state: ButtonState::Neutral },
-                MouseButton { x: 56, y: 248, button: input::MouseButton::Middle, state: ButtonState::Neutral },
-                MouseButton { x: 108, y: 248, button: input::MouseButton::Right, state: ButtonState::Neutral },
+                MouseButton {
+                    x: 4,
+                    y: 248,
+                    button: input::MouseButton::Left,
+                    state: ButtonState::Neutral,
+                    pattern: None,
+                },
+                MouseButton {
+                    x: 56,
+                    y: 248,
+                    button: input::MouseButton::Middle,
+                    state: ButtonState::Neutral,
+                    pattern: None,
+                },
+                MouseButton {
+                    x: 108,
+                    y: 248,
+                    button: input::MouseButton::Right,
+                    state: ButtonState::Neutral,
+                    pattern: None,
+                },
             ],
             mouse_position_button: MousePositionButton { x: 310, y: 250, active: false },
             big_save_button: BigSaveButton { x: 125, y: 400 },
+            rewind_button: RewindButton { x: 130, y: 32 },
+            piano_roll_button: PianoRollButton { x: 130, y: 56 },
+            watch_list_button: WatchListButton { x: 4, y: 56 },
+            overlay_button: OverlayButton { x: 260, y: 62 },
             save_buttons,
             seed_changer: SeedChanger { x: 8, y: 540 },
             stream,
@@ -327,8 +476,21 @@ impl ControlPanel {
             save_button_active,
             save_button_inactive,
             button_outline,
+            key_label_images,
 
             menu_context: None,
+            adding_key_at: None,
+            piano_roll: None,
+            watch_list: Vec::new(),
+            watch_values: Vec::new(),
+            showing_watch_list: false,
+            showing_overlay: false,
+            random_preview: None,
+            random_values: Vec::new(),
+            seed_search: None,
+            console_lines,
+            watchpoints: Vec::new(),
+            paused_by_watchpoint: false,
             read_buffer: Vec::new(),
             project_dir,
         })
@@ -350,11 +512,34 @@ impl ControlPanel {
                     self.watched_id = Some(details.id);
                     self.watched_instance = Some(details);
                 },
+                Some(Some(Information::ExecuteResult { result, tampered })) => {
+                    Self::print_execute_result(result, tampered)
+                },
+                Some(Some(Information::WatchpointHit { hit })) => self.handle_watchpoint_hit(hit),
+                Some(Some(Information::WatchpointError { error })) => println!("Couldn't set watchpoints: {}", error),
+                Some(Some(Information::PredicateMet { frame_count })) => {
+                    println!("The predicate was met on frame {}", frame_count)
+                },
+                Some(Some(Information::Frames { from, held })) => {
+                    if let Some(piano_roll) = self.piano_roll.as_mut() {
+                        piano_roll.set_frames(from, held);
+                    }
+                },
+                Some(Some(Information::RandomPreview { seed, values })) => self.set_random_values(seed, values),
+                Some(Some(Information::SearchProgress { done, total })) => {
+                    println!("Tried {} of {} seed(s)...", done, total)
+                },
+                Some(Some(Information::SearchResult { result })) => self.handle_search_result(result),
                 Some(Some(s)) => println!("Got TCP message: '{:?}'", s),
                 Some(None) => break,
             }
         }
 
+        // Send any console input to the game, running as the watched instance by default
+        while let Ok(line) = self.console_lines.try_recv() {
+            self.handle_console_line(line.trim())?;
+        }
+
         'evloop: for event in self.window.process_events() {
             match event {
                 Event::MouseMove(x, y) => {
@@ -368,6 +553,39 @@ impl ControlPanel {
                         break
                     }
 
+                    if self.rewind_button.contains_point(self.mouse_x, self.mouse_y) {
+                        self.send_rewind()?;
+                        break
+                    }
+
+                    if self.piano_roll_button.contains_point(self.mouse_x, self.mouse_y) {
+                        self.toggle_piano_roll()?;
+                        break
+                    }
+
+                    if self.watch_list_button.contains_point(self.mouse_x, self.mouse_y) {
+                        self.showing_watch_list = !self.showing_watch_list;
+                        if self.showing_watch_list {
+                            self.piano_roll = None;
+                        }
+                        break
+                    }
+
+                    if self.overlay_button.contains_point(self.mouse_x, self.mouse_y) {
+                        self.showing_overlay = !self.showing_overlay;
+                        self.stream.send_message(&message::Message::SetOverlay { enabled: self.showing_overlay })?;
+                        break
+                    }
+
+                    // The rest of the panel is hidden behind the piano roll or the watch list
+                    if self.piano_roll.is_some() {
+                        self.click_piano_roll()?;
+                        break
+                    }
+                    if self.showing_watch_list {
+                        break
+                    }
+
                     for button in self.key_buttons.iter_mut() {
                         if button.contains_point(self.mouse_x, self.mouse_y) {
                             button.state = match button.state {
@@ -425,10 +643,16 @@ impl ControlPanel {
                         } else {
                             self.new_seed = Some(self.seed + 1);
                         }
+                        self.request_random_preview()?;
+                        break
                     }
                 },
 
                 Event::MouseButtonUp(input::MouseButton::Right) => {
+                    if self.piano_roll.is_some() || self.showing_watch_list {
+                        break
+                    }
+
                     for button in self.key_buttons.iter_mut() {
                         if button.contains_point(self.mouse_x, self.mouse_y) {
                             let options = match button.state {
@@ -440,6 +664,7 @@ impl ControlPanel {
                                     ("Press-Release\0".into(), 2),
                                     ("Press\0".into(), 1),
                                     ("Reset\0".into(), 0),
+                                    ("Remove button\0".into(), 8),
                                 ],
                                 ButtonState::Held
                                 | ButtonState::HeldWillRelease
@@ -449,6 +674,7 @@ impl ControlPanel {
                                     ("Release-Press\0".into(), 6),
                                     ("Release\0".into(), 5),
                                     ("Reset\0".into(), 4),
+                                    ("Remove button\0".into(), 8),
                                 ],
                             };
                             self.window.show_context_menu(&options);
@@ -498,11 +724,23 @@ impl ControlPanel {
                         self.menu_context = Some(MenuContext::SaveButton("save.bin".into()));
                         break
                     }
+
+                    if self.mouse_y >= KEYBOARD_AREA.0 && self.mouse_y < KEYBOARD_AREA.1 {
+                        self.window.show_context_menu(&[("Add key button here\0".into(), 0)]);
+                        self.menu_context = Some(MenuContext::AddKeyButton(self.mouse_x, self.mouse_y));
+                        break
+                    }
                 },
 
                 Event::MenuOption(option) => {
                     match &self.menu_context {
                         Some(MenuContext::KeyButton(target_key)) => {
+                            if *option == 8 {
+                                let key = *target_key;
+                                self.key_buttons.retain(|button| button.key != key);
+                                self.save_key_layout();
+                                break
+                            }
                             let new_state = match option {
                                 0 => ButtonState::Neutral,
                                 1 => ButtonState::NeutralWillPress,
@@ -567,6 +805,13 @@ impl ControlPanel {
                             }
                         },
 
+                        Some(MenuContext::AddKeyButton(x, y)) => {
+                            if *option == 0 {
+                                self.adding_key_at = Some((*x, *y));
+                                println!("Press the key to add a button for, or Escape to cancel");
+                            }
+                        },
+
                         _ => (),
                     }
                 },
@@ -577,6 +822,15 @@ impl ControlPanel {
                     break
                 },
 
+                Event::MouseWheelUp | Event::MouseWheelDown => {
+                    let frames = if let Event::MouseWheelUp = event { -3 } else { 3 };
+                    if let Some(piano_roll) = self.piano_roll.as_mut() {
+                        if piano_roll.scroll(frames) {
+                            self.stream.send_message(piano_roll.request())?;
+                        }
+                    }
+                },
+
                 _ => (),
             }
         }
@@ -585,11 +839,22 @@ impl ControlPanel {
     }
 
     pub fn handle_key(&mut self, key: input::Key) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((x, y)) = self.adding_key_at.take() {
+            if key != input::Key::Escape {
+                self.add_key_button(key, x, y);
+            }
+            return Ok(())
+        }
+
         match key {
             input::Key::Space => {
                 self.send_advance()?;
             },
 
+            input::Key::Backspace => {
+                self.send_rewind()?;
+            },
+
             input::Key::Q => {
                 self.stream.send_message(&message::Message::Save { filename: "save.bin".into() })?;
                 println!("Probably saved");
@@ -612,12 +877,57 @@ impl ControlPanel {
         Ok(())
     }
 
+    // Adds a button for a key, centred on the given position as far as it fits in the keyboard area
+    fn add_key_button(&mut self, key: input::Key, x: i32, y: i32) {
+        if self.key_buttons.iter().any(|button| button.key == key) {
+            println!("There's already a button for {:?}", key);
+            return
+        }
+        let size = KEY_BUTTON_SIZE as i32;
+        let layout = KeyLayout {
+            key,
+            x: (x - size / 2).max(0).min(WINDOW_WIDTH as i32 - size),
+            y: (y - size / 2).max(KEYBOARD_AREA.0).min(KEYBOARD_AREA.1 - size),
+            label: None,
+        };
+        self.key_buttons.push(KeyButton::new(layout, &self.key_label_images));
+        self.save_key_layout();
+        println!("Added a button for {:?}, which will show whether it's held from the next frame", key);
+    }
+
+    fn save_key_layout(&self) {
+        let layout = self
+            .key_buttons
+            .iter()
+            .map(|button| KeyLayout { key: button.key, x: button.x, y: button.y, label: button.label.clone() })
+            .collect::<Vec<_>>();
+        if let Err(e) = layout::save(&self.project_dir, &layout) {
+            println!("Couldn't save the key layout: {}", e);
+        }
+    }
+
     fn send_advance(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.advance(1)
+    }
+
+    // Advances some frames. If any buttons have patterns, the game runs them, otherwise a single frame
+    // just gets the inputs set on the buttons
+    fn advance(&mut self, frames: usize) -> Result<bool, Box<dyn std::error::Error>> {
+        // Swallow the first advance after a pausing watchpoint, so holding down advance stops there
+        if self.paused_by_watchpoint {
+            self.paused_by_watchpoint = false;
+            println!("Stopped at watchpoint, advance again to continue");
+            return Ok(false)
+        }
+
         let mut key_inputs = Vec::new();
         let mut keys_requested = Vec::new();
 
         for key in self.key_buttons.iter() {
             keys_requested.push(key.key);
+            if key.pattern.is_some() {
+                continue
+            }
             match key.state {
                 ButtonState::Neutral | ButtonState::Held => (),
                 ButtonState::NeutralWillPress => key_inputs.push((key.key, true)),
@@ -648,6 +958,9 @@ impl ControlPanel {
 
         for button in self.mouse_buttons.iter() {
             mouse_buttons_requested.push(button.button);
+            if button.pattern.is_some() {
+                continue
+            }
             match button.state {
                 ButtonState::Neutral | ButtonState::Held => (),
                 ButtonState::NeutralWillPress => mouse_inputs.push((button.button, true)),
@@ -673,16 +986,82 @@ impl ControlPanel {
             }
         }
 
-        self.stream.send_message(message::Message::Advance {
-            key_inputs,
-            mouse_inputs,
-            mouse_location: self.game_mouse_pos,
-            keys_requested,
-            mouse_buttons_requested,
+        let key_patterns =
+            self.key_buttons.iter().filter_map(|button| Some((input::Button::Key(button.key), button.pattern?)));
+        let mouse_patterns =
+            self.mouse_buttons.iter().filter_map(|button| Some((input::Button::Mouse(button.button), button.pattern?)));
+        let patterns = key_patterns.chain(mouse_patterns).collect::<Vec<_>>();
+
+        if frames == 1 && patterns.is_empty() {
+            self.stream.send_message(message::Message::Advance {
+                key_inputs,
+                mouse_inputs,
+                mouse_location: self.game_mouse_pos,
+                keys_requested,
+                mouse_buttons_requested,
+                instance_requested: self.watched_id,
+                new_seed: self.new_seed,
+            })?;
+        } else {
+            self.stream.send_message(message::Message::AdvanceMany {
+                frames,
+                key_inputs,
+                mouse_inputs,
+                mouse_location: self.game_mouse_pos,
+                patterns,
+                keys_requested,
+                mouse_buttons_requested,
+                instance_requested: self.watched_id,
+                new_seed: self.new_seed,
+            })?;
+        }
+
+        self.await_update()
+    }
+
+    fn toggle_piano_roll(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.piano_roll.take().is_none() {
+            let columns = self
+                .key_buttons
+                .iter()
+                .map(|button| input::Button::Key(button.key))
+                .chain(self.mouse_buttons.iter().map(|button| input::Button::Mouse(button.button)))
+                .collect();
+            let piano_roll = PianoRoll::new(columns, self.frame_count);
+            self.stream.send_message(piano_roll.request())?;
+            self.piano_roll = Some(piano_roll);
+            self.showing_watch_list = false;
+        }
+        Ok(())
+    }
+
+    // Flips whether the button in the clicked cell of the piano roll is held on that frame
+    fn click_piano_roll(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (frame, button, held) = match self.piano_roll.as_ref().and_then(|piano_roll| {
+            let (frame, column) = piano_roll.cell_at(self.mouse_x, self.mouse_y)?;
+            Some((frame, piano_roll.columns[column], piano_roll.held(frame, column)?))
+        }) {
+            Some(cell) => cell,
+            None => return Ok(()),
+        };
+        self.stream.send_message(message::Message::SetHeld {
+            frame,
+            button,
+            held: !held,
+            keys_requested: self.key_buttons.iter().map(|x| x.key).collect(),
+            mouse_buttons_requested: self.mouse_buttons.iter().map(|x| x.button).collect(),
             instance_requested: self.watched_id,
-            new_seed: self.new_seed,
         })?;
+        self.await_update()?;
+        Ok(())
+    }
 
+    fn send_rewind(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.stream.send_message(message::Message::Rewind {
+            keys_requested: self.key_buttons.iter().map(|x| x.key).collect(),
+            mouse_buttons_requested: self.mouse_buttons.iter().map(|x| x.button).collect(),
+            instance_requested: self.watched_id,
+        })?;
         self.await_update()
     }
 
@@ -696,12 +1075,14 @@ impl ControlPanel {
                     frame_count,
                     seed,
                     instance,
+                    watch_values,
                 }))) => {
                     self.frame_count = frame_count;
                     self.game_mouse_pos = mouse_location;
                     self.watched_instance = instance;
                     self.seed = seed;
                     self.new_seed = None;
+                    self.watch_values = watch_values;
                     for button in self.key_buttons.iter_mut() {
                         if keys_held.contains(&button.key) {
                             button.state = ButtonState::Held;
@@ -716,14 +1097,317 @@ impl ControlPanel {
                             button.state = ButtonState::Neutral;
                         }
                     }
+                    // A hold is over once the frame which releases the button has been run
+                    let over = |pattern: &Option<InputPattern>| match pattern {
+                        Some(InputPattern::Hold { until }) => frame_count > *until,
+                        _ => false,
+                    };
+                    for button in self.key_buttons.iter_mut().filter(|button| over(&button.pattern)) {
+                        button.pattern = None;
+                    }
+                    for button in self.mouse_buttons.iter_mut().filter(|button| over(&button.pattern)) {
+                        button.pattern = None;
+                    }
+                    if let Some(piano_roll) = self.piano_roll.as_mut() {
+                        piano_roll.set_frame_count(frame_count);
+                        if let Err(e) = self.stream.send_message(piano_roll.request()) {
+                            break Err(e.into())
+                        }
+                    }
+                    if let Err(e) = self.request_random_preview() {
+                        break Err(e.into())
+                    }
                     break Ok(true)
                 },
+                Ok(Some(Some(message::Information::ExecuteResult { result, tampered }))) => {
+                    Self::print_execute_result(result, tampered)
+                },
+                Ok(Some(Some(message::Information::WatchpointHit { hit }))) => self.handle_watchpoint_hit(hit),
+                Ok(Some(Some(message::Information::General { message }))) => println!("{}", message),
+                Ok(Some(Some(message::Information::PredicateMet { frame_count }))) => {
+                    println!("The predicate was met on frame {}", frame_count)
+                },
+                Ok(Some(Some(message::Information::Frames { from, held }))) => {
+                    if let Some(piano_roll) = self.piano_roll.as_mut() {
+                        piano_roll.set_frames(from, held);
+                    }
+                },
+                Ok(Some(Some(message::Information::RandomPreview { seed, values }))) => {
+                    self.set_random_values(seed, values)
+                },
+                Ok(Some(Some(message::Information::SearchProgress { done, total }))) => {
+                    println!("Tried {} of {} seed(s)...", done, total)
+                },
+                Ok(Some(Some(message::Information::SearchResult { result }))) => self.handle_search_result(result),
                 Err(e) => break Err(e.into()),
                 _ => (),
             }
         }
     }
 
+    // Handles a line typed into the terminal: either a watchpoint command, or GML for the game to run
+    fn handle_console_line(&mut self, line: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if line.is_empty() {
+            return Ok(())
+        }
+        if let Some(watchpoint) = line.strip_prefix("/watch ") {
+            match watchpoint.parse::<Watchpoint>() {
+                Ok(watchpoint) => {
+                    self.watchpoints.push(watchpoint);
+                    self.send_watchpoints()?;
+                },
+                Err(e) => println!("Invalid watchpoint: {} (expected \"/watch ID|obj:INDEX VARIABLE [pause]\")", e),
+            }
+        } else if line == "/unwatch" {
+            self.watchpoints.clear();
+            self.send_watchpoints()?;
+        } else if let Some(variable) = line.strip_prefix("/show ") {
+            match variable.parse::<WatchedVariable>() {
+                Ok(variable) => {
+                    self.watch_list.push(variable);
+                    self.send_watch_list()?;
+                },
+                Err(e) => println!("Invalid variable: {} (expected \"/show ID|global VARIABLE[INDEX]\")", e),
+            }
+        } else if let Some(args) = line.strip_prefix("/hide").filter(|args| args.is_empty() || args.starts_with(' ')) {
+            match args.trim() {
+                "" => self.watch_list.clear(),
+                number => match number.parse::<usize>() {
+                    Ok(number) if number >= 1 && number <= self.watch_list.len() => {
+                        self.watch_list.remove(number - 1);
+                    },
+                    _ => {
+                        println!("Expected \"/hide [NUMBER]\", where NUMBER is the variable's place in the watch list");
+                        return Ok(())
+                    },
+                },
+            }
+            self.send_watch_list()?;
+        } else if let Some(args) = line.strip_prefix("/hold ") {
+            self.set_patterns(args, |frames, frame_count| InputPattern::Hold { until: frame_count + frames });
+        } else if let Some(args) = line.strip_prefix("/autofire ") {
+            self.set_patterns(args, |period, frame_count| InputPattern::Autofire { start: frame_count, period });
+        } else if let Some(args) = line.strip_prefix("/clear").filter(|args| args.is_empty() || args.starts_with(' ')) {
+            if args.trim().is_empty() {
+                self.key_buttons.iter_mut().for_each(|button| button.pattern = None);
+                self.mouse_buttons.iter_mut().for_each(|button| button.pattern = None);
+                println!("Cleared every button's pattern");
+            }
+            for name in args.split_whitespace() {
+                if !self.set_pattern(name, None) {
+                    println!("There's no button for {}", name);
+                }
+            }
+        } else if let Some(expression) = line.strip_prefix("/until").filter(|e| e.is_empty() || e.starts_with(' ')) {
+            let expression = Some(expression.trim()).filter(|e| !e.is_empty()).map(String::from);
+            match &expression {
+                Some(expression) => println!("Advancing several frames will stop once {} is true", expression),
+                None => println!("Turned off the predicate"),
+            }
+            self.stream.send_message(message::Message::SetPredicate { expression })?;
+        } else if let Some(args) = line.strip_prefix("/rng").filter(|args| args.is_empty() || args.starts_with(' ')) {
+            let args = args.trim();
+            if args.is_empty() {
+                self.random_preview = None;
+                return Ok(())
+            }
+            let (count, call) = args.split_at(args.find(' ').unwrap_or(args.len()));
+            let call = match call.trim() {
+                "" => Ok(RandomCall::Random(1.0)),
+                call => call.parse::<RandomCall>(),
+            };
+            match (count.parse::<usize>(), call) {
+                (Ok(count), Ok(call)) if count >= 1 && count <= RANDOM_PREVIEW_MAX => {
+                    self.random_preview = Some((call, count));
+                    self.random_values.clear();
+                    self.request_random_preview()?;
+                },
+                (_, Err(e)) => println!("{}", e),
+                _ => println!("Expected \"/rng [COUNT [CALL]]\", where COUNT is 1 to {}", RANDOM_PREVIEW_MAX),
+            }
+        } else if let Some(args) = line.strip_prefix("/seeds ") {
+            self.start_seed_search(args)?;
+        } else if line == "/cancel" {
+            if self.seed_search.is_some() {
+                println!("Cancelling the seed search...");
+                self.stream.send_message(message::Message::CancelSearch)?;
+            } else {
+                println!("There's no seed search to cancel");
+            }
+        } else if let Some(frames) = line.strip_prefix("/advance ") {
+            match frames.trim().parse::<usize>() {
+                Ok(frames) if frames > 0 => {
+                    self.advance(frames)?;
+                },
+                _ => println!("Expected \"/advance FRAMES\""),
+            }
+        } else {
+            match message::Message::execute_from_console(line, self.watched_id) {
+                Ok(m) => self.stream.send_message(m)?,
+                Err(e) => println!("{}", e),
+            }
+        }
+        Ok(())
+    }
+
+    // Handles the arguments to /hold or /autofire, which are a number of frames and then the buttons to give
+    // the pattern made from it to, by name (see input::Button::from_name)
+    fn set_patterns(&mut self, args: &str, make_pattern: impl Fn(usize, usize) -> InputPattern) {
+        let mut words = args.split_whitespace();
+        let frames = match words.next().and_then(|frames| frames.parse::<usize>().ok()) {
+            Some(frames) if frames > 0 => frames,
+            _ => {
+                println!("Expected a number of frames and then the buttons, for example \"/hold 20 Right Z\"");
+                return
+            },
+        };
+        let pattern = make_pattern(frames, self.frame_count);
+        for name in words {
+            if self.set_pattern(name, Some(pattern)) {
+                println!("{} will follow {:?} from the next frame", name, pattern);
+            } else {
+                println!("There's no button for {}", name);
+            }
+        }
+    }
+
+    // Sets the pattern of the button with the given name, returning false if the panel has no such button
+    fn set_pattern(&mut self, name: &str, pattern: Option<InputPattern>) -> bool {
+        let mut found = false;
+        match input::Button::from_name(name) {
+            Some(input::Button::Key(key)) => {
+                for button in self.key_buttons.iter_mut().filter(|button| button.key == key) {
+                    button.pattern = pattern;
+                    found = true;
+                }
+            },
+            Some(input::Button::Mouse(mouse_button)) => {
+                for button in self.mouse_buttons.iter_mut().filter(|button| button.button == mouse_button) {
+                    button.pattern = pattern;
+                    found = true;
+                }
+            },
+            None => (),
+        }
+        found
+    }
+
+    // Sends the watch list to the game. Its values only arrive with the next update, so they're cleared until then.
+    fn send_watch_list(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{} variable(s) on the watch list, which will update on the next frame", self.watch_list.len());
+        self.watch_values.clear();
+        Ok(self.stream.send_message(message::Message::SetWatchList { variables: self.watch_list.clone() })?)
+    }
+
+    fn send_watchpoints(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("{} watchpoint(s) set", self.watchpoints.len());
+        Ok(self.stream.send_message(message::Message::SetWatchpoints { watchpoints: self.watchpoints.clone() })?)
+    }
+
+    // Handles the arguments to /seeds, which are the savestate to start from, the first and last seeds to try,
+    // the number of frames to run, and then the GML condition
+    fn start_seed_search(&mut self, args: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut words = Vec::with_capacity(4);
+        let mut predicate = args.trim();
+        while words.len() < 4 && !predicate.is_empty() {
+            let (word, rest) = predicate.split_at(predicate.find(' ').unwrap_or(predicate.len()));
+            words.push(word);
+            predicate = rest.trim_start();
+        }
+        let (filename, first, last, frames) = match words.as_slice() {
+            [filename, first, last, frames] if !predicate.is_empty() => {
+                (*filename, first.parse::<i32>(), last.parse::<i32>(), frames.parse::<usize>())
+            },
+            _ => {
+                println!("Expected \"/seeds SAVESTATE FIRST LAST FRAMES CONDITION\"");
+                return Ok(())
+            },
+        };
+        let (first, last, frames) = match (first, last, frames) {
+            (Ok(first), Ok(last), Ok(frames)) if first <= last && frames > 0 => (first, last, frames),
+            _ => {
+                println!("Expected FIRST <= LAST and FRAMES > 0, for example \"/seeds save1.bin 0 999 60 x > 400\"");
+                return Ok(())
+            },
+        };
+        if self.seed_search.is_some() {
+            println!("A seed search is already running, which /cancel stops");
+            return Ok(())
+        }
+        let seeds = first..=last;
+        let count = message::seed_count(&seeds);
+        if count > message::MAX_SEARCH_SEEDS {
+            println!("That's {} seeds, but a search can try at most {}", count, message::MAX_SEARCH_SEEDS);
+            return Ok(())
+        }
+        println!("Trying {} seed(s) from {} for up to {} frame(s), which /cancel stops...", count, filename, frames);
+        self.stream.send_message(message::Message::SearchSeeds {
+            filename: filename.into(),
+            predicate: predicate.into(),
+            seeds: seeds.clone(),
+            frames,
+        })?;
+        self.seed_search = Some(seeds);
+        Ok(())
+    }
+
+    fn handle_search_result(&mut self, result: Result<Vec<SearchOutcome>, String>) {
+        let seeds = self.seed_search.take().into_iter().flatten();
+        match result {
+            Ok(outcomes) => {
+                let mut satisfied = 0;
+                for (seed, outcome) in seeds.zip(outcomes.iter()) {
+                    match outcome {
+                        SearchOutcome::Satisfied { frames } => {
+                            satisfied += 1;
+                            println!("Seed {} works after {} frame(s)", seed, frames);
+                        },
+                        SearchOutcome::Failed { error } => println!("Seed {} failed {}", seed, error),
+                        SearchOutcome::Unsatisfied => (),
+                    }
+                }
+                println!("{} of {} seed(s) worked", satisfied, outcomes.len());
+            },
+            Err(e) => println!("The search couldn't be run: {}", e),
+        }
+    }
+
+    // Asks the game to preview the random function for the seed the next frame will start with
+    fn request_random_preview(&mut self) -> io::Result<()> {
+        match self.random_preview {
+            Some((call, count)) => self.stream.send_message(message::Message::PreviewRandom {
+                seed: self.new_seed.unwrap_or(self.seed),
+                count,
+                call,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn set_random_values(&mut self, seed: i32, values: Vec<f64>) {
+        // The seed might have been changed again since this preview was asked for
+        if seed == self.new_seed.unwrap_or(self.seed) {
+            self.random_values = values;
+        }
+    }
+
+    fn handle_watchpoint_hit(&mut self, hit: WatchpointHit) {
+        println!("Watchpoint hit: {}", hit);
+        if hit.pause {
+            self.paused_by_watchpoint = true;
+        }
+    }
+
+    fn print_execute_result(result: Result<String, String>, tampered: bool) {
+        match result {
+            Ok(value) => println!("{}", value),
+            Err(e) => println!("{}", e),
+        }
+        if tampered {
+            println!("(game state was changed, so this replay has been marked as tampered)");
+        }
+    }
+
     pub fn draw(&mut self) {
         self.renderer.set_view(
             WINDOW_WIDTH,
@@ -743,6 +1427,43 @@ impl ControlPanel {
 
         draw_text(&mut self.renderer, "Frame:", 4.0, 19.0, &self.font, 0, 1.0);
         draw_text(&mut self.renderer, &self.frame_count.to_string(), 4.0, 32.0, &self.font, 0, 1.0);
+        draw_text(
+            &mut self.renderer,
+            "< Rewind",
+            self.rewind_button.x.into(),
+            self.rewind_button.y.into(),
+            &self.font,
+            0,
+            if self.rewind_button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 },
+        );
+
+        draw_text(
+            &mut self.renderer,
+            "Piano roll",
+            self.piano_roll_button.x.into(),
+            self.piano_roll_button.y.into(),
+            &self.font,
+            if self.piano_roll.is_some() { 0x4CB122 } else { 0 },
+            if self.piano_roll_button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 },
+        );
+        draw_text(
+            &mut self.renderer,
+            "Watch list",
+            self.watch_list_button.x.into(),
+            self.watch_list_button.y.into(),
+            &self.font,
+            if self.showing_watch_list { 0x4CB122 } else { 0 },
+            if self.watch_list_button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 },
+        );
+        draw_text(
+            &mut self.renderer,
+            "Overlay",
+            self.overlay_button.x.into(),
+            self.overlay_button.y.into(),
+            &self.font,
+            if self.showing_overlay { 0x4CB122 } else { 0 },
+            if self.overlay_button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 },
+        );
 
         self.renderer.draw_sprite(
             &self.advance_button_normal,
@@ -755,6 +1476,33 @@ impl ControlPanel {
             if self.advance_button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.8 },
         );
 
+        if let Some(piano_roll) = self.piano_roll.as_ref() {
+            piano_roll.draw(&mut self.renderer, &self.font_small, self.mouse_x, self.mouse_y);
+            self.renderer.finish(WINDOW_WIDTH, WINDOW_HEIGHT, self.clear_colour);
+            return
+        }
+
+        if self.showing_watch_list {
+            if self.watch_list.is_empty() {
+                let hint = "Type /show ID|global VARIABLE in the terminal";
+                draw_text(&mut self.renderer, hint, 8.0, 100.0, &self.font_small, 0, 1.0);
+            }
+            for (i, variable) in self.watch_list.iter().enumerate() {
+                let value = self.watch_values.get(i).map(String::as_str).unwrap_or("...");
+                draw_text(
+                    &mut self.renderer,
+                    &format!("{}. {} = {}", i + 1, variable, value),
+                    8.0,
+                    100.0 + 16.0 * i as f64,
+                    &self.font_small,
+                    0,
+                    1.0,
+                );
+            }
+            self.renderer.finish(WINDOW_WIDTH, WINDOW_HEIGHT, self.clear_colour);
+            return
+        }
+
         for button in self.key_buttons.iter() {
             let alpha = if button.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.6 };
             let atlas_ref_l = match button.state {
@@ -786,16 +1534,32 @@ impl ControlPanel {
                 0xFFFFFF,
                 alpha,
             );
-            self.renderer.draw_sprite(
-                &button.label,
-                f64::from(button.x),
-                f64::from(button.y),
-                1.0,
-                1.0,
-                0.0,
-                0xFFFFFF,
-                alpha,
-            );
+            match (&button.label, &button.label_image) {
+                (None, Some(image)) => {
+                    self.renderer.draw_sprite(
+                        image,
+                        f64::from(button.x),
+                        f64::from(button.y),
+                        1.0,
+                        1.0,
+                        0.0,
+                        0xFFFFFF,
+                        alpha,
+                    );
+                },
+                (label, _) => {
+                    let name = format!("{:?}", button.key);
+                    draw_text(
+                        &mut self.renderer,
+                        label.as_ref().unwrap_or(&name),
+                        f64::from(button.x) + 6.0,
+                        f64::from(button.y) + 29.0,
+                        &self.font_small,
+                        0,
+                        alpha,
+                    );
+                },
+            }
             self.renderer.draw_sprite(
                 &self.button_outline,
                 f64::from(button.x),
@@ -806,6 +1570,9 @@ impl ControlPanel {
                 0xFFFFFF,
                 alpha,
             );
+            if let Some(pattern) = button.pattern {
+                draw_pattern(&mut self.renderer, pattern, button.x, button.y, &self.font_small);
+            }
         }
 
         for button in self.mouse_buttons.iter() {
@@ -849,6 +1616,9 @@ impl ControlPanel {
                 0xFFFFFF,
                 alpha,
             );
+            if let Some(pattern) = button.pattern {
+                draw_pattern(&mut self.renderer, pattern, button.x, button.y, &self.font_small);
+            }
         }
 
         self.renderer.draw_sprite(
@@ -898,6 +1668,9 @@ impl ControlPanel {
         }
 
         draw_text(&mut self.renderer, "Keyboard", 123.0, 82.0, &self.font, 0, 1.0);
+        if self.adding_key_at.is_some() {
+            draw_text(&mut self.renderer, "Press a key to add (Esc cancels)", 8.0, 222.0, &self.font_small, 0xFF, 1.0);
+        }
         draw_text(&mut self.renderer, "Mouse", 143.0, 236.0, &self.font, 0, 1.0);
         draw_text(&mut self.renderer, "Saves", 143.0, 390.0, &self.font, 0, 1.0);
 
@@ -912,6 +1685,22 @@ impl ControlPanel {
             if self.seed_changer.contains_point(self.mouse_x, self.mouse_y) { 1.0 } else { 0.75 },
         );
 
+        if let Some((call, _)) = self.random_preview {
+            draw_text(&mut self.renderer, &format!("Next {}:", call), 8.0, 555.0, &self.font_small, 0, 1.0);
+            let values = self
+                .random_values
+                .iter()
+                .map(|value| match call {
+                    RandomCall::Random(_) => format!("{:.3}", value),
+                    RandomCall::Irandom(_) => value.to_string(),
+                })
+                .collect::<Vec<_>>();
+            for (i, line) in values.chunks(RANDOM_PREVIEW_MAX / 2).enumerate() {
+                let y = 569.0 + 13.0 * i as f64;
+                draw_text(&mut self.renderer, &line.join(", "), 8.0, y, &self.font_small, 0x303030, 1.0);
+            }
+        }
+
         if let Some(id) = self.watched_id.as_ref() {
             draw_text(&mut self.renderer, "Watching:", 8.0, 605.0, &self.font, 0, 1.0);
             if let Some(details) = self.watched_instance.as_ref() {
@@ -1043,7 +1832,16 @@ impl ControlPanel {
     }
 }
 
-fn draw_text(renderer: &mut Renderer, text: &str, mut x: f64, y: f64, font: &Font, colour: i32, alpha: f64) {
+// Marks a button which is following a pattern, in its top left corner
+fn draw_pattern(renderer: &mut Renderer, pattern: InputPattern, x: i32, y: i32, font: &Font) {
+    let text = match pattern {
+        InputPattern::Hold { .. } => "hold",
+        InputPattern::Autofire { .. } => "auto",
+    };
+    draw_text(renderer, text, f64::from(x) + 4.0, f64::from(y) + 13.0, font, 0xFF, 1.0);
+}
+
+pub fn draw_text(renderer: &mut Renderer, text: &str, mut x: f64, y: f64, font: &Font, colour: i32, alpha: f64) {
     for c in text.chars() {
         if let Some(character) = font.get(c as u8) {
             renderer.draw_sprite(
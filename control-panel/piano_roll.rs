@@ -0,0 +1,119 @@
+// A view of the replay recorded so far, with a row for each frame and a column for each of the panel's keys
+// and mouse buttons. A cell is filled in if its button is held on that frame, and clicking it asks the game
+// to change that and play the replay again from before the frame.
+
+use crate::{
+    font::Font,
+    panel::{draw_text, WINDOW_HEIGHT},
+};
+use gmio::render::Renderer;
+use shared::{
+    input::{self, Button},
+    message::Message,
+};
+
+const TOP: i32 = 70;
+const ROW_HEIGHT: i32 = 16;
+const FRAME_COLUMN_WIDTH: i32 = 60;
+const COLUMN_WIDTH: i32 = 24;
+
+/// How many frames are shown at once.
+pub const ROWS: usize = ((WINDOW_HEIGHT as i32 - TOP - ROW_HEIGHT) / ROW_HEIGHT) as usize;
+
+pub struct PianoRoll {
+    pub columns: Vec<Button>,
+
+    /// The first frame shown.
+    pub top: usize,
+
+    /// How many frames have been recorded.
+    frame_count: usize,
+
+    /// Whether each button is held on each frame from `top`, as last sent by the game.
+    rows: Vec<Vec<bool>>,
+}
+
+impl PianoRoll {
+    /// Makes a piano roll scrolled to the end of the replay.
+    pub fn new(columns: Vec<Button>, frame_count: usize) -> Self {
+        Self { columns, top: frame_count.saturating_sub(ROWS), frame_count, rows: Vec::new() }
+    }
+
+    /// The message asking the game for the frames which are shown.
+    pub fn request(&self) -> Message {
+        Message::GetFrames { from: self.top, count: ROWS, buttons: self.columns.clone() }
+    }
+
+    /// Takes the frames sent by the game, if they're still the ones which are shown.
+    pub fn set_frames(&mut self, from: usize, held: Vec<Vec<bool>>) {
+        if from == self.top {
+            self.rows = held;
+        }
+    }
+
+    /// Updates how many frames there are. If the last one was shown before, the view follows the end.
+    pub fn set_frame_count(&mut self, frame_count: usize) {
+        if self.top + ROWS >= self.frame_count || self.top >= frame_count {
+            self.top = frame_count.saturating_sub(ROWS);
+        }
+        self.frame_count = frame_count;
+    }
+
+    /// Scrolls by some number of frames, returning whether the frames shown changed.
+    pub fn scroll(&mut self, frames: isize) -> bool {
+        let top = if frames < 0 {
+            self.top.saturating_sub(-frames as usize)
+        } else {
+            self.top.saturating_add(frames as usize).min(self.frame_count.saturating_sub(ROWS))
+        };
+        let changed = top != self.top;
+        self.top = top;
+        changed
+    }
+
+    /// Finds the frame and column of the cell at a point, if there's a recorded frame there.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        if x < FRAME_COLUMN_WIDTH || y < TOP + ROW_HEIGHT {
+            return None
+        }
+        let column = ((x - FRAME_COLUMN_WIDTH) / COLUMN_WIDTH) as usize;
+        let row = ((y - TOP - ROW_HEIGHT) / ROW_HEIGHT) as usize;
+        Some((self.top + row, column)).filter(|(frame, _)| *frame < self.frame_count && column < self.columns.len())
+    }
+
+    /// Whether a cell's button is held on its frame, if that frame has been sent by the game.
+    pub fn held(&self, frame: usize, column: usize) -> Option<bool> {
+        self.rows.get(frame.checked_sub(self.top)?)?.get(column).copied()
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer, font: &Font, mouse_x: i32, mouse_y: i32) {
+        for (i, button) in self.columns.iter().enumerate() {
+            let name = match button {
+                Button::Key(key) => format!("{:?}", key),
+                Button::Mouse(input::MouseButton::Left) => "LMB".into(),
+                Button::Mouse(input::MouseButton::Middle) => "MMB".into(),
+                Button::Mouse(input::MouseButton::Right) => "RMB".into(),
+            };
+            let x = FRAME_COLUMN_WIDTH + COLUMN_WIDTH * i as i32;
+            let name = name.chars().take(3).collect::<String>();
+            draw_text(renderer, &name, f64::from(x), f64::from(TOP + 13), font, 0, 1.0);
+        }
+
+        let hovered = self.cell_at(mouse_x, mouse_y);
+        for (row, frame) in (self.top..self.frame_count.min(self.top + ROWS)).enumerate() {
+            let y = TOP + ROW_HEIGHT * (row as i32 + 1);
+            draw_text(renderer, &frame.to_string(), 4.0, f64::from(y + 13), font, 0x303030, 1.0);
+            for column in 0..self.columns.len() {
+                let x1 = f64::from(FRAME_COLUMN_WIDTH + COLUMN_WIDTH * column as i32);
+                let y1 = f64::from(y);
+                let (x2, y2) = (x1 + f64::from(COLUMN_WIDTH - 2), y1 + f64::from(ROW_HEIGHT - 2));
+                let alpha = if hovered == Some((frame, column)) { 1.0 } else { 0.6 };
+                match self.held(frame, column) {
+                    Some(true) => renderer.draw_rectangle(x1, y1, x2, y2, 0x303030, alpha),
+                    Some(false) => renderer.draw_rectangle_outline(x1, y1, x2, y2, 0x303030, alpha),
+                    None => renderer.draw_rectangle_outline(x1, y1, x2, y2, 0xA0A0A0, alpha),
+                }
+            }
+        }
+    }
+}
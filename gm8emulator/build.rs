@@ -40,6 +40,71 @@ fn main() -> Result<(), Box<dyn Error>> {
         fs::write(aa_macro_path, &aa_macro)?;
     }
 
+    // kernel function table for static analysis (see gml::lint)
+    // this scrapes kernel.rs for stubbed functions and the argument count each function expects
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/gml/kernel.rs");
+    let kernel = fs::read_to_string("src/gml/kernel.rs")?;
+    let mut kernel_fns: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in kernel.lines() {
+        if let Some(sig) = line.strip_prefix("    pub fn ") {
+            let name = sig.split(|c| c == '(' || c == '<').next().unwrap_or(sig);
+            kernel_fns.push((name.to_string(), Vec::new()));
+        } else if let Some((_, body)) = kernel_fns.last_mut() {
+            body.push(line);
+        }
+    }
+    let mut kernel_info = String::with_capacity(65536);
+    kernel_info += &format!("pub static KERNEL_INFO: [(&str, bool, Option<usize>); {}] = [\n", kernel_fns.len());
+    for (name, body) in &kernel_fns {
+        let implemented = !body.iter().any(|l| l.contains("unimplemented!(\"Called unimplemented kernel function"));
+        let mut arg_counts = body.iter().filter_map(|l| {
+            if let Some(count) = l.trim().strip_prefix("// Expected arg count: ") {
+                count.parse::<usize>().ok()
+            } else {
+                let list = &l[l.find("expect_args!(args, [")? + 20..];
+                let list = &list[..list.find(']')?];
+                Some(list.split(',').filter(|x| !x.trim().is_empty()).count())
+            }
+        });
+        let arg_count = match arg_counts.next() {
+            Some(n) if arg_counts.all(|m| m == n) && !body.iter().any(|l| l.contains("args.len()")) => Some(n),
+            _ => None,
+        };
+        kernel_info += &format!("    (\"{}\", {}, {:?}),\n", name, implemented, arg_count);
+    }
+    kernel_info += "];\n";
+    fs::write(&Path::new(&out).join("_kernel_info.rs"), &kernel_info)?;
+
+    // hash of everything that affects compiled GML or how it's serialized, so that cached code can be thrown out
+    // when any of it changes: the compiler, the types inside compiled code, and the gm8exe version (for CodeAction)
+    let mut compiler_hash: u64 = 0xCBF29CE484222325;
+    let mut hash_bytes = |bytes: &[u8]| {
+        for byte in bytes {
+            compiler_hash = (compiler_hash ^ u64::from(*byte)).wrapping_mul(0x100000001B3);
+        }
+    };
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    let manifest = fs::read_to_string("Cargo.toml")?;
+    let gm8exe = manifest.lines().find(|l| l.starts_with("gm8exe")).ok_or("gm8exe dependency not found")?;
+    hash_bytes(gm8exe.as_bytes());
+    for path in &[
+        "src/action.rs",
+        "src/gml.rs",
+        "src/gml/compiler.rs",
+        "src/gml/compiler/ast.rs",
+        "src/gml/compiler/lexer.rs",
+        "src/gml/compiler/mappings.rs",
+        "src/gml/compiler/token.rs",
+        "src/gml/runtime.rs",
+        "src/gml/value.rs",
+        "src/math.rs",
+    ] {
+        println!("cargo:rerun-if-changed={}", path);
+        hash_bytes(&fs::read(path)?);
+    }
+    println!("cargo:rustc-env=GM8EMU_COMPILER_HASH={:016x}", compiler_hash);
+
     // opengl bindings
     let mut bindings = File::create(&Path::new(&out).join("gl_bindings.rs"))?;
     Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, &OPENGL_EXTENSIONS)
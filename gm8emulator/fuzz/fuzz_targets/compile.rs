@@ -0,0 +1,11 @@
+#![no_main]
+use gm8emulator::gml::Compiler;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        let mut compiler = Compiler::new();
+        let _ = compiler.compile(source);
+        let _ = compiler.compile_expression(source);
+    }
+});
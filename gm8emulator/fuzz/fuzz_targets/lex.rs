@@ -0,0 +1,9 @@
+#![no_main]
+use gm8emulator::gml::compiler::lexer::Lexer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        Lexer::new(source).for_each(drop);
+    }
+});
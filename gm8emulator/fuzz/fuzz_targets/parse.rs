@@ -0,0 +1,10 @@
+#![no_main]
+use gm8emulator::gml::compiler::ast::AST;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        let _ = AST::new(source);
+        let _ = AST::expression(source);
+    }
+});
@@ -0,0 +1,16 @@
+// Alarm 0: fire a ring of bullets
+var i, b;
+repeat (3) {
+    for (i = 0; i < 360; i += 360 / 16) {
+        b = instance_create(x, y, obj_bullet);
+        b.direction = i + shift;
+        b.speed = 4;
+        with (b) {
+            image_angle = direction;
+            owner = other.id;
+        }
+    }
+    shift += 7.5;
+}
+with (obj_bullet) if (x < -16 || x > room_width + 16 || y < -16 || y > room_height + 16) instance_destroy();
+alarm[0] = 45;
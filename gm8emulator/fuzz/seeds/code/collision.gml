@@ -0,0 +1,12 @@
+// Collision with obj_block
+if (vspeed > 0) {
+    move_contact_solid(270, vspeed);
+    vspeed = 0;
+} else if vspeed < 0 {
+    move_contact_solid(90, -vspeed)
+    vspeed = 0
+}
+if (place_meeting(x + hspeed, y, other.id)) {
+    while (!place_meeting(x + sign(hspeed), y, other.id)) x += sign(hspeed);
+    hspeed = 0;
+}
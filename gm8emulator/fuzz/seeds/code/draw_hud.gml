@@ -0,0 +1,10 @@
+draw_set_font(fnt_hud);
+draw_set_color(c_white);
+draw_set_halign(fa_left);
+draw_text(view_xview[0] + 8, view_yview[0] + 8, "Deaths: " + string(global.deaths));
+draw_text(view_xview[0] + 8, view_yview[0] + 24, "Time: " + string(global.time div 3600) + ":"
+    + string_repeat("0", 2 - string_length(string((global.time div 60) mod 60))) + string((global.time div 60) mod 60));
+for (i = 0; i < global.lives; i += 1) {
+    draw_sprite(spr_life, 0, view_xview[0] + 8 + i * 18, view_yview[0] + 44)
+}
+draw_set_alpha(1);
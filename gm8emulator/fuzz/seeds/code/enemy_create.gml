@@ -0,0 +1,11 @@
+globalvar enemy_count;
+enemy_count += 1;
+hp = 3 + irandom(2);
+alarm[0] = 30 + irandom_range(-10, 10);
+target = instance_nearest(x, y, obj_player);
+direction = point_direction(x, y, target.x, target.y);
+speed = choose(1, 1.5, 2);
+friction = .05;
+image_speed = 1/3;
+state = "idle";
+path_start(pth_patrol, 2, 1, 0);
@@ -0,0 +1,22 @@
+// Build the level from a string
+var w, h, xx, yy, c;
+w = 25; h = 19;
+map = ds_grid_create(w, h);
+ds_grid_clear(map, 0);
+for (yy = 0; yy < h; yy += 1) {
+    for (xx = 0; xx < w; xx += 1) {
+        c = string_char_at(argument0, yy * w + xx + 1);
+        if (c == "#") {
+            ds_grid_set(map, xx, yy, 1);
+            instance_create(xx * 32, yy * 32, obj_block);
+        } else if (c == "S") {
+            tiles[xx, yy] = 2;
+            instance_create(xx * 32, yy * 32, obj_spike);
+        }
+    }
+}
+do {
+    xx = irandom(w - 1); yy = irandom(h - 1);
+} until (ds_grid_get(map, xx, yy) == 0)
+obj_player.x = xx * 32 + 16;
+obj_player.y = yy * 32 + 16;
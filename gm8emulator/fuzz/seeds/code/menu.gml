@@ -0,0 +1,21 @@
+switch (keyboard_key) {
+    case vk_up:
+        selected -= 1;
+        if selected < 0 then selected = options - 1
+        sound_play(snd_move);
+        break;
+    case vk_down:
+        selected = (selected + 1) mod options;
+        sound_play(snd_move);
+        break;
+    case vk_enter:
+    case ord("Z"):
+        switch (selected) {
+            case 0: room_goto(rm_stage1); break;
+            case 1: room_goto(rm_options); break;
+            case 2: game_end(); break;
+        }
+        break;
+    default:
+        exit;
+}
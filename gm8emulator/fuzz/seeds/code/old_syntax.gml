@@ -0,0 +1,12 @@
+{
+  if global.paused = 1 then exit
+  begin
+    a := 5;; b = $FF and not c or d xor e
+    if (a <> b) { x = x + 1 } else y = y - 1
+    while a > 0 do a -= 1
+    self.image_index = other.image_index
+    global.score[0,1] = 100 << 2 >> 1 | 3 & ~4 ^ 5
+    s = 'single' + "double"
+    n = 1.2.3 + .5 + 5.
+  end
+}
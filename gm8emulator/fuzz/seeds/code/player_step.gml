@@ -0,0 +1,28 @@
+// Player step event
+var onground;
+onground = place_meeting(x, y + 1, obj_block);
+
+if (keyboard_check(vk_left)) {
+    hspeed = -3;
+    image_xscale = -1;
+} else if (keyboard_check(vk_right)) {
+    hspeed = 3;
+    image_xscale = 1;
+} else {
+    hspeed = 0;
+}
+
+if (onground) {
+    vspeed = 0;
+    djump = 1;
+    if (keyboard_check_pressed(vk_shift)) vspeed = -8.5;
+} else {
+    vspeed += 0.4;
+    if (keyboard_check_pressed(vk_shift) && djump) {
+        vspeed = -7;
+        djump = 0;
+    }
+    if (vspeed > 9) vspeed = 9;
+}
+
+if (keyboard_check_released(vk_shift) && vspeed < 0) vspeed *= 0.45;
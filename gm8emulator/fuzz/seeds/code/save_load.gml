@@ -0,0 +1,18 @@
+// saveGame(slot)
+var f, i;
+ini_open("save" + string(argument0) + ".ini");
+ini_write_real("save", "room", room);
+ini_write_real("save", "x", obj_player.x);
+ini_write_real("save", "y", obj_player.y);
+ini_write_real("save", "deaths", global.deaths);
+ini_write_string("save", "name", global.name);
+for (i = 0; i < 8; i += 1) {
+    ini_write_real("items", "item" + string(i), global.items[i]);
+}
+ini_close();
+
+f = file_text_open_write("backup.txt");
+file_text_write_string(f, string(global.deaths) + "," + string(global.time));
+file_text_writeln(f);
+file_text_close(f);
+return 1;
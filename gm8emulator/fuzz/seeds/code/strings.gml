@@ -0,0 +1,14 @@
+var str, out, i, ch;
+str = string_lower(argument0);
+out = "";
+for (i = 1; i <= string_length(str); i += 1) {
+    ch = string_char_at(str, i);
+    if (string_pos(ch, "aeiou") > 0) continue;
+    if (ord(ch) < ord("a") || ord(ch) > ord("z")) {
+        out += "_";
+    } else {
+        out += chr(ord(ch) - 32);
+    }
+}
+show_debug_message(string_format(string_length(out), 4, 0) + ": " + string_replace_all(out, "__", "_"));
+return out;
@@ -0,0 +1 @@
+$FF & ~$0F | 1 << 4 ^ 256 >> 2
\ No newline at end of file
@@ -0,0 +1 @@
+ds_list_find_value(ds_list_create(), 0)
\ No newline at end of file
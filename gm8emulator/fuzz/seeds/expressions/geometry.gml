@@ -0,0 +1 @@
+point_distance(0, 0, 3, 4) + lengthdir_x(10, 45) * lengthdir_y(10, 135)
\ No newline at end of file
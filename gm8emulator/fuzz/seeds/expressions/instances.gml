@@ -0,0 +1 @@
+instance_exists(instance_create(0, 0, obj_child)) + instance_number(obj_parent)
\ No newline at end of file
@@ -0,0 +1 @@
+(5 div 2) mod 3 == 2 && !(1 > 2) || 3 ^^ 0
\ No newline at end of file
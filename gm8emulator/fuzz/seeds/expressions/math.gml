@@ -0,0 +1 @@
+max(min(3, 1, 2), median(5, 1, 4), round(2.5), floor(-0.5), frac(1.75))
\ No newline at end of file
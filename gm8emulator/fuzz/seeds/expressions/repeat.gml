@@ -0,0 +1 @@
+3 * "ab" + string_repeat("cd", 2) + string_format(1.23456, 8, 3)
\ No newline at end of file
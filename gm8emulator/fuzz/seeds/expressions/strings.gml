@@ -0,0 +1 @@
+string_upper(string_copy("hello world", 1, 5)) + string(pi) + chr(65)
\ No newline at end of file
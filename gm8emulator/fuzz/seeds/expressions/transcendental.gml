@@ -0,0 +1 @@
+arctan2(1, 1) + arccos(0.5) + sqrt(2) + power(2, 0.5) + ln(e) + log10(1000)
\ No newline at end of file
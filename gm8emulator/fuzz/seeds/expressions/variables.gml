@@ -0,0 +1 @@
+global.x + obj_parent.y + self.z + other.w[1, 2] + a[3]
\ No newline at end of file
@@ -22,6 +22,11 @@ pub mod kind {
     pub const VARIABLE: u32 = 6;
     pub const CODE: u32 = 7;
 }
+
+/// The library and action ids of GM8's "execute code" action
+pub const CODE_LIB_ID: u32 = 1;
+pub const CODE_ACTION_ID: u32 = 603;
+
 pub mod execution_type {
     pub const NONE: u32 = 0;
     pub const FUNCTION: u32 = 1;
@@ -29,11 +34,17 @@ pub mod execution_type {
 }
 
 /// A drag-n-drop action.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Action {
     /// The original index of this action in its list, starting at 0
     pub index: usize,
 
+    /// The ID of the action library this action came from.
+    pub lib_id: u32,
+
+    /// The ID of this action within its library.
+    pub action_id: u32,
+
     /// The target ID. May be self (-1) or other (-2) or an object or instance id.
     /// A value of None means applies_to_something was false.
     pub target: Option<i32>,
@@ -50,11 +61,11 @@ pub struct Action {
 
 /// Abstraction for a tree of Actions
 /// Note that Vec is necessary here due to functions such as object_event_add and object_event_clear
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Tree(Vec<Action>);
 
 /// Body of an action, depending on the action kind.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Body {
     Normal {
         /// The arguments to be passed to the function or code body
@@ -76,7 +87,7 @@ pub enum Body {
     Exit,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum GmlBody {
     Function(gml::Function),
     Code(Rc<[Instruction]>),
@@ -152,6 +163,8 @@ impl Tree {
                             {
                                 output.push(Action {
                                     index: i,
+                                    lib_id: action.lib_id,
+                                    action_id: action.id,
                                     target: if action.applies_to_something { Some(action.applies_to) } else { None },
                                     relative: action.is_relative,
                                     invert_condition: action.invert_condition,
@@ -176,6 +189,8 @@ impl Tree {
                             // The action's code is provided by its fn_code, so compile that.
                             output.push(Action {
                                 index: i,
+                                lib_id: action.lib_id,
+                                action_id: action.id,
                                 target: if action.applies_to_something { Some(action.applies_to) } else { None },
                                 relative: action.is_relative,
                                 invert_condition: action.invert_condition,
@@ -201,6 +216,8 @@ impl Tree {
                 kind::EXIT => {
                     output.push(Action {
                         index: i,
+                        lib_id: action.lib_id,
+                        action_id: action.id,
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
@@ -213,6 +230,8 @@ impl Tree {
                     Self::from_iter(iter, compiler, true, &mut body)?;
                     output.push(Action {
                         index: i,
+                        lib_id: action.lib_id,
+                        action_id: action.id,
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
@@ -229,6 +248,8 @@ impl Tree {
                         + &action.param_strings[1];
                     output.push(Action {
                         index: i,
+                        lib_id: action.lib_id,
+                        action_id: action.id,
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
@@ -243,6 +264,8 @@ impl Tree {
                 kind::CODE => {
                     output.push(Action {
                         index: i,
+                        lib_id: action.lib_id,
+                        action_id: action.id,
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
@@ -285,6 +308,11 @@ impl Tree {
             .into_boxed_slice())
     }
 
+    /// The top-level actions in this tree, in order.
+    pub fn actions(&self) -> &[Action] {
+        &self.0
+    }
+
     pub fn new_from_code(code: Rc<[Instruction]>) -> Rc<RefCell<Self>> {
         let mut tree = Self(Vec::new());
         tree.push_code(code);
@@ -294,6 +322,8 @@ impl Tree {
     pub fn push_code(&mut self, code: Rc<[Instruction]>) {
         self.0.push(Action {
             index: self.0.len(),
+            lib_id: CODE_LIB_ID,
+            action_id: CODE_ACTION_ID,
             target: None,
             relative: false,
             invert_condition: false,
@@ -446,3 +476,62 @@ impl Game {
         Ok(ReturnType::Continue)
     }
 }
+
+/// The layout actions had in savestates from before they stored their library and action ids.
+/// Those ids aren't known for actions loaded this way, so they're left as 0.
+pub mod legacy {
+    use super::GmlBody;
+    use crate::gml::runtime::Node;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Action {
+        pub index: usize,
+        pub target: Option<i32>,
+        pub relative: bool,
+        pub invert_condition: bool,
+        pub body: Body,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Tree(pub Vec<Action>);
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Body {
+        Normal { args: Box<[Node]>, body: GmlBody, if_else: Option<(Box<[Action]>, Box<[Action]>)> },
+        Repeat { count: Node, body: Box<[Action]> },
+        Exit,
+    }
+
+    fn convert_all(actions: Box<[Action]>) -> Box<[super::Action]> {
+        actions.into_vec().into_iter().map(super::Action::from).collect()
+    }
+
+    impl From<Action> for super::Action {
+        fn from(action: Action) -> Self {
+            Self {
+                index: action.index,
+                lib_id: 0,
+                action_id: 0,
+                target: action.target,
+                relative: action.relative,
+                invert_condition: action.invert_condition,
+                body: match action.body {
+                    Body::Normal { args, body, if_else } => super::Body::Normal {
+                        args,
+                        body,
+                        if_else: if_else.map(|(if_body, else_body)| (convert_all(if_body), convert_all(else_body))),
+                    },
+                    Body::Repeat { count, body } => super::Body::Repeat { count, body: convert_all(body) },
+                    Body::Exit => super::Body::Exit,
+                },
+            }
+        }
+    }
+
+    impl From<Tree> for super::Tree {
+        fn from(tree: Tree) -> Self {
+            Self(tree.0.into_iter().map(super::Action::from).collect())
+        }
+    }
+}
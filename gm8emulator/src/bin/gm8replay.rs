@@ -0,0 +1,178 @@
+// Edits replay files without running the game, so that they don't have to be patched by hand.
+
+use gm8emulator::game::Replay;
+use std::{
+    env,
+    error::Error,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+
+const COMMANDS: &str = "
+Commands:
+    print [FROM [TO]]           prints a summary of each frame, in the same form as a .gmtxt file
+    truncate FRAMES             keeps only the first FRAMES frames
+    insert AT COUNT [FPS]       inserts COUNT blank frames before frame AT, with the fps of the frame before
+    delete AT COUNT             deletes COUNT frames starting at frame AT
+    splice AT OTHER [FROM [TO]] inserts a copy of frames FROM to TO of the replay OTHER before frame AT
+    seed SEED                   changes the starting RNG seed
+    time NANOS                  changes the starting time
+    shift OFFSET                moves every frame's inputs OFFSET frames later, or earlier if it's negative
+
+Frames are numbered from 0. Negative numbers have to come after --, so they aren't taken for options.
+";
+
+fn help(argv0: &str, opts: getopts::Options) {
+    print!(
+        "{}{}",
+        opts.usage(&format!("Usage: {} FILE COMMAND [ARGS...] [options]", match Path::new(argv0).file_name() {
+            Some(file) => file.to_str().unwrap_or(argv0),
+            None => argv0,
+        })),
+        COMMANDS,
+    );
+}
+
+fn main() {
+    process::exit(xmain());
+}
+
+fn xmain() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    let process = args[0].clone();
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "prints this help message");
+    opts.optopt("o", "output", "file to write the edited replay to, instead of overwriting FILE", "FILE");
+    opts.optopt("x", "exe-hash", "hash of the game's exe to save with the replay, if it doesn't have one", "HASH");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE
+        },
+    };
+    if matches.free.len() < 2 || matches.opt_present("h") {
+        help(&process, opts);
+        return EXIT_SUCCESS
+    }
+
+    let input = PathBuf::from(&matches.free[0]);
+    let (mut replay, file_hash) = match Replay::read_file(&input) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("failed to load {}: {}", input.display(), e);
+            return EXIT_FAILURE
+        },
+    };
+    let exe_hash = match matches.opt_str("x").map(|x| u64::from_str_radix(&x, 16)).transpose() {
+        Ok(hash) => hash.or(file_hash),
+        Err(e) => {
+            eprintln!("invalid exe hash provided: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+
+    let command = matches.free[1].as_str();
+    let args = &matches.free[2..];
+    if command == "print" {
+        return match print(&replay, args) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                EXIT_FAILURE
+            },
+        }
+    }
+    if let Err(e) = edit(&mut replay, command, args, exe_hash) {
+        eprintln!("{}", e);
+        return EXIT_FAILURE
+    }
+
+    let output = matches.opt_str("o").map(PathBuf::from).unwrap_or(input);
+    let exe_hash = match exe_hash {
+        Some(hash) => hash,
+        None => {
+            eprintln!("the replay doesn't say which game it's for, so an exe hash has to be provided with -x");
+            return EXIT_FAILURE
+        },
+    };
+    if let Err(e) = replay.save_to_file(&output, exe_hash) {
+        eprintln!("failed to save {}: {}", output.display(), e);
+        return EXIT_FAILURE
+    }
+    EXIT_SUCCESS
+}
+
+fn print(replay: &Replay, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let from = arg(args, 0, "FROM").unwrap_or(Ok(0))?;
+    let to = arg(args, 1, "TO").unwrap_or(Ok(replay.frame_count()))?;
+    println!("start_time {}", replay.start_time);
+    println!("start_seed {}", replay.start_seed);
+    println!("{} frames", replay.frame_count());
+    if !replay.tampered_frames().is_empty() {
+        println!("tampered with before frames {:?}", replay.tampered_frames());
+    }
+    for i in from..to.min(replay.frame_count()) {
+        println!("{}: {}", i, replay.frame_text(i).unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn edit(replay: &mut Replay, command: &str, args: &[String], exe_hash: Option<u64>) -> Result<(), Box<dyn Error>> {
+    match command {
+        "truncate" => replay.truncate(required(args, 0, "FRAMES")?),
+        "insert" => {
+            let at: usize = required(args, 0, "AT")?;
+            let fps = match arg(args, 2, "FPS") {
+                Some(fps) => fps?,
+                None => at
+                    .checked_sub(1)
+                    .and_then(|i| replay.get_frame(i))
+                    .or_else(|| replay.get_frame(at))
+                    .map(|frame| frame.fps)
+                    .ok_or("there are no frames to take the fps from, so FPS has to be given")?,
+            };
+            replay.insert_blank_frames(at, required(args, 1, "COUNT")?, fps);
+        },
+        "delete" => replay.delete_frames(required(args, 0, "AT")?, required(args, 1, "COUNT")?),
+        "splice" => {
+            let at = required(args, 0, "AT")?;
+            let path = PathBuf::from(args.get(1).ok_or("missing OTHER")?);
+            let (other, other_hash) = Replay::read_file(&path)
+                .map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+            if let (Some(hash), Some(other_hash)) = (exe_hash, other_hash) {
+                if hash != other_hash {
+                    return Err(format!("{} is for a different game", path.display()).into())
+                }
+            }
+            let from = arg(args, 2, "FROM").unwrap_or(Ok(0))?;
+            let to = arg(args, 3, "TO").unwrap_or(Ok(other.frame_count()))?;
+            replay.splice(at, &other, from..to);
+        },
+        "seed" => {
+            replay.start_seed = required(args, 0, "SEED")?;
+            replay.clear_hashes(0);
+        },
+        "time" => {
+            replay.start_time = required(args, 0, "NANOS")?;
+            replay.clear_hashes(0);
+        },
+        "shift" => replay.shift_inputs(required(args, 0, "OFFSET")?),
+        _ => return Err(format!("unknown command {}", command).into()),
+    }
+    Ok(())
+}
+
+fn required<T: FromStr>(args: &[String], index: usize, name: &str) -> Result<T, Box<dyn Error>> {
+    arg(args, index, name).unwrap_or_else(|| Err(format!("missing {}", name).into()))
+}
+
+// Parses an argument to a command, which might not have been given
+fn arg<T: FromStr>(args: &[String], index: usize, name: &str) -> Option<Result<T, Box<dyn Error>>> {
+    args.get(index).map(|arg| arg.parse().map_err(|_| format!("invalid {}: {}", name, arg).into()))
+}
@@ -0,0 +1,266 @@
+// Searches for inputs which make something happen, by running several headless emulators at once over the
+// control protocol. Each of them loads the same savestate from a project, tries its share of a list of input
+// sequences from there, and reports which of them made a GML expression true.
+
+use shared::message::{Information, InputSequence, Message, MessageStream, SearchOutcome};
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Read},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{self, Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+
+const SEQUENCES: &str = "
+SEQUENCES is a file with one input sequence per line, or stdin if it isn't given. Each sequence is the frames
+to play, separated by spaces. A frame is the names of the buttons held on it joined with +, or . if nothing is,
+and can be followed by *N to repeat it N times. For example: Right*3 Right+Z .*10 LMB
+Blank lines and lines starting with # are skipped.
+";
+
+fn help(argv0: &str, opts: getopts::Options) {
+    let name = match Path::new(argv0).file_name() {
+        Some(file) => file.to_str().unwrap_or(argv0),
+        None => argv0,
+    };
+    print!(
+        "{}{}",
+        opts.usage(&format!("Usage: {} GAME [SEQUENCES] -n NAME -s FILE -e EXPR [options]", name)),
+        SEQUENCES
+    );
+}
+
+fn main() {
+    process::exit(xmain());
+}
+
+fn xmain() -> i32 {
+    let args: Vec<String> = env::args().collect();
+    let process = args[0].clone();
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "prints this help message");
+    opts.optflag("a", "all", "prints what happened with every sequence, not just the ones which worked");
+    opts.optopt("n", "project-name", "name of the TAS project the savestate is in", "NAME");
+    opts.optopt("s", "savestate", "savestate in the project to start every sequence from", "FILE");
+    opts.optopt("e", "predicate", "GML expression which a sequence has to make true after one of its frames", "EXPR");
+    opts.optopt("j", "jobs", "how many emulators to run at once (default 4)", "COUNT");
+    opts.optopt("p", "port", "port to listen for the emulators on (default 15570)", "PORT");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE
+        },
+    };
+    if matches.free.is_empty() || matches.opt_present("h") {
+        help(&process, opts);
+        return EXIT_SUCCESS
+    }
+    if matches.free.len() > 2 {
+        eprintln!("unexpected input {}", matches.free[2]);
+        return EXIT_FAILURE
+    }
+
+    let (project, savestate, predicate) = match (matches.opt_str("n"), matches.opt_str("s"), matches.opt_str("e")) {
+        (Some(project), Some(savestate), Some(predicate)) => (project, savestate, predicate),
+        _ => {
+            eprintln!("a project (-n), a savestate (-s) and a predicate (-e) are all needed");
+            return EXIT_FAILURE
+        },
+    };
+    let jobs = match matches.opt_str("j").map(|x| x.parse::<usize>()).transpose() {
+        Ok(jobs) => jobs.unwrap_or(4).max(1),
+        Err(e) => {
+            eprintln!("invalid number of jobs provided: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+    let port = match matches.opt_str("p").map(|x| x.parse::<u16>()).transpose() {
+        Ok(port) => port.unwrap_or(15570),
+        Err(e) => {
+            eprintln!("invalid port provided: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+
+    // The emulators look for the project relative to where they're run from, which is here
+    if !Path::new("projects").join(&project).join(&savestate).exists() {
+        eprintln!("there's no savestate {} in project {}", savestate, project);
+        return EXIT_FAILURE
+    }
+
+    let sequences = match read_sequences(matches.free.get(1).map(String::as_str)) {
+        Ok(sequences) if sequences.is_empty() => {
+            eprintln!("no sequences to try");
+            return EXIT_FAILURE
+        },
+        Ok(sequences) => sequences,
+        Err(e) => {
+            eprintln!("failed to read sequences: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+
+    let outcomes = match search(&matches.free[0], &project, &savestate, &predicate, &sequences, jobs, port) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            eprintln!("search failed: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+
+    let mut satisfied = 0;
+    for (sequence, outcome) in sequences.iter().zip(outcomes.iter()) {
+        match outcome {
+            SearchOutcome::Satisfied { frames } => {
+                satisfied += 1;
+                println!("satisfied after {} frame(s): {}", frames, sequence);
+            },
+            SearchOutcome::Unsatisfied if matches.opt_present("a") => println!("unsatisfied: {}", sequence),
+            SearchOutcome::Unsatisfied => (),
+            SearchOutcome::Failed { error } => eprintln!("failed {}: {}", error, sequence),
+        }
+    }
+    println!("{} of {} sequence(s) satisfied the predicate", satisfied, sequences.len());
+    EXIT_SUCCESS
+}
+
+// Reads and parses the sequences, one per line, from a file or from stdin
+fn read_sequences(path: Option<&str>) -> Result<Vec<InputSequence>, Box<dyn Error>> {
+    let text = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        },
+    };
+    let mut sequences = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        sequences.push(line.parse().map_err(|e| format!("line {}: {}", i + 1, e))?);
+    }
+    Ok(sequences)
+}
+
+// Starts the emulators, gives each of them a share of the sequences, and puts their outcomes back in order
+fn search(
+    game: &str,
+    project: &str,
+    savestate: &str,
+    predicate: &str,
+    sequences: &[InputSequence],
+    jobs: usize,
+    port: u16,
+) -> Result<Vec<SearchOutcome>, Box<dyn Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+
+    let chunk_size = (sequences.len() + jobs - 1) / jobs;
+    let chunks = sequences.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect::<Vec<_>>();
+    let emulator = env::current_exe()?.with_file_name(format!("gm8emulator{}", env::consts::EXE_SUFFIX));
+    let mut children = Vec::with_capacity(chunks.len());
+    for _ in 0..chunks.len() {
+        let child = Command::new(&emulator)
+            .arg(game)
+            .args(&["-n", project, "-p", &port.to_string(), "--headless"])
+            .stdout(Stdio::null())
+            .spawn();
+        match child {
+            Ok(child) => children.push(child),
+            Err(e) => {
+                kill_all(&mut children);
+                return Err(format!("couldn't start {}: {}", emulator.display(), e).into())
+            },
+        }
+    }
+
+    // Which emulator connects first doesn't matter, since they all do the same thing
+    let mut streams = Vec::with_capacity(children.len());
+    while streams.len() < children.len() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                streams.push(stream);
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if let Some(status) = children.iter_mut().find_map(|child| child.try_wait().ok().flatten()) {
+                    kill_all(&mut children);
+                    return Err(format!("an emulator exited before connecting ({})", status).into())
+                }
+                thread::sleep(Duration::from_millis(50));
+            },
+            Err(e) => {
+                kill_all(&mut children);
+                return Err(e.into())
+            },
+        }
+    }
+
+    let workers = streams
+        .into_iter()
+        .zip(chunks)
+        .map(|(stream, chunk)| {
+            let savestate = savestate.to_string();
+            let predicate = predicate.to_string();
+            thread::spawn(move || run_emulator(stream, savestate, predicate, chunk))
+        })
+        .collect::<Vec<_>>();
+    let mut outcomes = Vec::with_capacity(sequences.len());
+    let mut error = None;
+    for worker in workers {
+        match worker.join().unwrap_or_else(|_| Err("an emulator's thread panicked".into())) {
+            Ok(chunk_outcomes) => outcomes.extend(chunk_outcomes),
+            Err(e) => error = error.or(Some(e)),
+        }
+    }
+
+    // The emulators close by themselves once their connections are dropped, which happened in the threads
+    for child in children.iter_mut() {
+        child.wait()?;
+    }
+    match error {
+        Some(e) => Err(e.into()),
+        None => Ok(outcomes),
+    }
+}
+
+// Has one emulator load the savestate and try some sequences from it
+fn run_emulator(
+    mut stream: TcpStream,
+    savestate: String,
+    predicate: String,
+    sequences: Vec<InputSequence>,
+) -> Result<Vec<SearchOutcome>, String> {
+    let mut read_buffer = Vec::new();
+    let filename = savestate.clone();
+    stream
+        .send_message(&Message::Hello { keys_requested: Vec::new(), mouse_buttons_requested: Vec::new(), filename })
+        .map_err(|e| e.to_string())?;
+    stream.send_message(&Message::Search { filename: savestate, predicate, sequences }).map_err(|e| e.to_string())?;
+    loop {
+        match stream.receive_message::<Information>(&mut read_buffer).map_err(|e| e.to_string())? {
+            Some(Some(Information::SearchResult { result })) => break result,
+            Some(Some(Information::General { message })) => eprintln!("{}", message),
+            Some(_) => (),
+            None => break Err("an emulator disconnected before finishing".into()),
+        }
+    }
+}
+
+fn kill_all(children: &mut [Child]) {
+    for child in children.iter_mut() {
+        let _ = child.kill();
+    }
+}
@@ -1,19 +1,40 @@
 pub mod background;
+pub mod compilecache;
+#[cfg(test)]
+mod conformance;
+pub mod console;
 pub mod draw;
 pub mod events;
 pub mod external;
+pub mod fileformat;
+pub mod inputdisplay;
 pub mod movement;
+pub mod overlay;
 pub mod particle;
 pub mod replay;
+pub mod rewind;
+pub mod sandbox;
 pub mod savestate;
+pub mod search;
+pub mod speed;
+pub mod statehash;
 pub mod string;
 pub mod surface;
+pub mod verify;
 pub mod view;
+pub mod watchpoint;
 
 pub use background::Background;
+pub use compilecache::{CodeSource, CompileCache};
+pub use console::StdinConsole;
+pub use inputdisplay::InputDisplay;
 pub use replay::Replay;
+pub use rewind::Rewind;
 pub use savestate::SaveState;
+pub use search::{Predicate, SeedSearch};
+pub use speed::Speed;
 pub use view::View;
+pub use watchpoint::{WatchList, Watchpoints};
 
 use crate::{
     action::Tree,
@@ -48,20 +69,18 @@ use gmio::{
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use shared::{
-    input::MouseButton,
+    input::{Button, Key, MouseButton},
     message::{self, Message, MessageStream},
     types::{Colour, ID},
 };
 use std::{
     cell::RefCell,
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
-    fs::File,
-    io::{BufReader, Write},
+    io,
     net::{SocketAddr, TcpStream},
     path::PathBuf,
     rc::Rc,
-    thread,
-    time::{Duration, Instant},
+    time::Instant,
 };
 use string::RCStr;
 
@@ -138,6 +157,7 @@ pub struct Game {
     pub health_capt_d: bool,   // display in caption?
 
     pub game_id: i32,
+    pub exe_hash: u64, // for checking that savestates and replays are for this game
     pub program_directory: RCStr,
     pub gm_version: Version,
     pub open_ini: Option<(ini::Ini, RCStr)>, // keep the filename for writing
@@ -149,6 +169,12 @@ pub struct Game {
 
     pub play_type: PlayType,
     pub stored_events: VecDeque<replay::Event>,
+    pub watchpoints: Watchpoints,
+    pub speed: Speed,
+    // draws collision masks, bboxes, paths and views over each frame, without the game seeing them
+    pub show_overlay: bool,
+    // frame number, room and held buttons shown over the game during replay playback, if turned on
+    pub input_display: Option<InputDisplay>,
 
     // winit windowing
     pub window: Window,
@@ -181,7 +207,7 @@ pub enum SceneChange {
     End,      // End the game
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Assets {
     pub backgrounds: Vec<Option<Box<asset::Background>>>,
     pub fonts: Vec<Option<Box<Font>>>,
@@ -199,7 +225,10 @@ impl Game {
     pub fn launch(
         assets: gm8exe::GameAssets,
         file_path: PathBuf,
+        exe_hash: u64,
         spoofed_time_nanos: Option<u128>,
+        compile_cache_path: Option<PathBuf>,
+        headless: bool,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // Parse file path
         let mut file_path2 = file_path.clone();
@@ -223,6 +252,12 @@ impl Game {
             winapi::um::timeapi::timeBeginPeriod(1);
         }
 
+        // Set up a GML compiler, or load everything it would compile from the cache
+        let mut code_source = match compile_cache_path.as_ref().and_then(|p| CompileCache::load(p)) {
+            Some(cache) => CodeSource::from_cache(cache),
+            None => CodeSource::new(Self::create_compiler(&assets)),
+        };
+
         // Destructure assets
         let gm8exe::GameAssets {
             game_id,
@@ -264,55 +299,6 @@ impl Game {
         let room1_speed = room1.speed;
         let room1_colour = if room1.clear_screen { Some(room1.bg_colour.as_decimal().into()) } else { None };
 
-        // Set up a GML compiler
-        let mut compiler = Compiler::new();
-        compiler.reserve_scripts(scripts.iter().flatten().count());
-        compiler.reserve_constants(
-            backgrounds.iter().flatten().count()
-                + fonts.iter().flatten().count()
-                + objects.iter().flatten().count()
-                + paths.iter().flatten().count()
-                + rooms.iter().flatten().count()
-                + scripts.iter().flatten().count()
-                + sounds.iter().flatten().count()
-                + sprites.iter().flatten().count()
-                + timelines.iter().flatten().count()
-                + triggers.iter().flatten().count(),
-        );
-        compiler.reserve_user_constants(constants.len());
-
-        // Helper fn for registering asset names as constants
-        fn register_all<T>(compiler: &mut Compiler, assets: &[Option<T>], get_name: fn(&T) -> String) {
-            assets
-                .iter()
-                .enumerate()
-                .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
-                .for_each(|(i, x)| compiler.register_constant(get_name(x), i as f64))
-        }
-
-        // Register all asset names
-        // These are in order of asset precedence, please don't change the order
-        register_all(&mut compiler, &objects, |x| x.name.clone());
-        register_all(&mut compiler, &sprites, |x| x.name.clone());
-        register_all(&mut compiler, &sounds, |x| x.name.clone());
-        register_all(&mut compiler, &backgrounds, |x| x.name.clone());
-        register_all(&mut compiler, &paths, |x| x.name.clone());
-        register_all(&mut compiler, &fonts, |x| x.name.clone());
-        register_all(&mut compiler, &timelines, |x| x.name.clone());
-        register_all(&mut compiler, &scripts, |x| x.name.clone());
-        register_all(&mut compiler, &rooms, |x| x.name.clone());
-        register_all(&mut compiler, &triggers, |x| x.constant_name.clone());
-
-        // Register scripts
-        scripts
-            .iter()
-            .enumerate()
-            .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
-            .for_each(|(i, x)| compiler.register_script(x.name.clone(), i));
-
-        // Register user constants
-        constants.iter().enumerate().for_each(|(i, x)| compiler.register_user_constant(x.name.clone(), i));
-
         // Set up a Renderer
         let options = RendererOptions {
             size: (room1_width, room1_height),
@@ -325,8 +311,14 @@ impl Game {
 
         // TODO: specific flags here (make wb mutable)
 
-        let window = wb.build().expect("oh no");
-        let mut renderer = Renderer::new((), &options, &window, settings.clear_colour.into())?;
+        // Headless games have no window and draw nothing, for running replays on machines without a display
+        let (window, mut renderer) = if headless {
+            (wb.build_headless(), Renderer::new_headless(&options))
+        } else {
+            let window = wb.build().expect("oh no");
+            let renderer = Renderer::new((), &options, &window, settings.clear_colour.into())?;
+            (window, renderer)
+        };
 
         let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
 
@@ -476,7 +468,7 @@ impl Game {
                         for ((i, map), input) in events.iter_mut().enumerate().zip(b.events.iter()) {
                             map.reserve(input.len());
                             for (sub, actions) in input {
-                                map.insert(*sub, match Tree::from_list(actions, &mut compiler) {
+                                map.insert(*sub, match code_source.tree(actions) {
                                     Ok(t) => Rc::new(RefCell::new(t)),
                                     Err(e) => {
                                         return Err(format!(
@@ -563,7 +555,7 @@ impl Game {
                 t.map(|b| {
                     let mut moments: BTreeMap<i32, Rc<RefCell<Tree>>> = BTreeMap::new();
                     for (moment, actions) in b.moments.iter() {
-                        match Tree::from_list(actions, &mut compiler) {
+                        match code_source.tree(actions) {
                             Ok(t) => {
                                 moments.insert(*moment as i32, Rc::new(RefCell::new(t)));
                             },
@@ -582,7 +574,7 @@ impl Game {
             .into_iter()
             .map(|t| {
                 t.map(|b| {
-                    let compiled = match compiler.compile(&b.source) {
+                    let compiled = match code_source.code(&b.source) {
                         Ok(s) => s,
                         Err(e) => return Err(format!("Compiler error in script {}: {}", b.name, e)),
                     };
@@ -596,7 +588,7 @@ impl Game {
             .into_iter()
             .map(|t| {
                 t.map(|b| {
-                    let creation_code = match compiler.compile(&b.creation_code) {
+                    let creation_code = match code_source.code(&b.creation_code) {
                         Ok(c) => c,
                         Err(e) => return Err(format!("Compiler error in room {} creation code: {}", b.name, e)),
                     };
@@ -680,7 +672,7 @@ impl Game {
                                     y: i.y,
                                     object: i.object,
                                     id: i.id,
-                                    creation: match compiler.compile(&i.creation_code) {
+                                    creation: match code_source.code(&i.creation_code) {
                                         Ok(c) => c,
                                         Err(e) => {
                                             return Err(format!(
@@ -724,7 +716,7 @@ impl Game {
             .into_iter()
             .map(|t| {
                 t.map(|b| {
-                    let condition = match compiler.compile(&b.condition) {
+                    let condition = match code_source.code(&b.condition) {
                         Ok(s) => s,
                         Err(e) => return Err(format!("Compiler error in trigger {}: {}", b.name, e)),
                     };
@@ -734,6 +726,14 @@ impl Game {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
+        // Save compiled code for next time
+        let (compiler, compile_cache) = code_source.finish();
+        if let (Some(cache), Some(path)) = (compile_cache, &compile_cache_path) {
+            if let Err(e) = cache.save(path) {
+                println!("Failed to save compile cache: {}", e);
+            }
+        }
+
         // Make event holder lists
         let mut event_holders: [IndexMap<u32, Rc<RefCell<Vec<i32>>>>; 12] = Default::default();
         Self::fill_event_holders(&mut event_holders, &objects);
@@ -801,6 +801,7 @@ impl Game {
             health: Real::from(100.0),
             health_capt: "Health: ".to_string().into(),
             game_id: game_id as i32,
+            exe_hash,
             program_directory: program_directory.into(),
             gm_version,
             open_ini: None,
@@ -813,6 +814,10 @@ impl Game {
             window,
             play_type: PlayType::Normal,
             stored_events: VecDeque::new(),
+            watchpoints: Watchpoints::new(),
+            speed: Speed::new(),
+            show_overlay: false,
+            input_display: None,
 
             // load_room sets this
             unscaled_width: 0,
@@ -853,6 +858,74 @@ impl Game {
         Ok(game)
     }
 
+    /// Creates a GML compiler with all of the game's asset names, scripts and constants registered.
+    pub fn create_compiler(assets: &gm8exe::GameAssets) -> Compiler {
+        let gm8exe::GameAssets {
+            backgrounds,
+            constants,
+            fonts,
+            objects,
+            paths,
+            rooms,
+            scripts,
+            sounds,
+            sprites,
+            timelines,
+            triggers,
+            ..
+        } = assets;
+
+        let mut compiler = Compiler::new();
+        compiler.reserve_scripts(scripts.iter().flatten().count());
+        compiler.reserve_constants(
+            backgrounds.iter().flatten().count()
+                + fonts.iter().flatten().count()
+                + objects.iter().flatten().count()
+                + paths.iter().flatten().count()
+                + rooms.iter().flatten().count()
+                + scripts.iter().flatten().count()
+                + sounds.iter().flatten().count()
+                + sprites.iter().flatten().count()
+                + timelines.iter().flatten().count()
+                + triggers.iter().flatten().count(),
+        );
+        compiler.reserve_user_constants(constants.len());
+
+        // Helper fn for registering asset names as constants
+        fn register_all<T>(compiler: &mut Compiler, assets: &[Option<T>], get_name: fn(&T) -> String) {
+            assets
+                .iter()
+                .enumerate()
+                .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
+                .for_each(|(i, x)| compiler.register_constant(get_name(x), i as f64))
+        }
+
+        // Register all asset names
+        // These are in order of asset precedence, please don't change the order
+        register_all(&mut compiler, objects, |x| x.name.clone());
+        register_all(&mut compiler, sprites, |x| x.name.clone());
+        register_all(&mut compiler, sounds, |x| x.name.clone());
+        register_all(&mut compiler, backgrounds, |x| x.name.clone());
+        register_all(&mut compiler, paths, |x| x.name.clone());
+        register_all(&mut compiler, fonts, |x| x.name.clone());
+        register_all(&mut compiler, timelines, |x| x.name.clone());
+        register_all(&mut compiler, scripts, |x| x.name.clone());
+        register_all(&mut compiler, rooms, |x| x.name.clone());
+        register_all(&mut compiler, triggers, |x| x.constant_name.clone());
+
+        // Register scripts
+        scripts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
+            .for_each(|(i, x)| compiler.register_script(x.name.clone(), i));
+
+        // Register user constants
+        constants.iter().enumerate().for_each(|(i, x)| compiler.register_user_constant(x.name.clone(), i));
+
+        compiler
+    }
+
     pub fn refresh_event_holders(&mut self) {
         // It might be better to not redo the entire holder list from scratch?
 
@@ -1328,6 +1401,10 @@ impl Game {
                 self.input_manager.mouse_update_previous();
                 for event in self.window.process_events().copied() {
                     match event {
+                        Event::KeyboardDown(key) if self.speed.hotkey && key == speed::TURBO_KEY => {
+                            self.speed.turbo = !self.speed.turbo
+                        },
+                        Event::KeyboardUp(key) if self.speed.hotkey && key == speed::TURBO_KEY => (),
                         Event::KeyboardDown(key) => self.input_manager.key_press(key),
                         Event::KeyboardUp(key) => self.input_manager.key_release(key),
                         Event::MenuOption(_) => (),
@@ -1344,12 +1421,17 @@ impl Game {
         }
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn run(&mut self, console: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let console = if console { Some(StdinConsole::new()) } else { None };
         let mut time_now = Instant::now();
         loop {
             self.process_window_events();
 
             self.frame()?;
+            if let Some(console) = &console {
+                // nothing is being recorded or checked in normal play, so it doesn't matter if the state changed
+                console.run_pending(self);
+            }
             match self.scene_change {
                 Some(SceneChange::Room(id)) => self.load_room(id)?,
                 Some(SceneChange::Restart) => self.restart()?,
@@ -1362,23 +1444,17 @@ impl Game {
                 break Ok(self.run_game_end_events()?)
             }
 
-            // frame limiter
-            let diff = Instant::now().duration_since(time_now);
-            let duration = Duration::new(0, 1_000_000_000u32 / self.room_speed);
-            if let Some(t) = self.spoofed_time_nanos.as_mut() {
-                *t += duration.as_nanos();
-            }
-            if let Some(time) = duration.checked_sub(diff) {
-                thread::sleep(time);
-                time_now += duration;
-            } else {
-                time_now = Instant::now();
-            }
+            self.limit_frame_rate(&mut time_now);
         }
     }
 
     // Create a TAS for this game
-    pub fn record(&mut self, project_path: PathBuf, tcp_port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn record(
+        &mut self,
+        project_path: PathBuf,
+        tcp_port: u16,
+        mut rewind: Rewind,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         use gmio::window::Event;
 
         // Helper fn: Instance -> InstanceDetails
@@ -1419,11 +1495,44 @@ impl Game {
             }
         }
 
+        // Helper fn: sends the controller the state of the buttons and instance it asked about, and the watch list
+        fn send_update(
+            game: &mut Game,
+            stream: &mut TcpStream,
+            frame_count: usize,
+            keys_requested: Vec<Key>,
+            mouse_buttons_requested: Vec<MouseButton>,
+            instance_requested: Option<ID>,
+            watch_list: &WatchList,
+        ) -> io::Result<()> {
+            let instance = instance_requested.and_then(|x| game.instance_list.get_by_instid(x)).map(|x| {
+                let instance = game.instance_list.get(x);
+                instance.update_bbox(game.get_instance_mask_sprite(x));
+                instance_details(&game.assets, instance)
+            });
+            stream.send_message(&message::Information::Update {
+                keys_held: keys_requested
+                    .into_iter()
+                    .filter(|x| game.input_manager.key_check((*x as u8).into()))
+                    .collect(),
+                mouse_buttons_held: mouse_buttons_requested
+                    .into_iter()
+                    .filter(|x| game.input_manager.mouse_check(*x))
+                    .collect(),
+                mouse_location: game.input_manager.mouse_get_location(),
+                frame_count,
+                seed: game.rand.seed(),
+                instance,
+                watch_values: game.watch_list_values(watch_list),
+            })
+        }
+
         let mut stream = TcpStream::connect(&SocketAddr::from(([127, 0, 0, 1], tcp_port)))?;
         stream.set_nonblocking(true)?;
         let mut read_buffer: Vec<u8> = Vec::new();
 
         let mut replay = Replay::new(self.spoofed_time_nanos.unwrap_or(0), self.rand.seed());
+        let mut watch_list = WatchList::new();
 
         // Wait for a Hello, then send an update
         loop {
@@ -1437,29 +1546,23 @@ impl Game {
                         path.push(&filename);
                         if path.exists() {
                             println!("{} exists, loading workspace", filename);
-                            let state = bincode::deserialize_from::<_, SaveState>(BufReader::new(File::open(&path)?))?;
-                            replay = state.load_into(self);
+                            let state = SaveState::load_from_file(&path, self.exe_hash)?;
+                            replay = state.load_into(self)?;
                         } else {
                             println!("{} doesn't exist, creating workspace", filename);
-                            let bytes = bincode::serialize(&SaveState::from(self, replay.clone()))?;
-                            File::create(&path)?.write_all(&bytes)?;
+                            SaveState::from(self, replay.clone()).save_to_file(&path, self.exe_hash)?;
                         }
 
                         // Send an update
-                        stream.send_message(&message::Information::Update {
-                            keys_held: keys_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.key_check((*x as u8).into()))
-                                .collect(),
-                            mouse_buttons_held: mouse_buttons_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.mouse_check(*x))
-                                .collect(),
-                            mouse_location: self.input_manager.mouse_get_location(),
-                            frame_count: replay.frame_count(),
-                            seed: self.rand.seed(),
-                            instance: None,
-                        })?;
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            None,
+                            &watch_list,
+                        )?;
                         break
                     },
                     m => return Err(format!("Waiting for greeting from server, but got {:?}", m).into()),
@@ -1471,10 +1574,22 @@ impl Game {
         let mut game_mousex = 0;
         let mut game_mousey = 0;
         let mut do_update_mouse = false;
+        let mut predicate: Option<Predicate> = None;
+        let mut seed_search: Option<SeedSearch> = None;
+        let mut search_reported = Instant::now();
+        // How the game was before a seed search started playing seeds on it, until it's put back
+        let mut before_search: Option<SaveState> = None;
+        let mut instance_hashes = replay.instance_hashes();
+        rewind.capture(self, replay.frame_count());
         self.play_type = PlayType::Record;
 
         loop {
-            match stream.receive_message::<Message>(&mut read_buffer)? {
+            let message = stream.receive_message::<Message>(&mut read_buffer)?;
+            if matches!(message, Some(Some(_))) {
+                // Whatever the controller asks for is about the game as it was, not as the seed search left it
+                self.restore_after_search(&mut before_search)?;
+            }
+            match message {
                 Some(None) => (),
                 Some(Some(m)) => match m {
                     Message::Advance {
@@ -1486,119 +1601,333 @@ impl Game {
                         instance_requested,
                         new_seed,
                     } => {
-                        // Create a frame...
-                        let mut frame = replay.new_frame(self.room_speed);
-                        frame.mouse_x = mouse_location.0;
-                        frame.mouse_y = mouse_location.1;
-                        frame.new_seed = new_seed;
-
-                        if let Some(seed) = new_seed {
-                            self.rand.set_seed(seed);
+                        self.record_frame(
+                            &mut replay,
+                            &mut instance_hashes,
+                            key_inputs,
+                            mouse_inputs,
+                            mouse_location,
+                            new_seed,
+                        )?;
+                        rewind.capture(self, replay.frame_count());
+
+                        // Report any watchpoints which were hit during the frame
+                        for hit in self.watchpoints.take_hits() {
+                            stream.send_message(&message::Information::WatchpointHit { hit })?;
                         }
+                        self.check_recording_predicate(&mut predicate, &mut replay, &mut stream)?;
+
+                        // Send an update
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            instance_requested,
+                            &watch_list,
+                        )?
+                    },
 
-                        // Process inputs
-                        for (key, press) in key_inputs.into_iter() {
-                            if press {
-                                self.input_manager.key_press(key);
-                                frame.inputs.push(replay::Input::KeyPress(key));
-                            } else {
-                                self.input_manager.key_release(key);
-                                frame.inputs.push(replay::Input::KeyRelease(key));
+                    Message::AdvanceMany {
+                        frames,
+                        mut key_inputs,
+                        mut mouse_inputs,
+                        mouse_location,
+                        patterns,
+                        keys_requested,
+                        mouse_buttons_requested,
+                        instance_requested,
+                        mut new_seed,
+                    } => {
+                        // Buttons with patterns are only ever pressed or released by their patterns, so the
+                        // first frame's inputs can't press one the pattern then presses again
+                        let patterned = |button| patterns.iter().any(|(other, _)| *other == button);
+                        key_inputs.retain(|(key, _)| !patterned(Button::Key(*key)));
+                        mouse_inputs.retain(|(button, _)| !patterned(Button::Mouse(*button)));
+                        for _ in 0..frames {
+                            // The patterns only press or release their buttons if they aren't already that way
+                            let frame = replay.frame_count();
+                            for (button, pattern) in patterns.iter() {
+                                let held = pattern.held_on(frame);
+                                match *button {
+                                    Button::Key(key) => {
+                                        if held != self.input_manager.key_check((key as u8).into()) {
+                                            key_inputs.push((key, held));
+                                        }
+                                    },
+                                    Button::Mouse(button) => {
+                                        if held != self.input_manager.mouse_check(button) {
+                                            mouse_inputs.push((button, held));
+                                        }
+                                    },
+                                }
                             }
-                        }
-                        for (button, press) in mouse_inputs.into_iter() {
-                            if press {
-                                self.input_manager.mouse_press(button);
-                                frame.inputs.push(replay::Input::MousePress(button));
-                            } else {
-                                self.input_manager.mouse_release(button);
-                                frame.inputs.push(replay::Input::MouseRelease(button));
+                            self.record_frame(
+                                &mut replay,
+                                &mut instance_hashes,
+                                std::mem::take(&mut key_inputs),
+                                std::mem::take(&mut mouse_inputs),
+                                mouse_location,
+                                new_seed.take(),
+                            )?;
+                            rewind.capture(self, replay.frame_count());
+
+                            let mut pause = false;
+                            for hit in self.watchpoints.take_hits() {
+                                pause |= hit.pause;
+                                stream.send_message(&message::Information::WatchpointHit { hit })?;
+                            }
+                            pause |= self.check_recording_predicate(&mut predicate, &mut replay, &mut stream)?;
+                            if pause {
+                                break
                             }
                         }
-                        self.input_manager.mouse_update_previous();
-                        self.input_manager.set_mouse_pos(mouse_location.0, mouse_location.1);
-
-                        // Advance a frame
-                        self.frame()?;
-                        match self.scene_change {
-                            Some(SceneChange::Room(id)) => self.load_room(id)?,
-                            Some(SceneChange::Restart) => self.restart()?,
-                            Some(SceneChange::End) => self.restart()?,
-                            None => (),
-                        }
-                        for ev in self.stored_events.iter() {
-                            frame.events.push(ev.clone());
-                        }
-                        self.stored_events.clear();
 
                         // Send an update
-                        stream.send_message(&message::Information::Update {
-                            keys_held: keys_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.key_check((*x as u8).into()))
-                                .collect(),
-                            mouse_buttons_held: mouse_buttons_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.mouse_check(*x))
-                                .collect(),
-                            mouse_location: self.input_manager.mouse_get_location(),
-                            frame_count: replay.frame_count(),
-                            seed: self.rand.seed(),
-                            instance: instance_requested.and_then(|x| self.instance_list.get_by_instid(x)).map(|x| {
-                                let instance = self.instance_list.get(x);
-                                instance.update_bbox(self.get_instance_mask_sprite(x));
-                                instance_details(&self.assets, instance)
-                            }),
-                        })?
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            instance_requested,
+                            &watch_list,
+                        )?
                     },
 
                     Message::SetUpdateMouse { update } => do_update_mouse = update,
 
+                    Message::SetWatchpoints { watchpoints } => {
+                        if let Err(error) = self.watchpoints.set(watchpoints, &self.compiler) {
+                            stream.send_message(&message::Information::WatchpointError { error })?;
+                        }
+                    },
+
+                    Message::SetWatchList { variables } => {
+                        if let Err(e) = watch_list.set(variables, &self.compiler) {
+                            stream.send_message(&message::Information::General {
+                                message: format!("Couldn't set the watch list: {}", e),
+                            })?;
+                        }
+                    },
+
+                    Message::SetPredicate { expression } => {
+                        match expression.map(|e| Predicate::new(&e, &mut self.compiler)).transpose() {
+                            Ok(new_predicate) => predicate = new_predicate,
+                            Err(e) => stream.send_message(&message::Information::General {
+                                message: format!("Couldn't set the predicate: {}", e),
+                            })?,
+                        }
+                    },
+
+                    Message::SetOverlay { enabled } => self.show_overlay = enabled,
+
+                    Message::Search { filename, predicate: expression, sequences } => {
+                        // Try each sequence from the savestate, then go back to how things were
+                        let mut path = project_path.clone();
+                        path.push(filename);
+                        let result = self.search_from_file(&path, |game, state| {
+                            game.search(state, &expression, &sequences)
+                        });
+                        stream.send_message(&message::Information::SearchResult { result })?;
+                    },
+
+                    Message::SearchSeeds { filename, predicate: expression, seeds, frames } => {
+                        // Start trying the seeds, a few at a time after handling each message
+                        let mut path = project_path.clone();
+                        path.push(filename);
+                        if seed_search.is_some() {
+                            stream.send_message(&message::Information::General {
+                                message: "A seed search is already running".into(),
+                            })?;
+                        } else {
+                            let search = self
+                                .load_search_state(&path)
+                                .and_then(|state| SeedSearch::new(state, expression, seeds, frames));
+                            match search {
+                                Ok(search) => {
+                                    seed_search = Some(search);
+                                    search_reported = Instant::now();
+                                },
+                                Err(e) => stream.send_message(&message::Information::SearchResult { result: Err(e) })?,
+                            }
+                        }
+                    },
+
+                    Message::CancelSearch => {
+                        if let Some(search) = seed_search.take() {
+                            stream.send_message(&message::Information::SearchResult {
+                                result: Ok(search.into_outcomes()),
+                            })?;
+                        }
+                    },
+
+                    Message::PreviewRandom { seed, count, call } => {
+                        stream.send_message(&message::Information::RandomPreview {
+                            seed,
+                            values: search::preview_random(seed, call, count),
+                        })?;
+                    },
+
+                    Message::Execute { code, instance } => {
+                        // Run some code from the console, noting in the replay if it changed anything
+                        let (result, tampered) = self.execute_console_checked(&code, instance);
+                        if tampered {
+                            replay.mark_tampered();
+                        }
+                        stream.send_message(&message::Information::ExecuteResult {
+                            result: result.map(|value| value.to_string()),
+                            tampered,
+                        })?;
+                    },
+
                     Message::Save { filename } => {
                         // Save a savestate to a file
                         let mut path = project_path.clone();
                         std::fs::create_dir_all(&path)?;
                         path.push(filename);
-                        let mut f = File::create(&path)?;
-                        let bytes = bincode::serialize(&SaveState::from(self, replay.clone()))?;
-                        f.write_all(&bytes)?;
+                        SaveState::from(self, replay.clone()).save_to_file(&path, self.exe_hash)?;
                     },
 
                     Message::Load { filename, keys_requested, mouse_buttons_requested, instance_requested } => {
                         // Load savestate from a file
                         let mut path = project_path.clone();
                         path.push(filename);
-                        let f = File::open(&path)?;
-                        let state = bincode::deserialize_from::<_, SaveState>(BufReader::new(f))?;
-                        replay = state.load_into(self);
+                        let state = SaveState::load_from_file(&path, self.exe_hash)?;
+                        replay = state.load_into(self)?;
+                        instance_hashes = replay.instance_hashes();
+                        rewind.clear();
+                        rewind.capture(self, replay.frame_count());
+                        self.recompile_recording_predicate(&mut predicate, &mut stream)?;
 
                         // Send an update
-                        stream.send_message(&message::Information::Update {
-                            keys_held: keys_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.key_check((*x as u8).into()))
-                                .collect(),
-                            mouse_buttons_held: mouse_buttons_requested
-                                .into_iter()
-                                .filter(|x| self.input_manager.mouse_check(*x))
-                                .collect(),
-                            mouse_location: self.input_manager.mouse_get_location(),
-                            frame_count: replay.frame_count(),
-                            seed: self.rand.seed(),
-                            instance: instance_requested.and_then(|x| self.instance_list.get_by_instid(x)).map(|x| {
-                                let instance = self.instance_list.get(x);
-                                instance.update_bbox(self.get_instance_mask_sprite(x));
-                                instance_details(&self.assets, instance)
-                            }),
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            instance_requested,
+                            &watch_list,
+                        )?;
+                    },
+
+                    Message::Rewind { keys_requested, mouse_buttons_requested, instance_requested } => {
+                        // Go back to the latest in-memory state from before this frame
+                        if rewind.rewind(self, &mut replay)? {
+                            instance_hashes = replay.instance_hashes();
+                            self.recompile_recording_predicate(&mut predicate, &mut stream)?;
+                        } else {
+                            stream.send_message(&message::Information::General {
+                                message: "Nothing to rewind to".into(),
+                            })?;
+                        }
+
+                        // Send an update
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            instance_requested,
+                            &watch_list,
+                        )?;
+                    },
+
+                    Message::GetFrames { from, count, buttons } => {
+                        // Send which of the buttons are held on each of the frames, for the piano roll
+                        let held = buttons.into_iter().map(|button| replay.held(button)).collect::<Vec<_>>();
+                        let to = from.saturating_add(count).min(replay.frame_count());
+                        stream.send_message(&message::Information::Frames {
+                            from,
+                            held: (from..to).map(|i| held.iter().map(|column| column[i]).collect()).collect(),
                         })?;
                     },
 
+                    Message::SetHeld {
+                        frame,
+                        button,
+                        held,
+                        keys_requested,
+                        mouse_buttons_requested,
+                        instance_requested,
+                    } => {
+                        // Edit a frame, then go back to before it and play the rest of the replay again
+                        if frame >= replay.frame_count() {
+                            stream.send_message(&message::Information::General {
+                                message: format!("Frame {} hasn't been recorded yet", frame),
+                            })?;
+                        } else if let Some(start) = rewind.load_latest(self, frame)? {
+                            if replay.tampered_frames().iter().any(|tampered| *tampered > start) {
+                                stream.send_message(&message::Information::General {
+                                    message: format!("Console changes made after frame {} won't be redone", start),
+                                })?;
+                            }
+                            replay.set_held(frame, button, held);
+                            if let Err(e) = self.replay_recorded_frames(&mut replay, &mut rewind, start) {
+                                stream.send_message(&message::Information::General {
+                                    message: format!("The replay was cut short because it stopped working: {}", e),
+                                })?;
+                            }
+                            instance_hashes = replay.instance_hashes();
+                            self.recompile_recording_predicate(&mut predicate, &mut stream)?;
+                            for hit in self.watchpoints.take_hits() {
+                                stream.send_message(&message::Information::WatchpointHit { hit })?;
+                            }
+                        } else {
+                            stream.send_message(&message::Information::General {
+                                message: format!("No state from before frame {} is kept, so it can't be edited", frame),
+                            })?;
+                        }
+
+                        // Send an update
+                        send_update(
+                            self,
+                            &mut stream,
+                            replay.frame_count(),
+                            keys_requested,
+                            mouse_buttons_requested,
+                            instance_requested,
+                            &watch_list,
+                        )?;
+                    },
+
                     m => break Err(format!("Unexpected message from server: {:?}", m).into()),
                 },
                 None => break Ok(()),
             }
 
-            for event in self.window.process_events().copied() {
+            if let Some(search) = seed_search.as_mut() {
+                // Try some more seeds, then tell the controller how it went if it's over or it's been a while.
+                // The game's only put back how it was once the search is over, or when something else needs it.
+                if before_search.is_none() {
+                    before_search = Some(SaveState::from(self, Replay::new(0, 0)));
+                }
+                let deadline = Instant::now() + search::SEED_SEARCH_SLICE;
+                let result = self.continue_seed_search(search, deadline);
+                if result.is_err() || search.is_finished() {
+                    self.restore_after_search(&mut before_search)?;
+                    let outcomes = seed_search.take().map(SeedSearch::into_outcomes).unwrap_or_default();
+                    let result = result.map(|()| outcomes);
+                    stream.send_message(&message::Information::SearchResult { result })?;
+                } else if search_reported.elapsed() >= search::SEED_SEARCH_REPORT_INTERVAL {
+                    let (done, total) = search.progress();
+                    stream.send_message(&message::Information::SearchProgress { done, total })?;
+                    search_reported = Instant::now();
+                }
+            }
+
+            let events = self.window.process_events().copied().collect::<Vec<_>>();
+            if events
+                .iter()
+                .any(|event| matches!(event, Event::MouseButtonUp(MouseButton::Right) | Event::MenuOption(_)))
+            {
+                // These look at the instances, so they need the game as it was before the seed search
+                self.restore_after_search(&mut before_search)?;
+            }
+            for event in events {
                 match event {
                     Event::MouseMove(x, y) => {
                         if do_update_mouse {
@@ -1663,45 +1992,250 @@ impl Game {
         }
     }
 
+    // Loads a savestate from a file and runs a search from it, then puts the game back how it was before
+    fn search_from_file(
+        &mut self,
+        path: &std::path::Path,
+        search: impl FnOnce(&mut Self, &SaveState) -> Result<Vec<message::SearchOutcome>, String>,
+    ) -> Result<Vec<message::SearchOutcome>, String> {
+        let state = self.load_search_state(path)?;
+        let mut before_search = Some(SaveState::from(self, Replay::new(0, 0)));
+        let result = search(self, &state);
+        self.restore_after_search(&mut before_search)?;
+        result
+    }
+
+    // Loads the savestate a search starts from
+    fn load_search_state(&self, path: &std::path::Path) -> Result<SaveState, String> {
+        SaveState::load_from_file(path, self.exe_hash)
+            .map_err(|e| format!("couldn't load {}: {}", path.to_string_lossy(), e))
+    }
+
+    // Puts the game back how it was before a search started playing from a savestate, unless it already has been.
+    // Watchpoints hit during the search are forgotten.
+    fn restore_after_search(&mut self, before_search: &mut Option<SaveState>) -> Result<(), String> {
+        if let Some(state) = before_search.take() {
+            state.load_into(self)?;
+            self.watchpoints.take_hits();
+        }
+        Ok(())
+    }
+
+    // Compiles the predicate of a recording session again after a savestate is loaded, since the one it was compiled
+    // with has been replaced. If it doesn't compile any more, it's turned off.
+    fn recompile_recording_predicate(
+        &mut self,
+        predicate: &mut Option<Predicate>,
+        stream: &mut TcpStream,
+    ) -> io::Result<()> {
+        if let Some(Err(e)) = predicate.as_mut().map(|p| p.recompile(&mut self.compiler)) {
+            *predicate = None;
+            stream.send_message(&message::Information::General {
+                message: format!("Turned off the predicate because it doesn't compile any more: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+
+    // Checks the predicate after a frame of a recording session, telling the controller if it's true.
+    // If it can't be checked, say because an instance it uses is gone, it's turned off instead.
+    // It's also turned off if checking it changed the game, which can't be undone, so the replay is marked tampered.
+    fn check_recording_predicate(
+        &mut self,
+        predicate: &mut Option<Predicate>,
+        replay: &mut Replay,
+        stream: &mut TcpStream,
+    ) -> io::Result<bool> {
+        let (result, tampered) = match predicate.as_mut() {
+            Some(p) => self.check_predicate_checked(p),
+            None => return Ok(false),
+        };
+        if tampered {
+            replay.mark_tampered();
+            *predicate = None;
+            stream.send_message(&message::Information::General {
+                message: "Turned off the predicate because checking it changed the game, so the replay is now \
+                    marked as tampered with"
+                    .into(),
+            })?;
+            return Ok(false)
+        }
+        match result {
+            Ok(true) => {
+                let frame_count = replay.frame_count();
+                stream.send_message(&message::Information::PredicateMet { frame_count })?;
+                Ok(true)
+            },
+            Ok(false) => Ok(false),
+            Err(e) => {
+                *predicate = None;
+                stream.send_message(&message::Information::General {
+                    message: format!("Turned off the predicate because it stopped working: {}", e),
+                })?;
+                Ok(false)
+            },
+        }
+    }
+
+    // Runs one frame of a recording session with the given inputs, adding it to the end of the replay
+    fn record_frame(
+        &mut self,
+        replay: &mut Replay,
+        instance_hashes: &mut BTreeMap<ID, u64>,
+        key_inputs: Vec<(Key, bool)>,
+        mouse_inputs: Vec<(MouseButton, bool)>,
+        mouse_location: (f64, f64),
+        new_seed: Option<i32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.watchpoints.frame = replay.frame_count();
+        let frame = replay.new_frame(self.room_speed);
+        frame.mouse_x = mouse_location.0;
+        frame.mouse_y = mouse_location.1;
+        frame.new_seed = new_seed;
+
+        if let Some(seed) = new_seed {
+            self.rand.set_seed(seed);
+        }
+
+        // Process inputs
+        for (key, press) in key_inputs.into_iter() {
+            if press {
+                self.input_manager.key_press(key);
+                frame.inputs.push(replay::Input::KeyPress(key));
+            } else {
+                self.input_manager.key_release(key);
+                frame.inputs.push(replay::Input::KeyRelease(key));
+            }
+        }
+        for (button, press) in mouse_inputs.into_iter() {
+            if press {
+                self.input_manager.mouse_press(button);
+                frame.inputs.push(replay::Input::MousePress(button));
+            } else {
+                self.input_manager.mouse_release(button);
+                frame.inputs.push(replay::Input::MouseRelease(button));
+            }
+        }
+        self.input_manager.mouse_update_previous();
+        self.input_manager.set_mouse_pos(mouse_location.0, mouse_location.1);
+
+        // Advance a frame
+        self.frame()?;
+        match self.scene_change {
+            Some(SceneChange::Room(id)) => self.load_room(id)?,
+            Some(SceneChange::Restart) => self.restart()?,
+            Some(SceneChange::End) => self.restart()?,
+            None => (),
+        }
+        for ev in self.stored_events.iter() {
+            frame.events.push(ev.clone());
+        }
+        self.stored_events.clear();
+
+        // Hash the state so that playback can tell if it doesn't end up the same
+        let (state_hash, instances) = self.state_and_instance_hashes();
+        frame.hash = Some(replay::FrameHash::new(state_hash, instance_hashes, &instances));
+        *instance_hashes = instances;
+        Ok(())
+    }
+
+    // Plays a recording session's replay forward from frame `from`, which the game's state must already be at,
+    // to the end. This is for after recorded frames are edited: the frames get new hashes and new rewind states
+    // are taken along the way. If a frame fails, say because it no longer asks for a stored event it has,
+    // the replay is cut short before that frame, the game goes back to how it was then, and the error is returned.
+    fn replay_recorded_frames(&mut self, replay: &mut Replay, rewind: &mut Rewind, from: usize) -> Result<(), String> {
+        self.play_type = PlayType::Replay;
+        let mut result = Ok(());
+        let mut instance_hashes = replay.instance_hashes_at(from);
+        let mut frame_count = from;
+        while frame_count < replay.frame_count() {
+            if let Err(e) = self.run_recorded_frame(replay, frame_count, &mut instance_hashes) {
+                result = Err(format!("frame {}: {}", frame_count, e));
+                replay.truncate(frame_count);
+                match rewind.load_latest(self, frame_count) {
+                    Ok(Some(start)) => {
+                        frame_count = start;
+                        instance_hashes = replay.instance_hashes_at(start);
+                        continue
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        result = result.map_err(|error| format!("{}, and then going back failed: {}", error, e));
+                        break
+                    },
+                }
+            }
+            frame_count += 1;
+            rewind.capture(self, frame_count);
+        }
+        self.stored_events.clear();
+        self.play_type = PlayType::Record;
+        result
+    }
+
+    // Runs one frame of a recording session's replay, updating its hash
+    fn run_recorded_frame(
+        &mut self,
+        replay: &mut Replay,
+        index: usize,
+        instance_hashes: &mut BTreeMap<ID, u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.watchpoints.frame = index;
+        self.input_manager.mouse_update_previous();
+        if let Some(frame) = replay.get_frame(index) {
+            self.apply_replay_frame(frame);
+        }
+        self.frame()?;
+        match self.scene_change {
+            Some(SceneChange::Room(id)) => self.load_room(id)?,
+            Some(SceneChange::Restart) => self.restart()?,
+            Some(SceneChange::End) => self.restart()?,
+            None => (),
+        }
+        let (state_hash, instances) = self.state_and_instance_hashes();
+        if let Some(frame) = replay.get_frame_mut(index) {
+            frame.hash = Some(replay::FrameHash::new(state_hash, instance_hashes, &instances));
+        }
+        *instance_hashes = instances;
+        Ok(())
+    }
+
     // Replays some recorded inputs to the game
-    pub fn replay(mut self, replay: Replay) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn replay(mut self, replay: Replay, console: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let console = if console { Some(StdinConsole::new()) } else { None };
         let mut frame_count: usize = 0;
+        let mut instance_hashes = BTreeMap::new();
+        let mut desynced = false;
         self.rand.set_seed(replay.start_seed);
         self.spoofed_time_nanos = Some(replay.start_time);
         self.play_type = PlayType::Replay;
 
-        let mut time_now = std::time::Instant::now();
+        let mut time_now = Instant::now();
         loop {
-            self.window.process_events();
-            self.input_manager.mouse_update_previous();
-            if let Some(frame) = replay.get_frame(frame_count) {
-                self.stored_events.clear();
-                for ev in frame.events.iter() {
-                    self.stored_events.push_back(ev.clone());
-                }
-
-                if let Some(seed) = frame.new_seed {
-                    self.rand.set_seed(seed);
-                }
-
-                if let Some(time) = frame.new_time {
-                    self.spoofed_time_nanos = Some(time);
-                }
-
-                self.input_manager.set_mouse_pos(frame.mouse_x, frame.mouse_y);
-                for ev in frame.inputs.iter() {
-                    match ev {
-                        replay::Input::KeyPress(v) => self.input_manager.key_press(*v),
-                        replay::Input::KeyRelease(v) => self.input_manager.key_release(*v),
-                        replay::Input::MousePress(b) => self.input_manager.mouse_press(*b),
-                        replay::Input::MouseRelease(b) => self.input_manager.mouse_release(*b),
-                        replay::Input::MouseWheelUp => self.input_manager.mouse_scroll_up(),
-                        replay::Input::MouseWheelDown => self.input_manager.mouse_scroll_down(),
+            // the game's inputs come from the replay, so the only key that does anything is the turbo key, if enabled
+            for event in self.window.process_events() {
+                if let gmio::window::Event::KeyboardDown(key) = event {
+                    if self.speed.hotkey && *key == speed::TURBO_KEY {
+                        self.speed.turbo = !self.speed.turbo;
                     }
                 }
             }
+            self.input_manager.mouse_update_previous();
+            if let Some(frame) = replay.get_frame(frame_count) {
+                self.apply_replay_frame(frame);
+            }
+            if let Some(display) = self.input_display.as_mut() {
+                display.update(frame_count, replay.get_frame(frame_count));
+            }
 
             self.frame()?;
+            if let Some(console) = &console {
+                // the recorded hashes don't apply once the console has changed something, so stop checking them
+                if console.run_pending(&mut self) && !desynced {
+                    println!("Console changed the game's state on frame {}, so desyncs won't be reported", frame_count);
+                    desynced = true;
+                }
+            }
             match self.scene_change {
                 Some(SceneChange::Room(id)) => self.load_room(id)?,
                 Some(SceneChange::Restart) => self.restart()?,
@@ -1709,28 +2243,53 @@ impl Game {
                 None => (),
             }
 
+            // only the first desync is reported, since everything after it will probably be different too
+            if let Some(frame) = replay.get_frame(frame_count).filter(|_| !desynced) {
+                if let Some(desync) = self.check_frame_hash(frame, frame_count, &mut instance_hashes) {
+                    println!("Replay {}", desync);
+                    desynced = true;
+                }
+            }
+
             // exit if X pressed or game_end() invoked
             if self.window.close_requested() {
                 break Ok(self.run_game_end_events()?)
             }
 
-            // frame limiter
-            let diff = Instant::now().duration_since(time_now);
-            let duration = Duration::new(0, 1_000_000_000u32 / self.room_speed);
-            if let Some(t) = self.spoofed_time_nanos.as_mut() {
-                *t += duration.as_nanos();
-            }
-            if let Some(time) = duration.checked_sub(diff) {
-                thread::sleep(time);
-                time_now += duration;
-            } else {
-                time_now = Instant::now();
-            }
+            self.limit_frame_rate(&mut time_now);
 
             frame_count += 1;
         }
     }
 
+    // Sets up the inputs, stored events, seed and time from a replay frame, ready for the frame to be run
+    pub fn apply_replay_frame(&mut self, frame: &replay::Frame) {
+        self.stored_events.clear();
+        for ev in frame.events.iter() {
+            self.stored_events.push_back(ev.clone());
+        }
+
+        if let Some(seed) = frame.new_seed {
+            self.rand.set_seed(seed);
+        }
+
+        if let Some(time) = frame.new_time {
+            self.spoofed_time_nanos = Some(time);
+        }
+
+        self.input_manager.set_mouse_pos(frame.mouse_x, frame.mouse_y);
+        for ev in frame.inputs.iter() {
+            match ev {
+                replay::Input::KeyPress(v) => self.input_manager.key_press(*v),
+                replay::Input::KeyRelease(v) => self.input_manager.key_release(*v),
+                replay::Input::MousePress(b) => self.input_manager.mouse_press(*b),
+                replay::Input::MouseRelease(b) => self.input_manager.mouse_release(*b),
+                replay::Input::MouseWheelUp => self.input_manager.mouse_scroll_up(),
+                replay::Input::MouseWheelDown => self.input_manager.mouse_scroll_down(),
+            }
+        }
+    }
+
     // Gets the mouse position in room coordinates
     pub fn get_mouse_in_room(&self) -> (i32, i32) {
         let (x, y) = self.input_manager.mouse_get_location();
@@ -1919,40 +2478,9 @@ impl Game {
         }
 
         // Can't collide if no sprite or no associated collider
-        if let Some(sprite) = sprite {
-            // Get collider
-            let collider = match if sprite.per_frame_colliders {
-                sprite.colliders.get(inst.image_index.get().floor().into_inner() as usize % sprite.colliders.len())
-            } else {
-                sprite.colliders.first()
-            } {
-                Some(c) => c,
-                None => return false,
-            };
-
-            // Transform point to be relative to collider
-            let angle = inst.image_angle.get().to_radians();
-            let mut x = Real::from(x);
-            let mut y = Real::from(y);
-            util::rotate_around(
-                x.as_mut_ref(),
-                y.as_mut_ref(),
-                inst.x.get().into(),
-                inst.y.get().into(),
-                angle.sin().into(),
-                angle.cos().into(),
-            );
-            let x = (Real::from(sprite.origin_x) + ((x - inst.x.get()) / inst.image_xscale.get())).round();
-            let y = (Real::from(sprite.origin_y) + ((y - inst.y.get()) / inst.image_yscale.get())).round();
-
-            // And finally, look up this point in the collider
-            x >= collider.bbox_left as i32
-                && y >= collider.bbox_top as i32
-                && x <= collider.bbox_right as i32
-                && y <= collider.bbox_bottom as i32
-                && collider.data.get((y as usize * collider.width as usize) + x as usize).copied().unwrap_or(false)
-        } else {
-            false
+        match sprite.and_then(|sprite| inst.get_collider(sprite).map(|collider| (sprite, collider))) {
+            Some((sprite, collider)) => inst.collider_contains(sprite, collider, x, y),
+            None => false,
         }
     }
 
@@ -1989,11 +2517,7 @@ impl Game {
         // Can't collide if no sprite or no associated collider
         if let Some(sprite) = sprite {
             // Get collider
-            let collider = match if sprite.per_frame_colliders {
-                sprite.colliders.get(inst.image_index.get().floor().into_inner() as usize % sprite.colliders.len())
-            } else {
-                sprite.colliders.first()
-            } {
+            let collider = match inst.get_collider(sprite) {
                 Some(c) => c,
                 None => return false,
             };
@@ -2102,11 +2626,7 @@ impl Game {
         // Can't collide if no sprite or no associated collider
         if let Some(sprite) = sprite {
             // Get collider
-            let collider = match if sprite.per_frame_colliders {
-                sprite.colliders.get(inst.image_index.get().floor().into_inner() as usize % sprite.colliders.len())
-            } else {
-                sprite.colliders.first()
-            } {
+            let collider = match inst.get_collider(sprite) {
                 Some(c) => c,
                 None => return false,
             };
@@ -0,0 +1,136 @@
+use crate::{
+    action::Tree,
+    gml::{
+        compiler::{ast, Compiler},
+        runtime::Instruction,
+    },
+};
+use gm8exe::asset::etc::CodeAction;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    rc::Rc,
+    vec,
+};
+
+const EMULATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+const COMPILER_HASH: &str = env!("GM8EMU_COMPILER_HASH");
+
+/// All of a game's compiled code, saved to disk so it doesn't need to be compiled again next time the game's launched.
+/// Cache files are named after the hash of the exe they came from. They're thrown out if they were made by a
+/// different emulator version or a different compiler, since the compiled output might not be the same, or if
+/// anything in them is laid out differently, since then they can't be read correctly.
+/// The version and compiler hash are read before anything else to check that.
+#[derive(Serialize, Deserialize)]
+pub struct CompileCache {
+    emulator_version: String,
+    compiler_hash: String,
+
+    /// The compiler as it was after compiling everything, which holds the table of field ids.
+    compiler: Compiler,
+
+    trees: Vec<Tree>,
+    code: Vec<Rc<[Instruction]>>,
+}
+
+impl CompileCache {
+    /// Loads a cache file. Returns None if the file doesn't exist or can't be used.
+    pub fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        match bincode::deserialize::<(String, String)>(&data) {
+            Ok((version, hash)) if version == EMULATOR_VERSION && hash == COMPILER_HASH => {
+                bincode::deserialize(&data).ok()
+            },
+            _ => None,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Several emulators can start at once (see gm8search), so each writes to its own file first and then
+        // renames it, which means nobody ever reads a half-written cache
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Hands out compiled code during Game::launch, either by compiling it or by taking it from a loaded cache.
+/// Code must be requested in exactly the same order every time, because cached code is handed out in the order it
+/// was originally compiled in. Since the compiler's field table is restored from the cache too, field ids end up
+/// identical to a fresh compile, which keeps savestates compatible either way.
+pub struct CodeSource {
+    compiler: Compiler,
+    cached: Option<(vec::IntoIter<Tree>, vec::IntoIter<Rc<[Instruction]>>)>,
+    trees: Vec<Tree>,
+    code: Vec<Rc<[Instruction]>>,
+}
+
+impl CodeSource {
+    /// Compiles everything from scratch, using the given compiler.
+    pub fn new(compiler: Compiler) -> Self {
+        Self { compiler, cached: None, trees: Vec::new(), code: Vec::new() }
+    }
+
+    /// Uses the code stored in a cache instead of compiling.
+    pub fn from_cache(cache: CompileCache) -> Self {
+        Self {
+            compiler: cache.compiler,
+            cached: Some((cache.trees.into_iter(), cache.code.into_iter())),
+            trees: Vec::new(),
+            code: Vec::new(),
+        }
+    }
+
+    /// Gets the next action tree, compiling it from the given list if it isn't cached.
+    pub fn tree(&mut self, actions: &[CodeAction]) -> Result<Tree, String> {
+        if let Some((trees, _)) = &mut self.cached {
+            if let Some(tree) = trees.next() {
+                return Ok(tree)
+            }
+        }
+        let tree = Tree::from_list(actions, &mut self.compiler)?;
+        if self.cached.is_none() {
+            self.trees.push(tree.clone());
+        }
+        Ok(tree)
+    }
+
+    /// Gets the next piece of compiled code, compiling it from the given source if it isn't cached.
+    pub fn code(&mut self, source: &str) -> Result<Rc<[Instruction]>, ast::Error> {
+        if let Some((_, code)) = &mut self.cached {
+            if let Some(code) = code.next() {
+                return Ok(code)
+            }
+        }
+        let code = self.compiler.compile(source)?;
+        if self.cached.is_none() {
+            self.code.push(code.clone());
+        }
+        Ok(code)
+    }
+
+    /// Finishes up, giving back the compiler. If the code was freshly compiled, this also gives a cache of it.
+    pub fn finish(self) -> (Compiler, Option<CompileCache>) {
+        if self.cached.is_some() {
+            (self.compiler, None)
+        } else {
+            let cache = CompileCache {
+                emulator_version: EMULATOR_VERSION.into(),
+                compiler_hash: COMPILER_HASH.into(),
+                compiler: self.compiler.clone(),
+                trees: self.trees,
+                code: self.code,
+            };
+            (self.compiler, Some(cache))
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Runs the GML conformance tests in tests/gml against a sandboxed game (see sandbox.rs).
+//
+// Each .gml file there is a list of test cases. A case starts with a "#test <name>" line, followed by some GML,
+// and ends with either "#expect <value>" or "#error". The GML is run like a console command, so it can either
+// be an expression or some code ending in "return", and its value is compared against the expected one
+// formatted like a Value is displayed: numbers as they are and strings in double quotes.
+// Lines before the first case are ignored, so files can start with a comment saying what they're testing.
+// Every case gets a fresh game.
+
+use crate::game::{Game, SceneChange};
+use std::{fs, path::Path};
+
+enum Expected {
+    Value(String),
+    Error,
+}
+
+struct Case {
+    name: String,
+    line: usize,
+    code: String,
+    expected: Expected,
+}
+
+fn parse_cases(source: &str) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    let mut current: Option<(String, usize, String)> = None;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        if let Some(name) = line.strip_prefix("#test ") {
+            if let Some((name, start, _)) = current {
+                return Err(format!("line {}: test \"{}\" has no #expect or #error", start, name))
+            }
+            current = Some((name.trim().to_string(), line_number, String::new()));
+        } else if line.starts_with("#expect ") || line.trim_end() == "#error" {
+            let (name, start, code) = match current.take() {
+                Some(case) => case,
+                None => return Err(format!("line {}: expected result outside of a test", line_number)),
+            };
+            let expected = match line.strip_prefix("#expect ") {
+                Some(value) => Expected::Value(value.trim().to_string()),
+                None => Expected::Error,
+            };
+            cases.push(Case { name, line: start, code, expected });
+        } else if let Some((_, _, code)) = current.as_mut() {
+            code.push_str(line);
+            code.push('\n');
+        }
+    }
+    match current {
+        Some((name, start, _)) => Err(format!("line {}: test \"{}\" has no #expect or #error", start, name)),
+        None => Ok(cases),
+    }
+}
+
+// Runs every case in a file, returning a description of each one that failed
+fn run_file(path: &Path) -> Vec<String> {
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    let source = fs::read_to_string(path).unwrap();
+    let cases = match parse_cases(&source) {
+        Ok(cases) => cases,
+        Err(e) => return vec![format!("{}: {}", file_name, e)],
+    };
+    let mut failures = Vec::new();
+    for case in cases {
+        let mut game = Game::new_sandbox();
+        let result = game.execute_console(&case.code, None);
+        if matches!(game.scene_change, Some(SceneChange::End)) {
+            failures.push(format!("{}:{} ({}): ended the game", file_name, case.line, case.name));
+            continue
+        }
+        let failure = match (&case.expected, result) {
+            (Expected::Value(expected), Ok(value)) if value.to_string() == *expected => None,
+            (Expected::Value(expected), Ok(value)) => Some(format!("expected {}, got {}", expected, value)),
+            (Expected::Value(expected), Err(e)) => Some(format!("expected {}, got {}", expected, e)),
+            (Expected::Error, Ok(value)) => Some(format!("expected an error, got {}", value)),
+            (Expected::Error, Err(_)) => None,
+        };
+        if let Some(failure) = failure {
+            failures.push(format!("{}:{} ({}): {}", file_name, case.line, case.name, failure));
+        }
+    }
+    failures
+}
+
+#[test]
+fn conformance() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("gml");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "gml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no conformance tests found in {}", dir.display());
+
+    let failures = paths.iter().flat_map(|path| run_file(path)).collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{} conformance test(s) failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn parse() {
+    let cases = parse_cases("// header\n#test a\nx = 1\nreturn x\n#expect 1\n#test b\n\"a\" + 1\n#error\n").unwrap();
+    assert_eq!(cases.len(), 2);
+    assert_eq!(cases[0].name, "a");
+    assert_eq!(cases[0].line, 2);
+    assert_eq!(cases[0].code, "x = 1\nreturn x\n");
+    assert!(matches!(&cases[0].expected, Expected::Value(v) if v == "1"));
+    assert!(matches!(cases[1].expected, Expected::Error));
+    assert!(parse_cases("#test a\nreturn 1\n").is_err());
+    assert!(parse_cases("#expect 1\n").is_err());
+}
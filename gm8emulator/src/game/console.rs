@@ -0,0 +1,195 @@
+use crate::{
+    game::{Game, GetAsset, SceneChange},
+    gml::{runtime::Instruction, Context, Value},
+    instance::Instance,
+    util::Fnv1a,
+};
+use shared::{message::Message, types::ID};
+use std::{
+    hash::Hasher,
+    io::{self, BufRead},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+};
+
+impl Game {
+    /// Compiles and runs some GML between frames, as the given instance or as a dummy instance if None.
+    /// Anything that parses as an expression rather than a statement is evaluated, so entering "x" gives x.
+    /// Returns the code's return value, or the value of the function if the code was a single function call.
+    /// This never touches stored replay events, so it won't corrupt a recording or a playback in progress.
+    pub fn execute_console(&mut self, code: &str, instance: Option<ID>) -> Result<Value, String> {
+        let instructions: Rc<[Instruction]> = match self.compiler.compile(code) {
+            Ok(instructions) => instructions,
+            Err(e) => match self.compiler.compile_expression(code) {
+                Ok(node) => vec![Instruction::SetReturnValue { value: node }].into(),
+                Err(_) => return Err(format!("compiler error: {}", e)),
+            },
+        };
+
+        let (this, dummy) = match instance {
+            Some(id) => match self.instance_list.get_by_instid(id) {
+                Some(handle) => (handle, false),
+                None => return Err(format!("instance {} does not exist", id)),
+            },
+            None => {
+                let dummy = Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref()));
+                (self.instance_list.insert_dummy(dummy), true)
+            },
+        };
+        let mut context = Context {
+            this,
+            other: this,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: self.instance_list.get(this).object_index.get(),
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        };
+
+        let stored_events = self.stored_events.clone();
+        let result = match &*instructions {
+            [Instruction::EvalExpression { node }] => self.eval(node, &mut context),
+            _ => self.execute(&instructions, &mut context).map(|_| context.return_value),
+        };
+        self.stored_events = stored_events;
+        if dummy {
+            self.instance_list.remove_dummy(this);
+        }
+
+        // The frame is over, so any room change has to happen right away
+        // game_end() is left queued so the main loop picks it up after this
+        let scene_change = match self.scene_change {
+            Some(SceneChange::Room(id)) => self.load_room(id),
+            Some(SceneChange::Restart) => self.restart(),
+            Some(SceneChange::End) | None => Ok(()),
+        };
+        scene_change.map_err(|e| format!("error changing room: {}", e))?;
+
+        result.map_err(|e| format!("runtime error: {}", e))
+    }
+
+    /// Like execute_console, but also reports whether the code changed anything about the game's state.
+    /// This is done by hashing the state before and after. If the state can't be hashed afterwards, the code is
+    /// assumed to have changed it, since there's no telling.
+    /// Compiling the code can add new field names to the compiler, which would end up in savestates. If nothing else
+    /// changed, nothing can be using those names yet, so the compiler is put back how it was to leave no trace.
+    pub fn execute_console_checked(&mut self, code: &str, instance: Option<ID>) -> (Result<Value, String>, bool) {
+        let before = match self.state_fingerprint() {
+            Ok(hash) => hash,
+            Err(e) => return (Err(e), false),
+        };
+        let compiler = self.compiler.clone();
+        let result = self.execute_console(code, instance);
+        match self.state_fingerprint() {
+            Ok(after) if after == before => {
+                self.compiler = compiler;
+                (result, false)
+            },
+            Ok(_) => (result, true),
+            Err(e) => (result.and(Err(e)), true),
+        }
+    }
+
+    // Hashes the state replays are checked against, plus the rest of the state GML can change which isn't in that.
+    // This only ever gets compared within one process, so unlike state_hash it can hash things in any stable order.
+    // It hashes every asset in full, which is slow, but it's only used for code entered by hand and for the
+    // predicate while recording.
+    pub(super) fn state_fingerprint(&self) -> Result<u64, String> {
+        let mut hasher = Fnv1a::default();
+        hasher.write_u64(self.state_hash());
+        let assets = (&self.assets, &self.event_holders, &self.custom_draw_objects, &self.globalvars);
+        let room = (self.room_width, self.room_height, self.room_speed, &self.room_order, self.last_tile_id);
+        let views = (self.views_enabled, self.view_current, &self.views, &self.backgrounds);
+        let colours = (self.background_colour, self.room_colour, self.draw_colour, self.draw_alpha);
+        let drawing = (self.draw_font_id, self.draw_halign, self.draw_valign, &self.surfaces, self.surface_target);
+        let renderer = (self.renderer.get_blend_mode(), self.renderer.get_pixel_interpolation());
+        let captions = (
+            (&self.score_capt, self.score_capt_d, &self.lives_capt, self.lives_capt_d),
+            (&self.health_capt, self.health_capt_d, &self.caption),
+        );
+        let input = (&self.input_manager, self.spoofed_time_nanos, self.game_start);
+        let externals = self.externals.iter().map(|e| e.as_ref().map(|e| &e.info)).collect::<Vec<_>>();
+        let other = (&self.particles, self.transition_kind, self.transition_steps, self.ds_precision, externals);
+        let flags = (self.uninit_fields_are_zero, self.uninit_args_are_zero);
+        bincode::serialize_into(&mut hasher, &(assets, room, views, colours, drawing, renderer, captions, input))
+            .and_then(|_| bincode::serialize_into(&mut hasher, &(other, flags)))
+            .map_err(|e| format!("couldn't hash the game's state: {}", e))?;
+        Ok(hasher.finish())
+    }
+}
+
+/// Reads lines of GML from stdin on a background thread, so they can be run between frames.
+/// Lines can start with "@ID" to run as a specific instance, otherwise they run globally.
+pub struct StdinConsole {
+    lines: mpsc::Receiver<String>,
+}
+
+impl StdinConsole {
+    pub fn new() -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) if sender.send(line).is_ok() => (),
+                    _ => break,
+                }
+            }
+        });
+        Self { lines }
+    }
+
+    /// Runs every line that's been entered since the last call and prints the results.
+    /// Returns true if any of them changed the game's state, which callers playing back a replay need to know,
+    /// since the replay's inputs won't do the same thing afterwards.
+    pub fn run_pending(&self, game: &mut Game) -> bool {
+        let mut tampered = false;
+        while let Ok(line) = self.lines.try_recv() {
+            if line.trim().is_empty() {
+                continue
+            }
+            match Message::execute_from_console(&line, None) {
+                Ok(Message::Execute { code, instance }) => {
+                    let (result, changed) = game.execute_console_checked(&code, instance);
+                    match result {
+                        Ok(value) => println!("{}", value),
+                        Err(e) => println!("{}", e),
+                    }
+                    tampered |= changed;
+                },
+                Ok(_) => (),
+                Err(e) => println!("{}", e),
+            }
+        }
+        tampered
+    }
+}
+
+impl Default for StdinConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+
+    #[test]
+    fn tamper_check() {
+        let mut game = Game::new_sandbox();
+        let (result, tampered) = game.execute_console_checked("var unused_name; unused_name = 1", None);
+        assert!(result.is_ok());
+        assert!(!tampered);
+        assert_eq!(game.compiler.find_field_id("unused_name"), None);
+
+        // asset contents aren't part of the state hash, but still count
+        let (result, tampered) = game.execute_console_checked("object_set_depth(0, 5)", None);
+        assert!(result.is_ok());
+        assert!(tampered);
+    }
+}
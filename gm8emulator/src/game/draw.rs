@@ -119,7 +119,11 @@ impl Game {
 
         // Tell renderer to finish the frame
         if self.surface_target.is_none() {
-            self.renderer.present();
+            if self.show_overlay || self.input_display.is_some() {
+                self.present_with_overlay();
+            } else {
+                self.present();
+            }
         }
 
         // Reset viewport
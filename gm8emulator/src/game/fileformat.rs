@@ -0,0 +1,177 @@
+// The header at the start of savestate and replay files, which are kept around between sessions and emulator builds.
+// It has some magic bytes and a format version, so files can be migrated when the format changes and newer ones
+// refused, followed by the emulator version which wrote the file and the hash of the game's exe, so files aren't
+// loaded into the wrong game.
+//
+// Layout: 8 magic bytes, the format version as a little-endian u32, then bincode of (emulator version, exe hash).
+
+use std::{
+    error::Error,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+pub const EMULATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A kind of file with a header. Each kind has its own magic bytes and its own format versions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    SaveState,
+    Replay,
+}
+
+impl FileKind {
+    fn magic(self) -> &'static [u8; 8] {
+        match self {
+            Self::SaveState => b"GM8SAVE\0",
+            Self::Replay => b"GM8TAS\0\0",
+        }
+    }
+
+    /// The format version which this build writes, and the newest one it can read.
+    pub fn version(self) -> u32 {
+        match self {
+            Self::SaveState => 1,
+            Self::Replay => 1,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::SaveState => "savestate",
+            Self::Replay => "replay",
+        }
+    }
+}
+
+/// A file's header, as read from it.
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+
+    /// The version of the emulator which wrote the file.
+    pub emulator_version: String,
+
+    /// The hash of the exe of the game the file was made with.
+    pub exe_hash: u64,
+}
+
+impl Header {
+    /// Writes a header for the current format version.
+    pub fn write(kind: FileKind, exe_hash: u64, mut writer: impl Write) -> Result<(), Box<dyn Error>> {
+        writer.write_all(kind.magic())?;
+        writer.write_all(&kind.version().to_le_bytes())?;
+        bincode::serialize_into(writer, &(EMULATOR_VERSION, exe_hash))?;
+        Ok(())
+    }
+
+    /// Reads a header, leaving the reader at the start of what comes after it.
+    /// Returns None if the file doesn't start with any magic bytes, meaning it's from before files had headers,
+    /// and puts the reader back at the start. Returns an error if it's another kind of file with a header, or if
+    /// it's from a newer format than this build can read.
+    /// If another version of the emulator wrote it, that's printed as a warning, since the game might not run
+    /// the same way in this one even if the format hasn't changed.
+    pub fn read<R: Read + Seek>(kind: FileKind, mut reader: R) -> Result<Option<Self>, Box<dyn Error>> {
+        let mut magic = [0u8; 8];
+        if reader.read_exact(&mut magic).is_err() || &magic != kind.magic() {
+            if let Some(other) = [FileKind::SaveState, FileKind::Replay].iter().find(|other| &magic == other.magic()) {
+                return Err(format!("this is a {}, not a {}", other.name(), kind.name()).into())
+            }
+            reader.seek(SeekFrom::Start(0))?;
+            return Ok(None)
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version > kind.version() {
+            // newer formats might not have the same header, so nothing else can be read
+            return Err(format!(
+                "this {} is format version {}, but this build of the emulator ({}) only reads up to version {}",
+                kind.name(),
+                version,
+                EMULATOR_VERSION,
+                kind.version(),
+            )
+            .into())
+        }
+        let (emulator_version, exe_hash) = bincode::deserialize_from::<_, (String, u64)>(reader)?;
+        let header = Self { version, emulator_version, exe_hash };
+        if let Some(warning) = header.version_warning(kind) {
+            println!("warning: {}", warning);
+        }
+        Ok(Some(header))
+    }
+
+    /// Checks that the file belongs to the game with the given exe hash.
+    pub fn check(&self, kind: FileKind, exe_hash: u64) -> Result<(), String> {
+        check_exe_hash(kind, Some(self.exe_hash), exe_hash)
+    }
+
+    /// Says so if the file was written by another version of the emulator.
+    pub fn version_warning(&self, kind: FileKind) -> Option<String> {
+        if self.emulator_version != EMULATOR_VERSION {
+            Some(format!(
+                "this {} was made with version {} of the emulator, but this is version {}, so it might not play \
+                 back the same way",
+                kind.name(),
+                self.emulator_version,
+                EMULATOR_VERSION,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks the exe hash a file was saved with, if it has one, against the hash of the game it's being loaded into.
+pub fn check_exe_hash(kind: FileKind, file_hash: Option<u64>, exe_hash: u64) -> Result<(), String> {
+    match file_hash {
+        Some(hash) if hash != exe_hash => Err(format!(
+            "this {} was made with a different game (exe hash {:016x}, but this game's is {:016x})",
+            kind.name(),
+            hash,
+            exe_hash,
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header() {
+        let mut data = Vec::new();
+        Header::write(FileKind::Replay, 1234, &mut data).unwrap();
+        data.extend_from_slice(b"after");
+        let mut reader = Cursor::new(&data);
+        let header = Header::read(FileKind::Replay, &mut reader).unwrap().unwrap();
+        assert_eq!(header.exe_hash, 1234);
+        assert!(header.check(FileKind::Replay, 1234).is_ok());
+        assert!(header.check(FileKind::Replay, 4321).is_err());
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"after");
+
+        assert_eq!(header.version_warning(FileKind::Replay), None);
+
+        // wrong kind of file
+        let error = Header::read(FileKind::SaveState, Cursor::new(&data)).unwrap_err();
+        assert_eq!(error.to_string(), "this is a replay, not a savestate");
+
+        // no header at all
+        let mut reader = Cursor::new(b"old replay data");
+        assert_eq!(Header::read(FileKind::Replay, &mut reader).unwrap(), None);
+        assert_eq!(reader.position(), 0);
+
+        // another emulator version
+        let header = Header { emulator_version: "0.0.0".into(), ..header };
+        assert!(header.check(FileKind::Replay, 1234).is_ok());
+        assert!(header.version_warning(FileKind::Replay).unwrap().contains("version 0.0.0"));
+
+        // too new
+        data[8..12].copy_from_slice(&(FileKind::Replay.version() + 1).to_le_bytes());
+        assert!(Header::read(FileKind::Replay, Cursor::new(&data)).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+// An input display for replay playback, for encodes and demonstrations. It shows the frame number, the room, how many
+// frames lagged behind the room speed, and which buttons the replay is holding, in a small font that's built in so it
+// doesn't depend on the game's assets. It's drawn along with the other overlays, so the game never sees it either.
+
+use crate::game::{replay, Game, GetAsset};
+use shared::input::Button;
+
+const TEXT_COLOUR: i32 = 0xFFFFFF;
+const BACKGROUND_COLOUR: i32 = 0x000000;
+const BACKGROUND_ALPHA: f64 = 0.6;
+
+// How many window pixels each pixel of the font takes up, and the space around and between lines of text
+const SCALE: i32 = 2;
+const MARGIN: i32 = 4;
+const LINE_SPACING: i32 = 3;
+
+const GLYPH_WIDTH: i32 = 5;
+const GLYPH_HEIGHT: i32 = 7;
+
+/// What the input display shows, kept up to date from the frames of the replay being played.
+pub struct InputDisplay {
+    frame: usize,
+    held: Vec<Button>,
+}
+
+impl InputDisplay {
+    pub fn new() -> Self {
+        Self { frame: 0, held: Vec::new() }
+    }
+
+    /// Updates the display for the next frame, from that frame of the replay if it has one.
+    pub fn update(&mut self, frame_count: usize, frame: Option<&replay::Frame>) {
+        self.frame = frame_count;
+        for input in frame.iter().flat_map(|frame| frame.inputs.iter()) {
+            let (button, held) = match *input {
+                replay::Input::KeyPress(key) => (Button::Key(key), true),
+                replay::Input::KeyRelease(key) => (Button::Key(key), false),
+                replay::Input::MousePress(button) => (Button::Mouse(button), true),
+                replay::Input::MouseRelease(button) => (Button::Mouse(button), false),
+                replay::Input::MouseWheelUp | replay::Input::MouseWheelDown => continue,
+            };
+            self.held.retain(|x| *x != button);
+            if held {
+                self.held.push(button);
+            }
+        }
+    }
+
+    // The lines of text to show
+    fn lines(&self, game: &Game) -> Vec<String> {
+        let room = match game.assets.rooms.get_asset(game.room_id) {
+            Some(room) => format!("{} ({})", room.name, game.room_id),
+            None => game.room_id.to_string(),
+        };
+        let held = self.held.iter().map(Button::to_string).collect::<Vec<_>>();
+        vec![
+            format!("Frame {}", self.frame),
+            format!("Room {}  Lag {}", room, game.speed.lag_frames),
+            format!("Held {}", if held.is_empty() { "-".into() } else { held.join(" ") }),
+        ]
+    }
+}
+
+impl Default for InputDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    // Draws the input display in the top-left corner of the window
+    pub fn draw_input_display(&mut self) {
+        let lines = match &self.input_display {
+            Some(display) => display.lines(self),
+            None => return,
+        };
+
+        let (width, height) = self.window.get_inner_size();
+        let (view_width, view_height) = (self.unscaled_width as i32, self.unscaled_height as i32);
+        self.renderer.set_view(
+            width,
+            height,
+            self.unscaled_width,
+            self.unscaled_height,
+            0,
+            0,
+            view_width,
+            view_height,
+            0.0,
+            0,
+            0,
+            view_width,
+            view_height,
+        );
+
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) * SCALE;
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as i32;
+        let right = MARGIN * 2 + longest * (GLYPH_WIDTH + 1) * SCALE - SCALE;
+        let bottom = MARGIN * 2 + lines.len() as i32 * line_height - LINE_SPACING * SCALE;
+        self.renderer.draw_rectangle(
+            0.0,
+            0.0,
+            f64::from(right - 1),
+            f64::from(bottom - 1),
+            BACKGROUND_COLOUR,
+            BACKGROUND_ALPHA,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_built_in_text(line, MARGIN, MARGIN + i as i32 * line_height);
+        }
+    }
+
+    // Draws a line of text in the built-in font, with each glyph's columns drawn as a few tall rectangles
+    fn draw_built_in_text(&mut self, text: &str, x: i32, y: i32) {
+        for (i, c) in text.chars().enumerate() {
+            let glyph = glyph(c);
+            let glyph_x = x + i as i32 * (GLYPH_WIDTH + 1) * SCALE;
+            for (column, bits) in glyph.iter().enumerate() {
+                let column_x = glyph_x + column as i32 * SCALE;
+                let mut row = 0;
+                while row < GLYPH_HEIGHT {
+                    if bits & (1 << row) == 0 {
+                        row += 1;
+                        continue
+                    }
+                    let run_start = row;
+                    while row < GLYPH_HEIGHT && bits & (1 << row) != 0 {
+                        row += 1;
+                    }
+                    self.renderer.draw_rectangle(
+                        column_x.into(),
+                        (y + run_start * SCALE).into(),
+                        (column_x + SCALE - 1).into(),
+                        (y + row * SCALE - 1).into(),
+                        TEXT_COLOUR,
+                        1.0,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Gets the columns of a character in the built-in font, left to right, with the top row in the lowest bit.
+// Anything other than printable ASCII is drawn as a question mark.
+fn glyph(c: char) -> &'static [u8; 5] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT[index]
+}
+
+#[rustfmt::skip]
+static FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::input::{Key, MouseButton};
+
+    #[test]
+    fn held_buttons() {
+        let mut frame = replay::Frame {
+            fps: 50,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            inputs: vec![
+                replay::Input::KeyPress(Key::Left),
+                replay::Input::MousePress(MouseButton::Left),
+                replay::Input::KeyPress(Key::Z),
+            ],
+            events: Vec::new(),
+            new_seed: None,
+            new_time: None,
+            hash: None,
+        };
+        let mut display = InputDisplay::new();
+        display.update(0, Some(&frame));
+        assert_eq!(display.held, [Button::Key(Key::Left), Button::Mouse(MouseButton::Left), Button::Key(Key::Z)]);
+
+        frame.inputs = vec![replay::Input::KeyRelease(Key::Left), replay::Input::MouseWheelUp];
+        display.update(1, Some(&frame));
+        display.update(2, None);
+        assert_eq!(display.frame, 2);
+        assert_eq!(display.held, [Button::Mouse(MouseButton::Left), Button::Key(Key::Z)]);
+    }
+}
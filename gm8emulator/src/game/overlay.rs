@@ -0,0 +1,255 @@
+// An overlay for recording which shows what the game is doing underneath what it draws: each instance's bounding box
+// and collision mask, the paths instances are following, and the views. It's drawn over a frame just before that's
+// shown, then the frame is put back how the game drew it, so nothing the game can read back (including the
+// screenshots in savestates) ever has the overlay on it. Nothing here touches game state, not even the cached bboxes.
+// The input display for replay playback is drawn here too, for the same reasons.
+
+use crate::{
+    asset::Path,
+    game::{Game, GetAsset},
+    instance::Instance,
+    math::Real,
+    util,
+};
+use gmio::render::BlendType;
+
+const BBOX_COLOUR: i32 = 0x0000FF;
+const MASK_COLOUR: i32 = 0x00FF00;
+const MASK_ALPHA: f64 = 0.4;
+const PATH_COLOUR: i32 = 0x00FFFF;
+const VIEW_COLOUR: i32 = 0xFFFF00;
+const VIEW_BORDER_COLOUR: i32 = 0xFF00FF;
+
+// How far apart the dots marking out a path are, in pixels
+const PATH_DOT_SPACING: f64 = 4.0;
+const PATH_DOT_MAX: usize = 2000;
+
+#[rustfmt::skip]
+const IDENTITY_MATRIX: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+impl Game {
+    /// Shows the frame with the overlay and input display on top, whichever are turned on,
+    /// then puts back the frame as it was before they were drawn.
+    pub fn present_with_overlay(&mut self) {
+        if !self.speed.should_present() {
+            return self.present()
+        }
+
+        // The game's own settings are put back afterwards, since it might rely on them staying set across frames
+        self.renderer.flush_queue();
+        let (width, height) = self.window.get_inner_size();
+        let frame = self.renderer.get_pixels(0, 0, width as _, height as _);
+        let model_matrix = self.renderer.get_model_matrix();
+        let (blend_src, blend_dst) = self.renderer.get_blend_mode();
+        let interpolate_pixels = self.renderer.get_pixel_interpolation();
+        self.renderer.set_model_matrix(IDENTITY_MATRIX);
+        self.renderer.set_blend_mode(BlendType::SrcAlpha, BlendType::InvSrcAlpha);
+        self.renderer.set_pixel_interpolation(false);
+
+        if self.show_overlay {
+            self.draw_overlay();
+        }
+        self.draw_input_display();
+        self.renderer.present();
+
+        // The blit is clipped to the viewport, so that has to cover the whole window first
+        self.renderer.set_view(
+            width,
+            height,
+            self.unscaled_width,
+            self.unscaled_height,
+            0,
+            0,
+            width as _,
+            height as _,
+            0.0,
+            0,
+            0,
+            width as _,
+            height as _,
+        );
+        self.renderer.set_pixels(frame, width as _, height as _);
+        self.renderer.set_model_matrix(model_matrix);
+        self.renderer.set_blend_mode(blend_src, blend_dst);
+        self.renderer.set_pixel_interpolation(interpolate_pixels);
+    }
+
+    // Draws the overlay in each view, or over the whole room if views are off
+    fn draw_overlay(&mut self) {
+        if self.views_enabled {
+            // Iter views in a non-borrowing way
+            let mut count = 0;
+            while let Some(&view) = self.views.get(count) {
+                count += 1;
+                if !view.visible {
+                    continue
+                }
+                self.draw_overlay_view(
+                    view.source_x,
+                    view.source_y,
+                    view.source_w as _,
+                    view.source_h as _,
+                    view.port_x,
+                    view.port_y,
+                    view.port_w as _,
+                    view.port_h as _,
+                    view.angle.into(),
+                );
+            }
+        } else {
+            self.draw_overlay_view(
+                0,
+                0,
+                self.room_width,
+                self.room_height,
+                0,
+                0,
+                self.room_width,
+                self.room_height,
+                0.0,
+            );
+        }
+    }
+
+    // Draws the overlay for the part of the room a view can see
+    fn draw_overlay_view(
+        &mut self,
+        src_x: i32,
+        src_y: i32,
+        src_w: i32,
+        src_h: i32,
+        port_x: i32,
+        port_y: i32,
+        port_w: i32,
+        port_h: i32,
+        angle: f64,
+    ) {
+        let (width, height) = self.window.get_inner_size();
+        self.renderer.set_view(
+            width,
+            height,
+            self.unscaled_width,
+            self.unscaled_height,
+            src_x,
+            src_y,
+            src_w,
+            src_h,
+            angle,
+            port_x,
+            port_y,
+            port_w,
+            port_h,
+        );
+
+        for rect in self.overlay_rectangles(src_x, src_y, src_w, src_h) {
+            let (x1, y1, x2, y2) = (rect.x1.into(), rect.y1.into(), rect.x2.into(), rect.y2.into());
+            if rect.outline {
+                self.renderer.draw_rectangle_outline(x1, y1, x2, y2, rect.colour, rect.alpha);
+            } else {
+                self.renderer.draw_rectangle(x1, y1, x2, y2, rect.colour, rect.alpha);
+            }
+        }
+    }
+
+    // Works out everything to draw for the given part of the room
+    fn overlay_rectangles(&self, src_x: i32, src_y: i32, src_w: i32, src_h: i32) -> Vec<Rectangle> {
+        let mut rects = Vec::new();
+        let mut iter = self.instance_list.iter_by_drawing();
+        while let Some(handle) = iter.next(&self.instance_list) {
+            let instance = self.instance_list.get(handle);
+            if let Some(path) = self.assets.paths.get_asset(instance.path_index.get()) {
+                path_rectangles(instance, path, &mut rects);
+            }
+            if let Some(sprite) = self.get_instance_mask_sprite(handle) {
+                // Only the part of the bbox this view can see is checked, since big masks have a lot of pixels
+                let (left, top, right, bottom) = instance.compute_bbox(sprite);
+                if let Some(collider) = instance.get_collider(sprite) {
+                    let last_x = right.min(src_x + src_w);
+                    for y in top.max(src_y)..=bottom.min(src_y + src_h) {
+                        // Set pixels next to each other are drawn as one rectangle, rather than one per pixel
+                        let mut run_start = None;
+                        for x in left.max(src_x)..=last_x + 1 {
+                            match (run_start, x <= last_x && instance.collider_contains(sprite, collider, x, y)) {
+                                (None, true) => run_start = Some(x),
+                                (Some(x1), false) => {
+                                    rects.push(Rectangle::filled(x1, y, x - 1, y, MASK_COLOUR, MASK_ALPHA));
+                                    run_start = None;
+                                },
+                                _ => (),
+                            }
+                        }
+                    }
+                }
+                rects.push(Rectangle::outlined(left, top, right, bottom, BBOX_COLOUR));
+            }
+        }
+
+        // Every view's outline is drawn in every view, along with the area its target has to stay inside
+        for view in self.views.iter().filter(|x| self.views_enabled && x.visible) {
+            let (x1, y1) = (view.source_x, view.source_y);
+            let (x2, y2) = (x1 + view.source_w as i32 - 1, y1 + view.source_h as i32 - 1);
+            rects.push(Rectangle::outlined(x1, y1, x2, y2, VIEW_COLOUR));
+            if self.assets.objects.get_asset(view.follow_target).is_some() {
+                let (hborder, vborder) = (view.follow_hborder, view.follow_vborder);
+                rects.push(Rectangle::outlined(
+                    x1 + hborder,
+                    y1 + vborder,
+                    x2 - hborder,
+                    y2 - vborder,
+                    VIEW_BORDER_COLOUR,
+                ));
+            }
+        }
+        rects
+    }
+}
+
+// A rectangle to draw, given by the room coordinates of its top-left and bottom-right pixels
+struct Rectangle {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+    colour: i32,
+    alpha: f64,
+    outline: bool,
+}
+
+impl Rectangle {
+    fn filled(x1: i32, y1: i32, x2: i32, y2: i32, colour: i32, alpha: f64) -> Self {
+        Self { x1, y1, x2, y2, colour, alpha, outline: false }
+    }
+
+    fn outlined(x1: i32, y1: i32, x2: i32, y2: i32, colour: i32) -> Self {
+        Self { x1, y1, x2, y2, colour, alpha: 1.0, outline: true }
+    }
+}
+
+// Marks out the path an instance is following with dots, placed the same way it places the instance
+fn path_rectangles(instance: &Instance, path: &Path, rects: &mut Vec<Rectangle>) {
+    let scale = instance.path_scale.get();
+    let angle = instance.path_orientation.get().to_radians();
+    let length = (path.length * scale.abs()).into_inner();
+    let dots = ((length / PATH_DOT_SPACING) as usize).max(1).min(PATH_DOT_MAX);
+    for i in 0..=dots {
+        let mut point = path.get_point(Real::from(i as f64 / dots as f64));
+        point.x = (point.x - path.start.x) * scale;
+        point.y = (point.y - path.start.y) * scale;
+        util::rotate_around(
+            point.x.as_mut_ref(),
+            point.y.as_mut_ref(),
+            0.0,
+            0.0,
+            angle.sin().into(),
+            angle.cos().into(),
+        );
+        let x = (point.x + instance.path_xstart.get()).floor().round();
+        let y = (point.y + instance.path_ystart.get()).floor().round();
+        rects.push(Rectangle::filled(x, y, x, y, PATH_COLOUR, 1.0));
+    }
+}
@@ -1100,6 +1100,12 @@ impl ParticleType {
     }
 }
 
+impl Default for ParticleType {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl System {
     pub fn new() -> Self {
         Self {
@@ -1217,6 +1223,12 @@ impl System {
     }
 }
 
+impl Default for System {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Particle {
     fn new(
         ptype_id: i32,
@@ -1478,6 +1490,12 @@ impl Attractor {
     }
 }
 
+impl Default for Attractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Deflector {
     pub fn new() -> Self {
         Self {
@@ -1515,6 +1533,12 @@ impl Deflector {
     }
 }
 
+impl Default for Deflector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Changer {
     pub fn new() -> Self {
         Self {
@@ -1558,6 +1582,12 @@ impl Changer {
     }
 }
 
+impl Default for Changer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Destroyer {
     pub fn new() -> Self {
         Self {
@@ -1576,6 +1606,12 @@ impl Destroyer {
     }
 }
 
+impl Default for Destroyer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Emitter {
     pub fn new() -> Self {
         Self {
@@ -1642,6 +1678,12 @@ impl Emitter {
     }
 }
 
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn load_shapes(atlases: &mut AtlasBuilder) -> Vec<AtlasRef> {
     let raw = include_bytes!("../../data/particles.dat");
     let mut atlas_refs = Vec::with_capacity(14);
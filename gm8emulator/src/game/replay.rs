@@ -1,6 +1,23 @@
-use crate::gml::Value;
+use crate::{
+    game::fileformat::{check_exe_hash, FileKind, Header},
+    gml::Value,
+};
 use serde::{Deserialize, Serialize};
-use shared::input::{Key, MouseButton};
+use shared::{
+    input::{Button, Key, MouseButton},
+    types::ID,
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    ops::Range,
+    path::Path,
+};
+
+mod ltm;
+mod text;
 
 // Represents an entire replay (TAS) file
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,6 +31,10 @@ pub struct Replay {
 
     // List of frames in this replay.
     frames: Vec<Frame>,
+
+    // Frame counts at which the game's state was modified by something other than inputs, such as the console.
+    // A tampered replay probably won't play back the same way it was recorded.
+    tampered: Vec<usize>,
 }
 
 // Associated data for a single frame of playback
@@ -26,6 +47,20 @@ pub struct Frame {
     pub events: Vec<Event>,
     pub new_seed: Option<i32>,
     pub new_time: Option<u128>,
+    pub hash: Option<FrameHash>,
+}
+
+// Hashes of the game's state after a frame ran, taken while recording so playback can tell when it desyncs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameHash {
+    // Hash of the whole state, see Game::state_hash
+    pub state: u64,
+
+    // Hashes of the instances which changed during this frame, and the IDs of any which stopped existing.
+    // Instances which didn't change aren't stored, so these only make sense applied to the previous frame's.
+    // If the previous frame has no hash, every instance is stored.
+    pub changed: Vec<(ID, u64)>,
+    pub removed: Vec<ID>,
 }
 
 // Stored events for certain things which must always happen the same way during replay
@@ -52,7 +87,7 @@ pub enum Input {
 
 impl Replay {
     pub fn new(start_time: u128, start_seed: i32) -> Self {
-        Self { start_time, start_seed, frames: Vec::new() }
+        Self { start_time, start_seed, frames: Vec::new(), tampered: Vec::new() }
     }
 
     // Adds a new frame of input to the end of the replay.
@@ -71,20 +106,247 @@ impl Replay {
             events: Vec::new(),
             new_seed: None,
             new_time: None,
+            hash: None,
         });
         self.frames.last_mut().unwrap() // Last cannot be None since we just pushed an element
     }
 
+    // Writes the replay to a .gmtas file saying which game it's for, or as text if the path ends in .gmtxt,
+    // or as a libTAS movie if it ends in .ltm
+    pub fn save_to_file(&self, path: &Path, exe_hash: u64) -> Result<(), Box<dyn Error>> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gmtxt") => return Ok(std::fs::write(path, self.to_text(exe_hash))?),
+            Some("ltm") => return self.write_ltm(BufWriter::new(File::create(path)?)),
+            _ => (),
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        Header::write(FileKind::Replay, exe_hash, &mut file)?;
+        bincode::serialize_into(&mut file, self)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    // Reads a replay written by save_to_file, migrating it from an older format if need be.
+    // Returns an error if it's from a newer format or for a different game.
+    pub fn load_from_file(path: &Path, exe_hash: u64) -> Result<Self, Box<dyn Error>> {
+        let (replay, file_hash) = Self::read_file(path)?;
+        check_exe_hash(FileKind::Replay, file_hash, exe_hash)?;
+        Ok(replay)
+    }
+
+    // Reads a replay like load_from_file, but for any game.
+    // Also returns the exe hash of the game it was saved for, if the file says.
+    //
+    // Format versions of .gmtas files:
+    // none: no header, just a bincode Replay in the layout in the legacy module.
+    // 1: added the header.
+    pub fn read_file(path: &Path) -> Result<(Self, Option<u64>), Box<dyn Error>> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gmtxt") => return Ok(Self::from_text(&std::fs::read_to_string(path)?)?),
+            Some("ltm") => return Ok((Self::read_ltm(BufReader::new(File::open(path)?))?, None)),
+            _ => (),
+        }
+        let mut file = BufReader::new(File::open(path)?);
+        match Header::read(FileKind::Replay, &mut file)? {
+            Some(header) => Ok((bincode::deserialize_from(file)?, Some(header.exe_hash))),
+            None => {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                Ok((legacy::migrate(&data)?, None))
+            },
+        }
+    }
+
     // Gets the data associated with a given frame, if any
     pub fn get_frame(&self, index: usize) -> Option<&Frame> {
         self.frames.get(index)
     }
 
+    // Gets the data associated with a given frame mutably, if any
+    pub fn get_frame_mut(&mut self, index: usize) -> Option<&mut Frame> {
+        self.frames.get_mut(index)
+    }
+
     // Gets the replay's frame count
     pub fn frame_count(&self) -> usize {
         self.frames.len()
     }
 
+    // Cuts the replay short, keeping only its first frame_count frames
+    // Any tampering from after the last of those frames is forgotten too
+    pub fn truncate(&mut self, frame_count: usize) {
+        self.frames.truncate(frame_count);
+        self.tampered.retain(|frame| *frame < frame_count);
+    }
+
+    // Inserts blank frames before frame `at`, or at the end if it's past the end
+    // They have no inputs and the mouse stays where it was on the frame before
+    pub fn insert_blank_frames(&mut self, at: usize, count: usize, fps: u32) {
+        let at = at.min(self.frames.len());
+        let (mouse_x, mouse_y) = match at.checked_sub(1).and_then(|i| self.frames.get(i)) {
+            Some(frame) => (frame.mouse_x, frame.mouse_y),
+            None => (0.0, 0.0),
+        };
+        let frame = Frame {
+            fps,
+            mouse_x,
+            mouse_y,
+            inputs: Vec::new(),
+            events: Vec::new(),
+            new_seed: None,
+            new_time: None,
+            hash: None,
+        };
+        self.insert_frames(at, std::iter::repeat(frame).take(count).collect());
+    }
+
+    // Inserts a copy of some of another replay's frames before frame `at`, or at the end if it's past the end
+    pub fn splice(&mut self, at: usize, other: &Replay, range: Range<usize>) {
+        let end = range.end.min(other.frames.len());
+        let start = range.start.min(end);
+        self.insert_frames(at.min(self.frames.len()), other.frames[start..end].to_vec());
+    }
+
+    // Removes up to `count` frames starting at frame `at`
+    // Any tampering during those frames is treated as happening just before the frame after them
+    pub fn delete_frames(&mut self, at: usize, count: usize) {
+        let at = at.min(self.frames.len());
+        let end = at.saturating_add(count).min(self.frames.len());
+        self.frames.drain(at..end);
+        for frame in self.tampered.iter_mut() {
+            if *frame > end {
+                *frame -= end - at;
+            } else if *frame > at {
+                *frame = at;
+            }
+        }
+        self.tampered.dedup();
+        self.clear_hashes(at);
+    }
+
+    // Moves every frame's inputs and mouse position `offset` frames later, or earlier if it's negative,
+    // leaving the rest of each frame where it is. When moving them later, blank frames are added to the end
+    // so nothing is lost, and when moving them earlier, the inputs moved to before the first frame are dropped.
+    pub fn shift_inputs(&mut self, offset: isize) {
+        let (first_mouse, last_mouse) = match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => ((first.mouse_x, first.mouse_y), (last.mouse_x, last.mouse_y)),
+            _ => return,
+        };
+        let mut inputs = self
+            .frames
+            .iter_mut()
+            .map(|frame| (frame.mouse_x, frame.mouse_y, std::mem::take(&mut frame.inputs)))
+            .collect::<Vec<_>>();
+        if offset > 0 {
+            let fps = self.frames[self.frames.len() - 1].fps;
+            self.insert_blank_frames(self.frames.len(), offset as usize, fps);
+        }
+        for (i, frame) in self.frames.iter_mut().enumerate() {
+            let source = i as isize - offset;
+            let (x, y) = if source < 0 {
+                first_mouse
+            } else if let Some((x, y, inputs)) = inputs.get_mut(source as usize) {
+                frame.inputs = std::mem::take(inputs);
+                (*x, *y)
+            } else {
+                last_mouse
+            };
+            frame.mouse_x = x;
+            frame.mouse_y = y;
+        }
+        self.clear_hashes(0);
+    }
+
+    // Forgets the hashes of every frame from `from` onwards, which won't be right any more after the replay is edited
+    pub fn clear_hashes(&mut self, from: usize) {
+        for frame in self.frames.iter_mut().skip(from) {
+            frame.hash = None;
+        }
+    }
+
+    // Works out whether a key or mouse button is held after each frame's inputs, assuming it isn't at the start
+    pub fn held(&self, button: Button) -> Vec<bool> {
+        let mut held = false;
+        self.frames
+            .iter()
+            .map(|frame| {
+                for press in frame.inputs.iter().filter_map(|input| input.presses(button)) {
+                    held = press;
+                }
+                held
+            })
+            .collect()
+    }
+
+    // Makes a key or mouse button held or not after one frame's inputs, leaving it the same as before after
+    // every other frame. That means changing the next frame's inputs too. Any other presses and releases of it
+    // on the edited frame are lost, since the edit replaces them.
+    pub fn set_held(&mut self, index: usize, button: Button, held: bool) {
+        let states = self.held(button);
+        if states.get(index).map_or(true, |state| *state == held) {
+            return
+        }
+        let before = index.checked_sub(1).map_or(false, |i| states[i]);
+        let frame = &mut self.frames[index];
+        frame.inputs.retain(|input| input.presses(button).is_none());
+        if held != before {
+            frame.inputs.push(Input::new(button, held));
+        }
+
+        // The next frame now starts with the button the other way round. If its first input for the button
+        // used to change it to how it is now, that isn't needed, otherwise it has to be changed back first.
+        if let Some(next) = self.frames.get_mut(index + 1) {
+            match next.inputs.iter().position(|input| input.presses(button).is_some()) {
+                Some(i) if next.inputs[i].presses(button) == Some(held) => {
+                    next.inputs.remove(i);
+                },
+                i => next.inputs.insert(i.unwrap_or(next.inputs.len()), Input::new(button, !held)),
+            }
+        }
+        self.clear_hashes(index);
+    }
+
+    fn insert_frames(&mut self, at: usize, frames: Vec<Frame>) {
+        let count = frames.len();
+        self.frames.splice(at..at, frames);
+        for frame in self.tampered.iter_mut() {
+            if *frame > at {
+                *frame += count;
+            }
+        }
+        self.clear_hashes(at);
+    }
+
+    // Notes that the game's state was tampered with before the next frame
+    pub fn mark_tampered(&mut self) {
+        let frame = self.frames.len();
+        if self.tampered.last() != Some(&frame) {
+            self.tampered.push(frame);
+        }
+    }
+
+    // Gets the hash of every instance as of the end of the replay, as recorded
+    pub fn instance_hashes(&self) -> BTreeMap<ID, u64> {
+        self.instance_hashes_at(self.frames.len())
+    }
+
+    // Gets the hash of every instance as of the end of the first frame_count frames, as recorded
+    pub fn instance_hashes_at(&self, frame_count: usize) -> BTreeMap<ID, u64> {
+        let mut instances = BTreeMap::new();
+        for frame in self.frames.iter().take(frame_count) {
+            match &frame.hash {
+                Some(hash) => hash.apply(&mut instances),
+                None => instances.clear(),
+            }
+        }
+        instances
+    }
+
+    // Gets the frame counts at which the game's state was tampered with, in order
+    pub fn tampered_frames(&self) -> &[usize] {
+        &self.tampered
+    }
+
     // Calculates the length of this replay in milliseconds
     pub fn get_length(&self) -> f64 {
         // We want to do this in a way that'll avoid FPI as much as possible (for example in a 60FPS game)
@@ -110,3 +372,221 @@ impl Replay {
         ms
     }
 }
+
+impl Input {
+    fn new(button: Button, press: bool) -> Self {
+        match (button, press) {
+            (Button::Key(key), true) => Input::KeyPress(key),
+            (Button::Key(key), false) => Input::KeyRelease(key),
+            (Button::Mouse(button), true) => Input::MousePress(button),
+            (Button::Mouse(button), false) => Input::MouseRelease(button),
+        }
+    }
+
+    // Whether this input presses (true) or releases (false) the given button, or None if it's about something else
+    fn presses(&self, button: Button) -> Option<bool> {
+        match (self, button) {
+            (Input::KeyPress(key), Button::Key(other)) if *key == other => Some(true),
+            (Input::KeyRelease(key), Button::Key(other)) if *key == other => Some(false),
+            (Input::MousePress(button), Button::Mouse(other)) if *button == other => Some(true),
+            (Input::MouseRelease(button), Button::Mouse(other)) if *button == other => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl FrameHash {
+    // Makes a frame hash from the instance hashes after the previous frame and after this one
+    pub fn new(state: u64, previous: &BTreeMap<ID, u64>, current: &BTreeMap<ID, u64>) -> Self {
+        Self {
+            state,
+            changed: current
+                .iter()
+                .filter(|(id, hash)| previous.get(id) != Some(hash))
+                .map(|(&id, &hash)| (id, hash))
+                .collect(),
+            removed: previous.keys().filter(|id| !current.contains_key(id)).copied().collect(),
+        }
+    }
+
+    // Updates the previous frame's instance hashes to this frame's
+    pub fn apply(&self, instances: &mut BTreeMap<ID, u64>) {
+        for id in self.removed.iter() {
+            instances.remove(id);
+        }
+        instances.extend(self.changed.iter().copied());
+    }
+}
+
+// The layout replays had before files got headers, when they were plain bincode. Since then, the tampered list and
+// frame hashes have been added.
+pub mod legacy {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Frame {
+        pub fps: u32,
+        pub mouse_x: f64,
+        pub mouse_y: f64,
+        pub inputs: Vec<Input>,
+        pub events: Vec<Event>,
+        pub new_seed: Option<i32>,
+        pub new_time: Option<u128>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Replay0 {
+        pub start_time: u128,
+        pub start_seed: i32,
+        pub frames: Vec<Frame>,
+    }
+
+    impl From<Frame> for super::Frame {
+        fn from(frame: Frame) -> Self {
+            Self {
+                fps: frame.fps,
+                mouse_x: frame.mouse_x,
+                mouse_y: frame.mouse_y,
+                inputs: frame.inputs,
+                events: frame.events,
+                new_seed: frame.new_seed,
+                new_time: frame.new_time,
+                hash: None,
+            }
+        }
+    }
+
+    impl From<Replay0> for Replay {
+        fn from(replay: Replay0) -> Self {
+            Self {
+                start_time: replay.start_time,
+                start_seed: replay.start_seed,
+                frames: replay.frames.into_iter().map(super::Frame::from).collect(),
+                tampered: Vec::new(),
+            }
+        }
+    }
+
+    pub fn migrate(data: &[u8]) -> Result<Replay, Box<dyn Error>> {
+        bincode::deserialize::<Replay0>(data).map(Replay::from).map_err(|e| {
+            format!("couldn't read replay, which is from an old version with no format header: {}", e).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_hash_changes() {
+        let first = [(100001, 1), (100002, 2)].iter().copied().collect::<BTreeMap<_, _>>();
+        let second = [(100002, 2), (100003, 3)].iter().copied().collect::<BTreeMap<_, _>>();
+        let mut replay = Replay::new(0, 0);
+        replay.new_frame(50).hash = Some(FrameHash::new(0, &BTreeMap::new(), &first));
+        let hash = FrameHash::new(0, &first, &second);
+        assert_eq!(hash.changed, vec![(100003, 3)]);
+        assert_eq!(hash.removed, vec![100001]);
+        replay.new_frame(50).hash = Some(hash);
+        assert_eq!(replay.instance_hashes(), second);
+
+        // a frame without a hash means nothing is known about the instances any more
+        replay.new_frame(50);
+        assert!(replay.instance_hashes().is_empty());
+    }
+
+    #[test]
+    fn editing() {
+        let mut replay = Replay::new(0, 0);
+        for i in 0..4 {
+            let frame = replay.new_frame(50);
+            frame.inputs.push(Input::KeyPress(Key::from_winapi(b'A' + i).unwrap()));
+            frame.mouse_x = f64::from(i);
+            frame.hash = Some(FrameHash::new(0, &BTreeMap::new(), &BTreeMap::new()));
+        }
+        replay.mark_tampered();
+        let keys = |replay: &Replay| {
+            (0..replay.frame_count())
+                .map(|i| match replay.get_frame(i).unwrap().inputs.first() {
+                    Some(Input::KeyPress(key)) => format!("{:?}", key),
+                    _ => String::from("_"),
+                })
+                .collect::<String>()
+        };
+
+        replay.insert_blank_frames(1, 2, 30);
+        assert_eq!(keys(&replay), "A__BCD");
+        assert_eq!(replay.get_frame(2).unwrap().mouse_x, 0.0);
+        assert!(replay.get_frame(0).unwrap().hash.is_some());
+        assert!(replay.get_frame(5).unwrap().hash.is_none());
+        assert_eq!(replay.tampered_frames(), &[6]);
+
+        replay.delete_frames(0, 2);
+        assert_eq!(keys(&replay), "_BCD");
+        assert_eq!(replay.tampered_frames(), &[4]);
+
+        let other = replay.clone();
+        replay.splice(4, &other, 1..3);
+        assert_eq!(keys(&replay), "_BCDBC");
+
+        replay.shift_inputs(-1);
+        assert_eq!(keys(&replay), "BCDBC_");
+        assert_eq!(replay.get_frame(5).unwrap().mouse_x, 2.0);
+        replay.shift_inputs(2);
+        assert_eq!(keys(&replay), "__BCDBC_");
+        assert_eq!(replay.get_frame(0).unwrap().mouse_x, 1.0);
+
+        replay.truncate(3);
+        assert_eq!(keys(&replay), "__B");
+        assert!(replay.tampered_frames().is_empty());
+    }
+
+    #[test]
+    fn setting_held() {
+        let a = Button::Key(Key::A);
+        let mut replay = Replay::new(0, 0);
+        for _ in 0..5 {
+            replay.new_frame(50);
+        }
+        replay.get_frame_mut(1).unwrap().inputs.push(Input::KeyPress(Key::A));
+        replay.get_frame_mut(3).unwrap().inputs.push(Input::KeyRelease(Key::A));
+        replay.get_frame_mut(3).unwrap().hash = Some(FrameHash::new(0, &BTreeMap::new(), &BTreeMap::new()));
+        assert_eq!(replay.held(a), vec![false, true, true, false, false]);
+
+        replay.set_held(2, a, false);
+        assert_eq!(replay.held(a), vec![false, true, false, false, false]);
+        assert!(replay.get_frame(3).unwrap().hash.is_none());
+        replay.set_held(1, a, false);
+        assert_eq!(replay.held(a), vec![false; 5]);
+        assert!((0..5).all(|i| replay.get_frame(i).unwrap().inputs.is_empty()));
+        replay.set_held(3, a, true);
+        assert_eq!(replay.held(a), vec![false, false, false, true, false]);
+        replay.set_held(4, a, true);
+        assert_eq!(replay.held(a), vec![false, false, false, true, true]);
+        assert!(replay.get_frame(4).unwrap().inputs.is_empty());
+        replay.set_held(1, a, true);
+        assert_eq!(replay.held(a), vec![false, true, false, true, true]);
+        assert!(replay.held(Button::Mouse(MouseButton::Left)).iter().all(|held| !held));
+    }
+
+    #[test]
+    fn legacy_migration() {
+        let frame = legacy::Frame {
+            fps: 50,
+            mouse_x: 1.0,
+            mouse_y: 2.0,
+            inputs: vec![Input::MouseWheelUp],
+            events: Vec::new(),
+            new_seed: Some(3),
+            new_time: None,
+        };
+        let old = legacy::Replay0 { start_time: 4, start_seed: 5, frames: vec![frame] };
+        let replay = legacy::migrate(&bincode::serialize(&old).unwrap()).unwrap();
+        assert_eq!((replay.start_time, replay.start_seed, replay.frame_count()), (4, 5, 1));
+        assert_eq!(replay.get_frame(0).unwrap().new_seed, Some(3));
+        assert!(replay.get_frame(0).unwrap().hash.is_none());
+        assert!(replay.tampered_frames().is_empty());
+
+        assert!(legacy::migrate(b"nonsense").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+// Conversion between replays and libTAS movies (.ltm), so that movies can be moved between the two and checked
+// against each other. A .ltm is a gzipped tarball with a config.ini describing the movie, and an inputs file with a
+// line for each frame listing the X keysyms of the keys held, then the mouse position and which buttons are held.
+//
+// libTAS records which keys and buttons are held on each frame rather than when they're pressed and released, so a key
+// pressed and released within a single frame is lost, and the mouse wheel is stored as buttons 4 and 5 being held.
+// It also only has one framerate and knows nothing about the RNG, stored events or frame hashes, so the framerate is
+// taken from the first frame, the starting seed is kept in a section of config.ini which libTAS ignores, and the rest
+// is dropped.
+
+use super::{Frame, Input, Replay};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use ini::Ini;
+use shared::input::{Key, MouseButton};
+use std::{
+    error::Error,
+    fmt::Write as _,
+    io::{self, Read, Write},
+};
+
+// The config.ini section for things libTAS doesn't have
+const GM8_SECTION: &str = "gm8emulator";
+
+// The libTAS version whose movie format this writes
+const LIBTAS_VERSION: (u32, u32, u32) = (1, 4, 0);
+
+// X keysyms for keys. Where keys share a keysym, such as Control and LeftControl, the first is used when importing.
+#[rustfmt::skip]
+const KEYSYMS: &[(Key, u32)] = &[
+    (Key::Add, 0xffab), (Key::Alt, 0xffe9), (Key::Backspace, 0xff08), (Key::CapsLock, 0xffe5),
+    (Key::Control, 0xffe3), (Key::LeftControl, 0xffe3), (Key::RightControl, 0xffe4),
+    (Key::Comma, 0x2c), (Key::Decimal, 0xffae), (Key::Delete, 0xffff), (Key::Divide, 0xffaf),
+    (Key::End, 0xff57), (Key::Enter, 0xff0d), (Key::Escape, 0xff1b),
+    (Key::F1, 0xffbe), (Key::F2, 0xffbf), (Key::F3, 0xffc0), (Key::F4, 0xffc1), (Key::F5, 0xffc2),
+    (Key::F6, 0xffc3), (Key::F7, 0xffc4), (Key::F8, 0xffc5), (Key::F9, 0xffc6), (Key::F10, 0xffc7),
+    (Key::F11, 0xffc8), (Key::F12, 0xffc9),
+    (Key::Home, 0xff50), (Key::Insert, 0xff63),
+    (Key::Left, 0xff51), (Key::Up, 0xff52), (Key::Right, 0xff53), (Key::Down, 0xff54),
+    (Key::Shift, 0xffe1), (Key::LeftShift, 0xffe1), (Key::RightShift, 0xffe2),
+    (Key::LeftWin, 0xffeb), (Key::RightWin, 0xffec),
+    (Key::Minus, 0x2d), (Key::Multiply, 0xffaa), (Key::NumLock, 0xff7f),
+    (Key::Numpad0, 0xffb0), (Key::Numpad1, 0xffb1), (Key::Numpad2, 0xffb2), (Key::Numpad3, 0xffb3),
+    (Key::Numpad4, 0xffb4), (Key::Numpad5, 0xffb5), (Key::Numpad6, 0xffb6), (Key::Numpad7, 0xffb7),
+    (Key::Numpad8, 0xffb8), (Key::Numpad9, 0xffb9),
+    (Key::OEM1, 0x3b), (Key::OEM2, 0x2f), (Key::OEM3, 0x60), (Key::OEM4, 0x5b), (Key::OEM5, 0x5c),
+    (Key::OEM6, 0x5d), (Key::OEM7, 0x27), (Key::OEM102, 0x3c),
+    (Key::PageDown, 0xff56), (Key::PageUp, 0xff55), (Key::Pause, 0xff13), (Key::Period, 0x2e),
+    (Key::Plus, 0x3d), (Key::PrintScreen, 0xff61), (Key::ScrollLock, 0xff14), (Key::Space, 0x20),
+    (Key::Subtract, 0xffad), (Key::Tab, 0xff09),
+    (Key::NumRow0, 0x30), (Key::NumRow1, 0x31), (Key::NumRow2, 0x32), (Key::NumRow3, 0x33), (Key::NumRow4, 0x34),
+    (Key::NumRow5, 0x35), (Key::NumRow6, 0x36), (Key::NumRow7, 0x37), (Key::NumRow8, 0x38), (Key::NumRow9, 0x39),
+    (Key::A, 0x61), (Key::B, 0x62), (Key::C, 0x63), (Key::D, 0x64), (Key::E, 0x65), (Key::F, 0x66),
+    (Key::G, 0x67), (Key::H, 0x68), (Key::I, 0x69), (Key::J, 0x6a), (Key::K, 0x6b), (Key::L, 0x6c),
+    (Key::M, 0x6d), (Key::N, 0x6e), (Key::O, 0x6f), (Key::P, 0x70), (Key::Q, 0x71), (Key::R, 0x72),
+    (Key::S, 0x73), (Key::T, 0x74), (Key::U, 0x75), (Key::V, 0x76), (Key::W, 0x77), (Key::X, 0x78),
+    (Key::Y, 0x79), (Key::Z, 0x7a),
+];
+
+impl Replay {
+    // Writes the replay as a libTAS movie
+    pub fn write_ltm(&self, writer: impl Write) -> Result<(), Box<dyn Error>> {
+        let fps = self.frames.first().map(|frame| frame.fps).unwrap_or(30);
+        let mut config = Ini::new();
+        config
+            .with_section(Some("General"))
+            .set("frame_count", self.frames.len().to_string())
+            .set("keyboard_support", "true")
+            .set("mouse_support", "true")
+            .set("nb_controllers", "0")
+            .set("initial_time_sec", (self.start_time / 1_000_000_000).to_string())
+            .set("initial_time_nsec", (self.start_time % 1_000_000_000).to_string())
+            .set("framerate_num", fps.to_string())
+            .set("framerate_den", "1")
+            .set("rerecord_count", "0")
+            .set("libtas_major_version", LIBTAS_VERSION.0.to_string())
+            .set("libtas_minor_version", LIBTAS_VERSION.1.to_string())
+            .set("libtas_patch_version", LIBTAS_VERSION.2.to_string());
+        config.with_section(Some(GM8_SECTION)).set("start_seed", self.start_seed.to_string());
+        let mut config_data = Vec::new();
+        config.write_to(&mut config_data)?;
+
+        let mut inputs = String::new();
+        let mut keys = Vec::new();
+        let mut buttons = [false; 5];
+        for frame in self.frames.iter() {
+            // the wheel is only "held" on the frames it moves
+            buttons[3] = false;
+            buttons[4] = false;
+            for input in frame.inputs.iter() {
+                match input {
+                    Input::KeyPress(key) => {
+                        if let Some(keysym) = keysym(*key).filter(|keysym| !keys.contains(keysym)) {
+                            keys.push(keysym);
+                        }
+                    },
+                    Input::KeyRelease(key) => {
+                        if let Some(keysym) = keysym(*key) {
+                            keys.retain(|held| *held != keysym);
+                        }
+                    },
+                    Input::MousePress(button) => buttons[button_index(*button)] = true,
+                    Input::MouseRelease(button) => buttons[button_index(*button)] = false,
+                    Input::MouseWheelUp => buttons[3] = true,
+                    Input::MouseWheelDown => buttons[4] = true,
+                }
+            }
+
+            inputs.push_str("|K");
+            for (i, keysym) in keys.iter().enumerate() {
+                let _ = write!(inputs, "{}{:x}", if i > 0 { ":" } else { "" }, keysym);
+            }
+            let _ = write!(inputs, "|M{}:{}:A:", frame.mouse_x.round() as i32, frame.mouse_y.round() as i32);
+            for (i, held) in buttons.iter().enumerate() {
+                inputs.push(if *held { (b'1' + i as u8).into() } else { '.' });
+            }
+            inputs.push_str("|\n");
+        }
+
+        let mut archive = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        append_file(&mut archive, "config.ini", &config_data)?;
+        append_file(&mut archive, "inputs", inputs.as_bytes())?;
+        archive.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    // Reads a libTAS movie. Keys which the emulator doesn't have are ignored.
+    pub fn read_ltm(reader: impl Read) -> Result<Self, Box<dyn Error>> {
+        let mut config = None;
+        let mut inputs = None;
+        let mut archive = tar::Archive::new(GzDecoder::new(reader));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let file = match entry.path()?.to_str() {
+                Some("config.ini") => &mut config,
+                Some("inputs") => &mut inputs,
+                _ => continue,
+            };
+            let mut data = String::new();
+            entry.read_to_string(&mut data)?;
+            *file = Some(data);
+        }
+        let config = Ini::load_from_str(&config.ok_or("the movie has no config.ini")?)?;
+        let inputs = inputs.ok_or("the movie has no inputs")?;
+
+        let general = config.section(Some("General")).ok_or("the movie's config.ini has no General section")?;
+        let number = |key| general.get(key).and_then(|value| value.parse::<u64>().ok());
+        let fps = match (number("framerate_num"), number("framerate_den")) {
+            (Some(num), Some(den)) if den != 0 => ((num + den / 2) / den) as u32,
+            _ => return Err("the movie has no framerate".into()),
+        };
+        let start_time = u128::from(number("initial_time_sec").unwrap_or(0)) * 1_000_000_000
+            + u128::from(number("initial_time_nsec").unwrap_or(0));
+        let start_seed = config
+            .section(Some(GM8_SECTION))
+            .and_then(|section| section.get("start_seed"))
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(0);
+        // older movies don't label what's on each line, so what's there has to be worked out from the config
+        let mut layout = Vec::new();
+        if general.get("keyboard_support") != Some("false") {
+            layout.push('K');
+        }
+        if general.get("mouse_support") != Some("false") {
+            layout.push('M');
+        }
+
+        let mut replay = Self::new(start_time, start_seed);
+        let mut keys = Vec::new();
+        let mut buttons = [false; 3];
+        for (i, line) in inputs.lines().enumerate() {
+            read_ltm_frame(line, replay.new_frame(fps), &layout, &mut keys, &mut buttons)
+                .map_err(|e| format!("line {} of the movie's inputs: {}", i + 1, e))?;
+        }
+        Ok(replay)
+    }
+}
+
+// Reads a line of a movie's inputs into a frame, given which keys and mouse buttons were held on the frame before
+fn read_ltm_frame(
+    line: &str,
+    frame: &mut Frame,
+    layout: &[char],
+    keys: &mut Vec<Key>,
+    buttons: &mut [bool; 3],
+) -> Result<(), String> {
+    let parts = line.trim().split('|').collect::<Vec<_>>();
+    if parts.len() < 2 || !parts[0].is_empty() || !parts[parts.len() - 1].is_empty() {
+        return Err(String::from("expected it to start and end with |"))
+    }
+    let mut unlabeled = layout.iter();
+    for part in &parts[1..parts.len() - 1] {
+        let (kind, part) = match part.chars().next() {
+            Some(c) if c.is_ascii_uppercase() => (c, &part[1..]),
+            _ => (unlabeled.next().copied().unwrap_or_default(), *part),
+        };
+        match kind {
+            'K' => {
+                let mut held = Vec::new();
+                for keysym in part.split(':').filter(|keysym| !keysym.is_empty()) {
+                    let keysym = u32::from_str_radix(keysym, 16).map_err(|_| format!("invalid keysym {}", keysym))?;
+                    held.extend(key(keysym));
+                }
+                for key in keys.iter().filter(|key| !held.contains(key)) {
+                    frame.inputs.push(Input::KeyRelease(*key));
+                }
+                for key in held.iter().filter(|key| !keys.contains(key)) {
+                    frame.inputs.push(Input::KeyPress(*key));
+                }
+                *keys = held;
+            },
+            'M' => {
+                let fields = part.split(':').collect::<Vec<_>>();
+                let (x, y, held) = match fields.as_slice() {
+                    [x, y, "A", held] | [x, y, held] => (x, y, held),
+                    [_, _, _, _] => return Err(String::from("relative mouse movement isn't supported")),
+                    _ => return Err(format!("can't make sense of mouse input {}", part)),
+                };
+                frame.mouse_x = x.parse::<i32>().map_err(|_| format!("invalid mouse x {}", x))?.into();
+                frame.mouse_y = y.parse::<i32>().map_err(|_| format!("invalid mouse y {}", y))?.into();
+                let held = held.chars().map(|c| c != '.').chain(std::iter::repeat(false)).take(5).collect::<Vec<_>>();
+                for (i, button) in [MouseButton::Left, MouseButton::Middle, MouseButton::Right].iter().enumerate() {
+                    match (buttons[i], held[i]) {
+                        (false, true) => frame.inputs.push(Input::MousePress(*button)),
+                        (true, false) => frame.inputs.push(Input::MouseRelease(*button)),
+                        _ => (),
+                    }
+                    buttons[i] = held[i];
+                }
+                if held[3] {
+                    frame.inputs.push(Input::MouseWheelUp);
+                }
+                if held[4] {
+                    frame.inputs.push(Input::MouseWheelDown);
+                }
+            },
+            // controllers, flags and anything else the emulator doesn't have
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn keysym(key: Key) -> Option<u32> {
+    KEYSYMS.iter().find(|(k, _)| *k == key).map(|(_, keysym)| *keysym)
+}
+
+fn key(keysym: u32) -> Option<Key> {
+    // uppercase letters are the same keys as lowercase ones
+    let keysym = if (0x41..=0x5a).contains(&keysym) { keysym + 0x20 } else { keysym };
+    KEYSYMS.iter().find(|(_, k)| *k == keysym).map(|(key, _)| *key)
+}
+
+// X button numbers, minus one
+fn button_index(button: MouseButton) -> usize {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    }
+}
+
+fn append_file(archive: &mut tar::Builder<impl Write>, name: &str, data: &[u8]) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    archive.append_data(&mut header, name, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(1_500_000_000, 42);
+        let frame = replay.new_frame(50);
+        frame.inputs.push(Input::KeyPress(Key::Left));
+        frame.inputs.push(Input::KeyPress(Key::Z));
+        let frame = replay.new_frame(50);
+        frame.mouse_x = 100.0;
+        frame.mouse_y = 200.0;
+        frame.inputs.push(Input::KeyRelease(Key::Left));
+        frame.inputs.push(Input::MousePress(MouseButton::Right));
+        frame.inputs.push(Input::MouseWheelUp);
+        let frame = replay.new_frame(50);
+        frame.inputs.push(Input::KeyRelease(Key::Z));
+        frame.inputs.push(Input::MouseRelease(MouseButton::Right));
+
+        let mut data = Vec::new();
+        replay.write_ltm(&mut data).unwrap();
+        let read = Replay::read_ltm(data.as_slice()).unwrap();
+        assert_eq!(read.to_text(0), replay.to_text(0));
+
+        // an older movie, which doesn't label the keyboard and mouse
+        let frame = "||10:20:1....|\n";
+        let mut data = Vec::new();
+        let mut archive = tar::Builder::new(GzEncoder::new(&mut data, Compression::default()));
+        append_file(&mut archive, "config.ini", b"[General]\nframerate_num=60\nframerate_den=1\n").unwrap();
+        append_file(&mut archive, "inputs", frame.as_bytes()).unwrap();
+        archive.into_inner().unwrap().finish().unwrap();
+        let read = Replay::read_ltm(data.as_slice()).unwrap();
+        assert_eq!(read.frame_text(0).unwrap(), "60 mouse=10,20 +LMB");
+    }
+}
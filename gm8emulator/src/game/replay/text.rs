@@ -0,0 +1,325 @@
+// A line-based text format for replays, so they can be diffed, reviewed and edited by hand.
+// It holds everything a .gmtas does, so converting either way loses nothing.
+//
+// Blank lines and lines starting with # are ignored. Header lines are a name followed by values:
+//   exe_hash <hex>        the game the replay was made with, which is optional when writing a replay by hand
+//   start_time <nanos>
+//   start_seed <seed>
+//   tampered <frame>...   frame counts at which the game's state was tampered with, if any
+// Every other line is a frame, in order. It starts with the frame's fps, followed by any of these:
+//   mouse=<x>,<y>         the mouse position, if it's different from the previous frame's, or from 0,0 on the first
+//   +<key> -<key>         a key being pressed or released, named as in shared::input::Key
+//   +LMB -RMB +MMB ...    a mouse button being pressed or released
+//   wheel_up wheel_down   the mouse wheel moving
+//   seed=<seed>           the RNG seed being set before the frame
+//   time=<nanos>          the spoofed time being set before the frame
+//   get_integer=<value> get_string=<value> show_menu=<value> show_question=<value> randomize=<seed> show_message
+//                         stored events, where a value is either a number or a "quoted string"
+//   hash=<hex> hash+<id>=<hex> hash-<id>
+//                         the frame's state hash, and the hashes of instances which changed or stopped existing
+// Inputs and events happen in the order they're written in.
+
+use super::{Event, Frame, FrameHash, Input, Replay};
+use crate::gml::Value;
+use shared::{input::Button, types::ID};
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+// The part of a token after its =
+enum Arg<'a> {
+    Plain(&'a str),
+    Quoted(String),
+}
+
+impl Replay {
+    // Writes the replay as text, for the game with the given exe hash
+    pub fn to_text(&self, exe_hash: u64) -> String {
+        let mut text = String::new();
+        // writing to a String can't fail
+        let _ = self.write_text(&mut text, exe_hash);
+        text
+    }
+
+    // Reads a replay from text written by to_text or by hand, along with its exe hash if it has one.
+    // Returns an error naming the line if something on it doesn't make sense.
+    pub fn from_text(text: &str) -> Result<(Self, Option<u64>), String> {
+        let mut replay = Self::new(0, 0);
+        let mut exe_hash = None;
+        for (i, line) in text.lines().enumerate() {
+            replay.read_line(line, &mut exe_hash).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok((replay, exe_hash))
+    }
+
+    // Writes a single frame as it would be in the text format, except that the mouse position is always included
+    pub fn frame_text(&self, index: usize) -> Option<String> {
+        let mut text = String::new();
+        let _ = write_frame(&mut text, self.frames.get(index)?, None);
+        Some(text)
+    }
+
+    fn write_text(&self, out: &mut String, exe_hash: u64) -> fmt::Result {
+        writeln!(out, "exe_hash {:016x}", exe_hash)?;
+        writeln!(out, "start_time {}", self.start_time)?;
+        writeln!(out, "start_seed {}", self.start_seed)?;
+        if !self.tampered.is_empty() {
+            write!(out, "tampered")?;
+            for frame in self.tampered.iter() {
+                write!(out, " {}", frame)?;
+            }
+            writeln!(out)?;
+        }
+
+        let mut mouse = (0.0, 0.0);
+        for frame in self.frames.iter() {
+            write_frame(out, frame, Some(mouse))?;
+            writeln!(out)?;
+            mouse = (frame.mouse_x, frame.mouse_y);
+        }
+        Ok(())
+    }
+
+    fn read_line(&mut self, line: &str, exe_hash: &mut Option<u64>) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(())
+        }
+        let mut tokens = tokenize(line)?.into_iter();
+        let (name, _) = tokens.next().unwrap(); // the line isn't empty, so it has a token
+        let mut values = || tokens.next().map(|(name, _)| name).ok_or_else(|| format!("missing value for {}", name));
+        match name {
+            "exe_hash" => *exe_hash = Some(parse_hex(values()?)?),
+            "start_time" => self.start_time = parse(values()?)?,
+            "start_seed" => self.start_seed = parse(values()?)?,
+            "tampered" => {
+                for (frame, _) in tokens {
+                    self.tampered.push(parse(frame)?);
+                }
+            },
+            fps => self.read_frame(parse(fps)?, tokens)?,
+        }
+        Ok(())
+    }
+
+    fn read_frame<'a>(
+        &mut self,
+        fps: u32,
+        tokens: impl Iterator<Item = (&'a str, Option<Arg<'a>>)>,
+    ) -> Result<(), String> {
+        let frame = self.new_frame(fps);
+        for (name, arg) in tokens {
+            match (name, arg) {
+                ("mouse", Some(Arg::Plain(pos))) => {
+                    let mut coords = pos.splitn(2, ',');
+                    frame.mouse_x = parse(coords.next().unwrap_or_default())?;
+                    frame.mouse_y = parse(coords.next().unwrap_or_default())?;
+                },
+                ("wheel_up", None) => frame.inputs.push(Input::MouseWheelUp),
+                ("wheel_down", None) => frame.inputs.push(Input::MouseWheelDown),
+                ("seed", Some(Arg::Plain(seed))) => frame.new_seed = Some(parse(seed)?),
+                ("time", Some(Arg::Plain(time))) => frame.new_time = Some(parse(time)?),
+                ("get_integer", Some(value)) => frame.events.push(Event::GetInteger(parse_value(value)?)),
+                ("get_string", Some(value)) => frame.events.push(Event::GetString(parse_value(value)?)),
+                ("randomize", Some(Arg::Plain(seed))) => frame.events.push(Event::Randomize(parse(seed)?)),
+                ("show_menu", Some(value)) => frame.events.push(Event::ShowMenu(parse_value(value)?)),
+                ("show_message", None) => frame.events.push(Event::ShowMessage),
+                ("show_question", Some(value)) => frame.events.push(Event::ShowQuestion(parse_value(value)?)),
+                ("hash", Some(Arg::Plain(state))) => {
+                    frame.hash = Some(FrameHash { state: parse_hex(state)?, changed: Vec::new(), removed: Vec::new() })
+                },
+                (name, arg) if name.starts_with("hash+") || name.starts_with("hash-") => {
+                    let hash = frame.hash.as_mut().ok_or_else(|| format!("{} must come after hash=", name))?;
+                    let id = parse::<ID>(&name[5..])?;
+                    match (name.as_bytes()[4], arg) {
+                        (b'+', Some(Arg::Plain(instance))) => hash.changed.push((id, parse_hex(instance)?)),
+                        (b'-', None) => hash.removed.push(id),
+                        _ => return Err(format!("can't make sense of {}", name)),
+                    }
+                },
+                (name, None) if name.starts_with('+') || name.starts_with('-') => {
+                    let press = name.starts_with('+');
+                    let input = match (Button::from_name(&name[1..]), press) {
+                        (Some(Button::Key(key)), true) => Input::KeyPress(key),
+                        (Some(Button::Key(key)), false) => Input::KeyRelease(key),
+                        (Some(Button::Mouse(button)), true) => Input::MousePress(button),
+                        (Some(Button::Mouse(button)), false) => Input::MouseRelease(button),
+                        (None, _) => return Err(format!("unknown key {}", &name[1..])),
+                    };
+                    frame.inputs.push(input);
+                },
+                (name, _) => return Err(format!("can't make sense of {}", name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Writes a frame's line, without a line break, leaving out the mouse position if it's the same as the previous frame's
+fn write_frame(out: &mut String, frame: &Frame, previous_mouse: Option<(f64, f64)>) -> fmt::Result {
+    write!(out, "{}", frame.fps)?;
+    if Some((frame.mouse_x, frame.mouse_y)) != previous_mouse {
+        write!(out, " mouse={},{}", frame.mouse_x, frame.mouse_y)?;
+    }
+    for input in frame.inputs.iter() {
+        match input {
+            Input::KeyPress(key) => write!(out, " +{}", Button::Key(*key))?,
+            Input::KeyRelease(key) => write!(out, " -{}", Button::Key(*key))?,
+            Input::MousePress(button) => write!(out, " +{}", Button::Mouse(*button))?,
+            Input::MouseRelease(button) => write!(out, " -{}", Button::Mouse(*button))?,
+            Input::MouseWheelUp => write!(out, " wheel_up")?,
+            Input::MouseWheelDown => write!(out, " wheel_down")?,
+        }
+    }
+    if let Some(seed) = frame.new_seed {
+        write!(out, " seed={}", seed)?;
+    }
+    if let Some(time) = frame.new_time {
+        write!(out, " time={}", time)?;
+    }
+    for event in frame.events.iter() {
+        match event {
+            Event::GetInteger(value) => write!(out, " get_integer={}", quote(value))?,
+            Event::GetString(value) => write!(out, " get_string={}", quote(value))?,
+            Event::Randomize(seed) => write!(out, " randomize={}", seed)?,
+            Event::ShowMenu(value) => write!(out, " show_menu={}", quote(value))?,
+            Event::ShowMessage => write!(out, " show_message")?,
+            Event::ShowQuestion(value) => write!(out, " show_question={}", quote(value))?,
+        }
+    }
+    if let Some(hash) = &frame.hash {
+        write!(out, " hash={:016x}", hash.state)?;
+        for (id, instance) in hash.changed.iter() {
+            write!(out, " hash+{}={:016x}", id, instance)?;
+        }
+        for id in hash.removed.iter() {
+            write!(out, " hash-{}", id)?;
+        }
+    }
+    Ok(())
+}
+
+// Writes a value as a number, or as a string in quotes with quotes, backslashes and line breaks escaped
+fn quote(value: &Value) -> String {
+    match value {
+        Value::Real(real) => f64::from(*real).to_string(),
+        Value::Str(string) => {
+            let mut quoted = String::from("\"");
+            for c in string.as_ref().chars() {
+                match c {
+                    '"' => quoted.push_str("\\\""),
+                    '\\' => quoted.push_str("\\\\"),
+                    '\n' => quoted.push_str("\\n"),
+                    '\r' => quoted.push_str("\\r"),
+                    c => quoted.push(c),
+                }
+            }
+            quoted.push('"');
+            quoted
+        },
+    }
+}
+
+// Splits a line into tokens separated by whitespace, each being a name and optionally an = and an argument.
+// Arguments in quotes can have whitespace in them.
+fn tokenize(line: &str) -> Result<Vec<(&str, Option<Arg<'_>>)>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = &rest[name_end..];
+        let arg = if rest.starts_with('=') {
+            rest = &rest[1..];
+            if rest.starts_with('"') {
+                let (string, len) = unquote(rest).ok_or_else(|| format!("unterminated string after {}=", name))?;
+                rest = &rest[len..];
+                Some(Arg::Quoted(string))
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let arg = &rest[..end];
+                rest = &rest[end..];
+                Some(Arg::Plain(arg))
+            }
+        } else {
+            None
+        };
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return Err(format!("expected a space after {}", name))
+        }
+        tokens.push((name, arg));
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// Reads a string in quotes from the start of some text, returning it and how many bytes it took up
+fn unquote(text: &str) -> Option<(String, usize)> {
+    let mut string = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, i + 1)),
+            '\\' => match chars.next()?.1 {
+                'n' => string.push('\n'),
+                'r' => string.push('\r'),
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+    None
+}
+
+fn parse<T: FromStr>(text: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("can't make sense of {}", text))
+}
+
+fn parse_hex(text: &str) -> Result<u64, String> {
+    u64::from_str_radix(text, 16).map_err(|_| format!("can't make sense of hash {}", text))
+}
+
+fn parse_value(arg: Arg) -> Result<Value, String> {
+    match arg {
+        Arg::Plain(number) => parse::<f64>(number).map(Value::from),
+        Arg::Quoted(string) => Ok(Value::from(string)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::input::{Key, MouseButton};
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(123456789, -5);
+        let frame = replay.new_frame(50);
+        frame.inputs.push(Input::KeyPress(Key::Left));
+        frame.inputs.push(Input::MousePress(MouseButton::Left));
+        frame.inputs.push(Input::MouseWheelDown);
+        frame.new_seed = Some(7);
+        frame.events.push(Event::GetString(Value::from("a \"b\"\\\nc=d")));
+        frame.events.push(Event::GetInteger(Value::from(0.1)));
+        frame.events.push(Event::ShowMessage);
+        frame.hash = Some(FrameHash { state: 1, changed: vec![(100001, u64::MAX)], removed: vec![100002] });
+        replay.mark_tampered();
+        let frame = replay.new_frame(30);
+        frame.mouse_x = 10.5;
+        frame.inputs.push(Input::KeyRelease(Key::Left));
+        frame.new_time = Some(1000);
+        replay.new_frame(30);
+
+        let text = replay.to_text(99);
+        let (read, exe_hash) = Replay::from_text(&text).unwrap();
+        assert_eq!(exe_hash, Some(99));
+        assert_eq!(read.to_text(99), text);
+        assert_eq!(bincode::serialize(&read).unwrap(), bincode::serialize(&replay).unwrap());
+        assert_eq!(read.get_frame(2).unwrap().mouse_x, 10.5);
+
+        assert_eq!(read.frame_text(2).unwrap(), "30 mouse=10.5,0");
+        assert!(Replay::from_text("50 +NotAKey").is_err());
+        assert!(Replay::from_text("50 get_string=\"unterminated").is_err());
+    }
+}
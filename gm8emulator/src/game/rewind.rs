@@ -0,0 +1,124 @@
+use crate::game::{savestate, Assets, Game, Replay, SaveState};
+use std::{collections::VecDeque, rc::Rc};
+
+/// Savestates kept in memory while recording, taken every few frames, so that stepping back a bit
+/// doesn't need a named save. The replay isn't kept in them, since rewinding only ever cuts it short.
+pub struct Rewind {
+    /// How many frames apart the states are taken. 0 turns rewinding off.
+    pub interval: usize,
+
+    /// How many states are kept before the oldest ones start being dropped.
+    pub capacity: usize,
+
+    /// The states, oldest first, along with the frame count each was taken at.
+    states: VecDeque<(usize, SaveState)>,
+
+    /// The assets the latest state was taken with, along with their hash. Assets hardly ever change while playing,
+    /// so states share one copy of them for as long as the hash stays the same.
+    assets: Option<(u64, Rc<Assets>)>,
+}
+
+impl Rewind {
+    pub fn new(interval: usize, capacity: usize) -> Self {
+        Self { interval, capacity, states: VecDeque::new(), assets: None }
+    }
+
+    /// Takes a state if one is due at the given frame count.
+    pub fn capture(&mut self, game: &Game, frame_count: usize) {
+        if self.interval == 0 || self.capacity == 0 || frame_count % self.interval != 0 {
+            return
+        }
+        // A state at this frame count might already exist after rewinding and advancing again
+        while self.states.back().map_or(false, |(frame, _)| *frame >= frame_count) {
+            self.states.pop_back();
+        }
+        if self.states.len() >= self.capacity {
+            self.states.pop_front();
+        }
+        let hash = savestate::hash_assets(&game.assets);
+        let assets = match &self.assets {
+            Some((last_hash, assets)) if *last_hash == hash => assets.clone(),
+            _ => Rc::new(game.assets.clone()),
+        };
+        self.assets = Some((hash, assets.clone()));
+        self.states.push_back((frame_count, SaveState::with_assets(game, Replay::new(0, 0), assets)));
+    }
+
+    /// Loads the latest state taken before the given frame count, cutting the replay short to match.
+    /// Returns false if there's no such state, in which case nothing changes.
+    /// The state stays in the buffer, so rewinding again from there goes back further.
+    pub fn rewind(&mut self, game: &mut Game, replay: &mut Replay) -> Result<bool, String> {
+        let frame_count = match replay.frame_count().checked_sub(1) {
+            Some(frame_count) => frame_count,
+            None => return Ok(false),
+        };
+        match self.load_latest(game, frame_count)? {
+            Some(frame_count) => {
+                replay.truncate(frame_count);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Loads the latest state taken at or before the given frame count, forgetting any taken after it,
+    /// and returns the frame count it was taken at. Returns None if there's no such state, leaving the game as it is.
+    /// Fails if the state can't be loaded, as with SaveState::load_into.
+    pub fn load_latest(&mut self, game: &mut Game, frame_count: usize) -> Result<Option<usize>, String> {
+        while self.states.back().map_or(false, |(frame, _)| *frame > frame_count) {
+            self.states.pop_back();
+        }
+        match self.states.back() {
+            Some((frame, state)) => {
+                state.clone().load_into(game)?;
+                Ok(Some(*frame))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Forgets every state, for when the game's state is replaced by loading a savestate.
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.assets = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewind() {
+        let mut game = Game::new_sandbox();
+        let mut replay = Replay::new(0, 0);
+        let mut rewind = Rewind::new(2, 2);
+        for _ in 0..6 {
+            game.execute_console(&format!("global.t = {}", replay.frame_count()), None).unwrap();
+            rewind.capture(&game, replay.frame_count());
+            replay.new_frame(50);
+        }
+        let t = |game: &mut Game| i32::from(game.execute_console("global.t", None).unwrap());
+
+        // only the states from frames 2 and 4 are kept
+        assert!(rewind.rewind(&mut game, &mut replay).unwrap());
+        assert_eq!((replay.frame_count(), t(&mut game)), (4, 4));
+        assert!(rewind.rewind(&mut game, &mut replay).unwrap());
+        assert_eq!((replay.frame_count(), t(&mut game)), (2, 2));
+        assert!(!rewind.rewind(&mut game, &mut replay).unwrap());
+        assert_eq!(replay.frame_count(), 2);
+    }
+
+    #[test]
+    fn shared_assets() {
+        let mut game = Game::new_sandbox();
+        let mut rewind = Rewind::new(1, 3);
+        rewind.capture(&game, 0);
+        rewind.capture(&game, 1);
+        game.execute_console("path_add()", None).unwrap();
+        rewind.capture(&game, 2);
+        let assets = rewind.states.iter().map(|(_, state)| state.assets.clone()).collect::<Vec<_>>();
+        assert!(Rc::ptr_eq(&assets[0], &assets[1]));
+        assert!(!Rc::ptr_eq(&assets[1], &assets[2]));
+    }
+}
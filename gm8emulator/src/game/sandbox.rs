@@ -0,0 +1,219 @@
+// A game with no window and next to no assets, for running GML in isolation.
+// This is what the GML conformance tests and the fuzz targets run their code in.
+//
+// It has no sprites or anything else, just these objects and a single room:
+// 0: obj_parent
+// 1: obj_child, which has obj_parent as its parent
+// 2: obj_other
+//
+// Note that it's only isolated from the real game, not from the rest of the system: GML run in it can still
+// do things like write files.
+
+use crate::{
+    asset::{room::Room, Object},
+    game::{draw, particle, Assets, Background, Game, PlayType, Speed, Version, View, Watchpoints},
+    gml::{ds::DataStructureManager, file::FileManager, rand::Random, Compiler},
+    input::InputManager,
+    instance::DummyFieldHolder,
+    instancelist::{InstanceList, TileList},
+    math::Real,
+};
+use gmio::{
+    atlas::AtlasBuilder,
+    render::{Renderer, RendererOptions},
+    window::WindowBuilder,
+};
+use shared::types::Colour;
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    rc::Rc,
+};
+
+const ROOM_WIDTH: u32 = 640;
+const ROOM_HEIGHT: u32 = 480;
+
+fn object(name: &str, parent_index: i32) -> Option<Box<Object>> {
+    Some(Box::new(Object {
+        name: name.into(),
+        solid: false,
+        visible: true,
+        persistent: false,
+        depth: 0,
+        sprite_index: -1,
+        mask_index: -1,
+        parent_index,
+        events: Default::default(),
+        children: Rc::new(RefCell::new(HashSet::new())),
+    }))
+}
+
+impl Game {
+    /// Creates a game with no window and next to no assets, as described at the top of this file.
+    pub fn new_sandbox() -> Self {
+        let objects = vec![object("obj_parent", -1), object("obj_child", 0), object("obj_other", -1)];
+        // Each object counts as one of its own children, as well as its parents'
+        for i in 0..objects.len() {
+            let mut index = i as i32;
+            while let Some(Some(parent)) = objects.get(index as usize) {
+                parent.children.borrow_mut().insert(i as i32);
+                index = parent.parent_index;
+            }
+        }
+
+        let view = View {
+            visible: false,
+            source_x: 0,
+            source_y: 0,
+            source_w: ROOM_WIDTH,
+            source_h: ROOM_HEIGHT,
+            port_x: 0,
+            port_y: 0,
+            port_w: ROOM_WIDTH,
+            port_h: ROOM_HEIGHT,
+            angle: Real::from(0.0),
+            follow_target: -1,
+            follow_hborder: 32,
+            follow_vborder: 32,
+            follow_hspeed: -1,
+            follow_vspeed: -1,
+        };
+        let background = Background {
+            visible: false,
+            is_foreground: false,
+            background_id: -1,
+            x_offset: Real::from(0.0),
+            y_offset: Real::from(0.0),
+            tile_horizontal: true,
+            tile_vertical: true,
+            hspeed: Real::from(0.0),
+            vspeed: Real::from(0.0),
+            xscale: Real::from(1.0),
+            yscale: Real::from(1.0),
+            blend: 0xFFFFFF,
+            alpha: Real::from(1.0),
+        };
+        let room = Room {
+            name: "rm_test".into(),
+            caption: "".into(),
+            width: ROOM_WIDTH,
+            height: ROOM_HEIGHT,
+            speed: 30,
+            persistent: false,
+            bg_colour: Colour::new(0.0, 0.0, 0.0),
+            clear_screen: true,
+            creation_code: Vec::new().into(),
+            backgrounds: Rc::new(vec![background; 8]),
+            views_enabled: false,
+            views: Rc::new(vec![view; 8]),
+            instances: Rc::new(Vec::new()),
+            tiles: Rc::new(Vec::new()),
+        };
+
+        let mut compiler = Compiler::new();
+        for (i, object) in objects.iter().enumerate() {
+            if let Some(object) = object {
+                compiler.register_constant(object.name.to_string(), i as f64);
+            }
+        }
+        compiler.register_constant("rm_test".into(), 0.0);
+
+        let options = RendererOptions { size: (ROOM_WIDTH, ROOM_HEIGHT), vsync: false, interpolate_pixels: false };
+        let window = WindowBuilder::new().with_size(ROOM_WIDTH, ROOM_HEIGHT).build_headless();
+        let mut renderer = Renderer::new_headless(&options);
+        let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
+        let particle_shapes = particle::load_shapes(&mut atlases);
+        renderer.push_atlases(atlases).unwrap();
+
+        let mut game = Game {
+            compiler,
+            file_manager: FileManager::new(),
+            instance_list: InstanceList::new(),
+            tile_list: TileList::new(),
+            rand: Random::new(),
+            renderer,
+            background_colour: Colour::new(0.0, 0.0, 0.0),
+            externals: Vec::new(),
+            room_colour: None,
+            input_manager: InputManager::new(),
+            assets: Assets {
+                backgrounds: Vec::new(),
+                fonts: Vec::new(),
+                objects,
+                paths: Vec::new(),
+                rooms: vec![Some(Box::new(room))],
+                scripts: Vec::new(),
+                sprites: Vec::new(),
+                timelines: Vec::new(),
+                triggers: Vec::new(),
+            },
+            event_holders: Default::default(),
+            custom_draw_objects: HashSet::new(),
+            views_enabled: false,
+            view_current: 0,
+            views: Vec::new(),
+            backgrounds: Vec::new(),
+            particles: particle::Manager::new(particle_shapes),
+            room_id: 0,
+            room_width: ROOM_WIDTH as i32,
+            room_height: ROOM_HEIGHT as i32,
+            room_order: vec![0].into_boxed_slice(),
+            room_speed: 30,
+            scene_change: None,
+            constants: Vec::new(),
+            globals: DummyFieldHolder::new(),
+            globalvars: HashSet::new(),
+            game_start: true,
+            stacks: DataStructureManager::new(),
+            queues: DataStructureManager::new(),
+            lists: DataStructureManager::new(),
+            maps: DataStructureManager::new(),
+            priority_queues: DataStructureManager::new(),
+            grids: DataStructureManager::new(),
+            ds_precision: Real::from(0.00000001),
+            draw_font: None,
+            draw_font_id: -1,
+            draw_colour: Colour::new(0.0, 0.0, 0.0),
+            draw_alpha: Real::from(1.0),
+            draw_halign: draw::Halign::Left,
+            draw_valign: draw::Valign::Top,
+            surfaces: Vec::new(),
+            surface_target: None,
+            last_instance_id: 100000,
+            last_tile_id: 10000000,
+            uninit_fields_are_zero: false,
+            uninit_args_are_zero: false,
+            transition_kind: 0,
+            transition_steps: 80,
+            score: 0,
+            score_capt: "Score: ".to_string().into(),
+            lives: -1,
+            lives_capt: "Lives: ".to_string().into(),
+            health: Real::from(100.0),
+            health_capt: "Health: ".to_string().into(),
+            game_id: 0,
+            exe_hash: 0,
+            program_directory: "".to_string().into(),
+            gm_version: Version::GameMaker8_0,
+            open_ini: None,
+            spoofed_time_nanos: Some(0),
+            caption: "".to_string().into(),
+            caption_stale: false,
+            score_capt_d: false,
+            lives_capt_d: false,
+            health_capt_d: false,
+            window,
+            play_type: PlayType::Normal,
+            stored_events: VecDeque::new(),
+            watchpoints: Watchpoints::new(),
+            speed: Speed::new(),
+            show_overlay: false,
+            input_display: None,
+            unscaled_width: 0,
+            unscaled_height: 0,
+        };
+        game.refresh_event_holders();
+        game.load_room(0).unwrap();
+        game
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     game::{
         background, draw,
         external::{DefineInfo, External},
+        fileformat::{FileKind, Header},
         particle,
         string::RCStr,
         surface::Surface,
@@ -18,12 +19,25 @@ use crate::{
     instance::DummyFieldHolder,
     instancelist::{InstanceList, TileList},
     math::Real,
+    util,
 };
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use gmio::render::{BlendType, SavedTexture};
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use shared::types::{Colour, ID};
-use std::{cell::RefCell, collections::HashSet, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs::{self, File},
+    hash::Hasher,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// The folder next to savestate files which holds the data they share. See SaveState::save_to_file.
+const SHARED_DIR: &str = "shared";
 
 /// Represents a savestate. Very similar to the Game struct, but without things which aren't serialized.
 #[derive(Clone, Serialize, Deserialize)]
@@ -33,7 +47,7 @@ pub struct SaveState {
     pub tile_list: TileList,
     pub rand: Random,
     pub input_manager: InputManager,
-    pub assets: Assets,
+    pub assets: Rc<Assets>,
     pub event_holders: [IndexMap<u32, Rc<RefCell<Vec<ID>>>>; 12],
     pub custom_draw_objects: HashSet<ID>,
 
@@ -115,6 +129,12 @@ pub struct SaveState {
 
 impl SaveState {
     pub fn from(game: &Game, replay: Replay) -> Self {
+        Self::with_assets(game, replay, Rc::new(game.assets.clone()))
+    }
+
+    /// Like SaveState::from, but uses a copy of the game's assets which the caller already has, so that
+    /// states taken while the assets don't change can share them instead of each cloning them.
+    pub fn with_assets(game: &Game, replay: Replay, assets: Rc<Assets>) -> Self {
         let (width, height) = game.window.get_inner_size();
         let screenshot = game.renderer.get_pixels(0, 0, width as _, height as _);
 
@@ -124,7 +144,7 @@ impl SaveState {
             tile_list: game.tile_list.clone(),
             rand: game.rand.clone(),
             input_manager: game.input_manager.clone(),
-            assets: game.assets.clone(),
+            assets,
             event_holders: game.event_holders.clone(),
             custom_draw_objects: game.custom_draw_objects.clone(),
             background_colour: game.background_colour,
@@ -191,8 +211,19 @@ impl SaveState {
         }
     }
 
-    pub fn load_into(self, game: &mut Game) -> Replay {
-        game.window.resize(self.screenshot_width, self.screenshot_height);
+    /// Puts the game in this state, returning the replay that was saved with it. This fails if one of the
+    /// external functions can't be loaded again, in which case the game is left as it was.
+    pub fn load_into(self, game: &mut Game) -> Result<Replay, String> {
+        // we're always gonna be recording if we're loading savestates so disable sound
+        let externals = self
+            .externals
+            .into_iter()
+            .map(|info| info.map(|info| External::new(info, true)).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if game.window.get_inner_size() != (self.screenshot_width, self.screenshot_height) {
+            game.window.resize(self.screenshot_width, self.screenshot_height);
+        }
 
         game.renderer.upload_dynamic_textures(&self.textures);
 
@@ -216,16 +247,13 @@ impl SaveState {
         game.renderer.set_blend_mode(self.blend_mode.0, self.blend_mode.1);
         game.renderer.set_pixel_interpolation(self.interpolate_pixels);
 
-        let mut externals = self.externals;
-        // we're always gonna be recording if we're loading savestates so disable sound
-        game.externals = externals.drain(..).map(|i| i.map(|i| External::new(i, true).unwrap())).collect();
-
+        game.externals = externals;
         game.compiler = self.compiler;
         game.instance_list = self.instance_list;
         game.tile_list = self.tile_list;
         game.rand = self.rand;
         game.input_manager = self.input_manager;
-        game.assets = self.assets;
+        game.assets = Rc::try_unwrap(self.assets).unwrap_or_else(|assets| (*assets).clone());
         game.event_holders = self.event_holders;
         game.custom_draw_objects = self.custom_draw_objects;
         game.background_colour = self.background_colour;
@@ -281,10 +309,480 @@ impl SaveState {
         game.caption_stale = self.caption_stale;
         game.unscaled_width = self.unscaled_width;
         game.unscaled_height = self.unscaled_height;
-        self.replay
+        Ok(self.replay)
     }
 
     pub fn into_replay(self) -> Replay {
         self.replay
     }
+
+    // Writes the savestate to a compressed file, for the game with the given exe hash.
+    // The assets and each of the textures are stored separately, in a folder next to the file and named by the
+    // hash of their contents, since they're most of a savestate's size and they rarely change between savestates,
+    // so this way a project only keeps one copy of each.
+    // Nothing is ever deleted from that folder, as other savestates might still need it.
+    pub fn save_to_file(mut self, path: &Path, exe_hash: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let shared_dir = shared_dir(path);
+        fs::create_dir_all(&shared_dir)?;
+        let assets = write_shared(&shared_dir, &std::mem::take(&mut self.assets))?;
+        let textures = std::mem::take(&mut self.textures)
+            .iter()
+            .map(|texture| texture.as_ref().map(|texture| write_shared(&shared_dir, texture)).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut file = BufWriter::new(File::create(path)?);
+        Header::write(FileKind::SaveState, exe_hash, &mut file)?;
+        let mut encoder = DeflateEncoder::new(file, Compression::fast());
+        bincode::serialize_into(&mut encoder, &SaveFile { assets, textures, state: self })?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    // Reads a savestate written by save_to_file, migrating it from an older format if need be.
+    // Returns an error if it's from a newer format or for a different game.
+    //
+    // Format versions:
+    // none: no header, just a bincode SaveState in the layout in the legacy module.
+    // 1: added the header, and compressed, with assets and textures in the shared folder.
+    pub fn load_from_file(path: &Path, exe_hash: u64) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = BufReader::new(File::open(path)?);
+        match Header::read(FileKind::SaveState, &mut file)? {
+            Some(header) => header.check(FileKind::SaveState, exe_hash)?,
+            None => {
+                return bincode::deserialize_from::<_, legacy::SaveState0>(file).map(SaveState::from).map_err(|e| {
+                    format!("couldn't read savestate, which is from an old version with no format header: {}", e)
+                        .into()
+                })
+            },
+        }
+
+        let SaveFile { assets, textures, mut state } = bincode::deserialize_from(DeflateDecoder::new(file))?;
+        let shared_dir = shared_dir(path);
+        state.assets = Rc::new(read_shared(&shared_dir, assets)?);
+        state.textures = textures
+            .into_iter()
+            .map(|texture| texture.map(|hash| read_shared(&shared_dir, hash)).transpose())
+            .collect::<Result<_, _>>()?;
+        Ok(state)
+    }
+}
+
+// What's in a savestate file after the header, once decompressed.
+// The state's assets and textures are left empty, and they're stored in the shared folder under these hashes.
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    assets: u64,
+    textures: Vec<Option<u64>>,
+    state: SaveState,
+}
+
+// The layout savestates had before files got headers. Since then, actions have stored their library and action ids,
+// and replays have gained the tampered list and frame hashes, so the assets and the replay have to be read in their
+// old layouts and migrated.
+mod legacy {
+    use super::*;
+    use crate::{
+        action,
+        asset::{self, Object, Path, Room, Script, Sprite, Timeline, Trigger},
+        game::replay,
+    };
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SaveState0 {
+        pub compiler: Compiler,
+        pub instance_list: InstanceList,
+        pub tile_list: TileList,
+        pub rand: Random,
+        pub input_manager: InputManager,
+        pub assets: Assets0,
+        pub event_holders: [IndexMap<u32, Rc<RefCell<Vec<ID>>>>; 12],
+        pub custom_draw_objects: HashSet<ID>,
+        pub background_colour: Colour,
+        pub room_colour: Option<Colour>,
+        pub textures: Vec<Option<SavedTexture>>,
+        pub blend_mode: (BlendType, BlendType),
+        pub interpolate_pixels: bool,
+        pub externals: Vec<Option<DefineInfo>>,
+        pub last_instance_id: ID,
+        pub last_tile_id: ID,
+        pub views_enabled: bool,
+        pub view_current: usize,
+        pub views: Vec<View>,
+        pub backgrounds: Vec<background::Background>,
+        pub particles: particle::Manager,
+        pub room_id: i32,
+        pub room_width: i32,
+        pub room_height: i32,
+        pub room_order: Box<[i32]>,
+        pub room_speed: u32,
+        pub globals: DummyFieldHolder,
+        pub globalvars: HashSet<usize>,
+        pub game_start: bool,
+        pub stacks: DataStructureManager<ds::Stack>,
+        pub queues: DataStructureManager<ds::Queue>,
+        pub lists: DataStructureManager<ds::List>,
+        pub maps: DataStructureManager<ds::Map>,
+        pub priority_queues: DataStructureManager<ds::Priority>,
+        pub grids: DataStructureManager<ds::Grid>,
+        pub ds_precision: Real,
+        pub draw_font: Option<Font>,
+        pub draw_font_id: ID,
+        pub draw_colour: Colour,
+        pub draw_alpha: Real,
+        pub draw_halign: draw::Halign,
+        pub draw_valign: draw::Valign,
+        pub surfaces: Vec<Option<Surface>>,
+        pub surface_target: Option<i32>,
+        pub uninit_fields_are_zero: bool,
+        pub uninit_args_are_zero: bool,
+        pub transition_kind: i32,
+        pub transition_steps: i32,
+        pub score: i32,
+        pub score_capt: RCStr,
+        pub score_capt_d: bool,
+        pub lives: i32,
+        pub lives_capt: RCStr,
+        pub lives_capt_d: bool,
+        pub health: Real,
+        pub health_capt: RCStr,
+        pub health_capt_d: bool,
+        pub game_id: i32,
+        pub program_directory: RCStr,
+        pub gm_version: Version,
+        pub spoofed_time_nanos: Option<u128>,
+        pub caption: RCStr,
+        pub caption_stale: bool,
+        pub unscaled_width: u32,
+        pub unscaled_height: u32,
+        pub replay: replay::legacy::Replay0,
+        pub screenshot: Box<[u8]>,
+        pub screenshot_width: u32,
+        pub screenshot_height: u32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Assets0 {
+        pub backgrounds: Vec<Option<Box<asset::Background>>>,
+        pub fonts: Vec<Option<Box<Font>>>,
+        pub objects: Vec<Option<Box<Object0>>>,
+        pub paths: Vec<Option<Box<Path>>>,
+        pub rooms: Vec<Option<Box<Room>>>,
+        pub scripts: Vec<Option<Box<Script>>>,
+        pub sprites: Vec<Option<Box<Sprite>>>,
+        pub timelines: Vec<Option<Box<Timeline0>>>,
+        pub triggers: Vec<Option<Box<Trigger>>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Object0 {
+        pub name: RCStr,
+        pub solid: bool,
+        pub visible: bool,
+        pub persistent: bool,
+        pub depth: i32,
+        pub sprite_index: i32,
+        pub mask_index: i32,
+        pub parent_index: i32,
+        pub events: [HashMap<u32, Rc<RefCell<action::legacy::Tree>>>; 12],
+        pub children: Rc<RefCell<HashSet<i32>>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Timeline0 {
+        pub name: RCStr,
+        pub moments: Rc<RefCell<BTreeMap<i32, Rc<RefCell<action::legacy::Tree>>>>>,
+    }
+
+    fn convert_tree(tree: Rc<RefCell<action::legacy::Tree>>) -> Rc<RefCell<action::Tree>> {
+        Rc::new(RefCell::new(tree.replace(action::legacy::Tree(Vec::new())).into()))
+    }
+
+    impl From<Object0> for Object {
+        fn from(mut object: Object0) -> Self {
+            let mut events: [HashMap<u32, Rc<RefCell<action::Tree>>>; 12] = Default::default();
+            for (new, old) in events.iter_mut().zip(object.events.iter_mut()) {
+                *new = std::mem::take(old).into_iter().map(|(number, tree)| (number, convert_tree(tree))).collect();
+            }
+            Self {
+                name: object.name,
+                solid: object.solid,
+                visible: object.visible,
+                persistent: object.persistent,
+                depth: object.depth,
+                sprite_index: object.sprite_index,
+                mask_index: object.mask_index,
+                parent_index: object.parent_index,
+                events,
+                children: object.children,
+            }
+        }
+    }
+
+    impl From<Timeline0> for Timeline {
+        fn from(timeline: Timeline0) -> Self {
+            let moments = timeline.moments.replace(BTreeMap::new());
+            Self {
+                name: timeline.name,
+                moments: Rc::new(RefCell::new(
+                    moments.into_iter().map(|(moment, tree)| (moment, convert_tree(tree))).collect(),
+                )),
+            }
+        }
+    }
+
+    impl From<Assets0> for Assets {
+        fn from(assets: Assets0) -> Self {
+            Self {
+                backgrounds: assets.backgrounds,
+                fonts: assets.fonts,
+                objects: assets.objects.into_iter().map(|x| x.map(|x| Box::new(Object::from(*x)))).collect(),
+                paths: assets.paths,
+                rooms: assets.rooms,
+                scripts: assets.scripts,
+                sprites: assets.sprites,
+                timelines: assets.timelines.into_iter().map(|x| x.map(|x| Box::new(Timeline::from(*x)))).collect(),
+                triggers: assets.triggers,
+            }
+        }
+    }
+
+    impl From<SaveState0> for SaveState {
+        fn from(state: SaveState0) -> Self {
+            Self {
+                compiler: state.compiler,
+                instance_list: state.instance_list,
+                tile_list: state.tile_list,
+                rand: state.rand,
+                input_manager: state.input_manager,
+                assets: Rc::new(state.assets.into()),
+                event_holders: state.event_holders,
+                custom_draw_objects: state.custom_draw_objects,
+                background_colour: state.background_colour,
+                room_colour: state.room_colour,
+                textures: state.textures,
+                blend_mode: state.blend_mode,
+                interpolate_pixels: state.interpolate_pixels,
+                externals: state.externals,
+                last_instance_id: state.last_instance_id,
+                last_tile_id: state.last_tile_id,
+                views_enabled: state.views_enabled,
+                view_current: state.view_current,
+                views: state.views,
+                backgrounds: state.backgrounds,
+                particles: state.particles,
+                room_id: state.room_id,
+                room_width: state.room_width,
+                room_height: state.room_height,
+                room_order: state.room_order,
+                room_speed: state.room_speed,
+                globals: state.globals,
+                globalvars: state.globalvars,
+                game_start: state.game_start,
+                stacks: state.stacks,
+                queues: state.queues,
+                lists: state.lists,
+                maps: state.maps,
+                priority_queues: state.priority_queues,
+                grids: state.grids,
+                ds_precision: state.ds_precision,
+                draw_font: state.draw_font,
+                draw_font_id: state.draw_font_id,
+                draw_colour: state.draw_colour,
+                draw_alpha: state.draw_alpha,
+                draw_halign: state.draw_halign,
+                draw_valign: state.draw_valign,
+                surfaces: state.surfaces,
+                surface_target: state.surface_target,
+                uninit_fields_are_zero: state.uninit_fields_are_zero,
+                uninit_args_are_zero: state.uninit_args_are_zero,
+                transition_kind: state.transition_kind,
+                transition_steps: state.transition_steps,
+                score: state.score,
+                score_capt: state.score_capt,
+                score_capt_d: state.score_capt_d,
+                lives: state.lives,
+                lives_capt: state.lives_capt,
+                lives_capt_d: state.lives_capt_d,
+                health: state.health,
+                health_capt: state.health_capt,
+                health_capt_d: state.health_capt_d,
+                game_id: state.game_id,
+                program_directory: state.program_directory,
+                gm_version: state.gm_version,
+                spoofed_time_nanos: state.spoofed_time_nanos,
+                caption: state.caption,
+                caption_stale: state.caption_stale,
+                unscaled_width: state.unscaled_width,
+                unscaled_height: state.unscaled_height,
+                replay: state.replay.into(),
+                screenshot: state.screenshot,
+                screenshot_width: state.screenshot_width,
+                screenshot_height: state.screenshot_height,
+            }
+        }
+    }
+}
+
+fn shared_dir(savestate_path: &Path) -> PathBuf {
+    let mut dir = savestate_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    dir.push(SHARED_DIR);
+    dir
+}
+
+/// Hashes a game's assets, for telling whether they've changed since a savestate's copy of them was taken.
+/// Like the ones in the shared folder, these hashes can differ between sessions for the same assets.
+pub fn hash_assets(assets: &Assets) -> u64 {
+    let mut hasher = util::Fnv1a::default();
+    // writing to a hasher can't fail
+    let _ = bincode::serialize_into(&mut hasher, assets);
+    hasher.finish()
+}
+
+fn shared_path(dir: &Path, hash: u64) -> PathBuf {
+    dir.join(format!("{:016x}.bin", hash))
+}
+
+// Writes something to the shared folder, unless it's there already, and returns its hash.
+// Note that assets contain HashMaps, so the same assets can serialize differently after being loaded
+// in another session. That only costs an extra copy of them, though.
+fn write_shared(dir: &Path, value: &impl Serialize) -> Result<u64, Box<dyn std::error::Error>> {
+    let data = bincode::serialize(value)?;
+    let hash = util::fnv1a(&data);
+    let path = shared_path(dir, hash);
+    if !path.exists() {
+        // write it under another name first, so a crash can't leave a broken file which looks finished
+        let temp_path = path.with_extension("tmp");
+        let mut encoder = DeflateEncoder::new(BufWriter::new(File::create(&temp_path)?), Compression::fast());
+        encoder.write_all(&data)?;
+        encoder.finish()?.flush()?;
+        fs::rename(&temp_path, &path)?;
+    }
+    Ok(hash)
+}
+
+fn read_shared<T: DeserializeOwned>(dir: &Path, hash: u64) -> Result<T, Box<dyn std::error::Error>> {
+    let path = shared_path(dir, hash);
+    let file = File::open(&path).map_err(|e| format!("couldn't open {}: {}", path.display(), e))?;
+    Ok(bincode::deserialize_from(DeflateDecoder::new(BufReader::new(file)))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{self, Action, Body},
+        game::replay,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn save_files() {
+        let dir = std::env::temp_dir().join(format!("gm8emulator-savestate-{}", std::process::id()));
+        let shared_count = || fs::read_dir(dir.join(SHARED_DIR)).unwrap().count();
+        let mut game = Game::new_sandbox();
+        let x = |game: &mut Game| i32::from(game.execute_console("global.x", None).unwrap());
+
+        game.execute_console("global.x = 1", None).unwrap();
+        SaveState::from(&game, Replay::new(0, 0)).save_to_file(&dir.join("a.bin"), 0).unwrap();
+        let count = shared_count();
+        game.execute_console("global.x = 2", None).unwrap();
+        SaveState::from(&game, Replay::new(0, 0)).save_to_file(&dir.join("b.bin"), 0).unwrap();
+        assert_eq!(shared_count(), count);
+
+        // uncompressed savestates from before the shared folder should still load
+        game.execute_console("global.x = 3", None).unwrap();
+        fs::write(dir.join("c.bin"), legacy_bytes(&SaveState::from(&game, Replay::new(0, 0)))).unwrap();
+
+        assert!(SaveState::load_from_file(&dir.join("a.bin"), 1).is_err());
+        SaveState::load_from_file(&dir.join("a.bin"), 0).unwrap().load_into(&mut game).unwrap();
+        assert_eq!(x(&mut game), 1);
+        let replay = SaveState::load_from_file(&dir.join("c.bin"), 0).unwrap().load_into(&mut game).unwrap();
+        assert_eq!(x(&mut game), 3);
+        assert_eq!((replay.start_time, replay.start_seed, replay.frame_count()), (4, 5, 1));
+        assert!(replay.get_frame(0).unwrap().hash.is_none());
+        let tree = game.assets.objects[0].as_ref().unwrap().events[0][&0].clone();
+        assert!(matches!(tree.borrow().actions(), [Action { index: 0, lib_id: 0, body: Body::Exit, .. }]));
+
+        // a replay isn't a savestate
+        Replay::new(0, 0).save_to_file(&dir.join("d.gmtas"), 0).unwrap();
+        let error = SaveState::load_from_file(&dir.join("d.gmtas"), 0).err().unwrap();
+        assert_eq!(error.to_string(), "this is a replay, not a savestate");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Writes a savestate in the layout from before files had headers, with a create event on object 0 and a frame
+    // in the replay. That layout only differs in the assets and the replay, and since bincode just writes each
+    // field in turn, those parts of the current layout can be swapped out for the old ones.
+    fn legacy_bytes(state: &SaveState) -> Vec<u8> {
+        let size = |size: bincode::Result<u64>| size.unwrap() as usize;
+        let data = bincode::serialize(state).unwrap();
+        let assets_start = size(bincode::serialized_size(&(
+            &state.compiler,
+            &state.instance_list,
+            &state.tile_list,
+            &state.rand,
+            &state.input_manager,
+        )));
+        let assets_end = assets_start + size(bincode::serialized_size(&state.assets));
+        let screenshot = (&state.screenshot, state.screenshot_width, state.screenshot_height);
+        let replay_end = data.len() - size(bincode::serialized_size(&screenshot));
+        let replay_start = replay_end - size(bincode::serialized_size(&state.replay));
+
+        let objects = state.assets.objects.iter().enumerate().map(|(i, object)| {
+            object.as_ref().map(|object| {
+                let mut events: [HashMap<u32, Rc<RefCell<action::legacy::Tree>>>; 12] = Default::default();
+                if i == 0 {
+                    let exit = action::legacy::Action {
+                        index: 0,
+                        target: None,
+                        relative: false,
+                        invert_condition: false,
+                        body: action::legacy::Body::Exit,
+                    };
+                    events[0].insert(0, Rc::new(RefCell::new(action::legacy::Tree(vec![exit]))));
+                }
+                Box::new(legacy::Object0 {
+                    name: object.name.clone(),
+                    solid: object.solid,
+                    visible: object.visible,
+                    persistent: object.persistent,
+                    depth: object.depth,
+                    sprite_index: object.sprite_index,
+                    mask_index: object.mask_index,
+                    parent_index: object.parent_index,
+                    events,
+                    children: object.children.clone(),
+                })
+            })
+        });
+        let assets = legacy::Assets0 {
+            backgrounds: state.assets.backgrounds.clone(),
+            fonts: state.assets.fonts.clone(),
+            objects: objects.collect(),
+            paths: state.assets.paths.clone(),
+            rooms: state.assets.rooms.clone(),
+            scripts: state.assets.scripts.clone(),
+            sprites: state.assets.sprites.clone(),
+            timelines: Vec::new(),
+            triggers: state.assets.triggers.clone(),
+        };
+        let frame = replay::legacy::Frame {
+            fps: 50,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            inputs: Vec::new(),
+            events: Vec::new(),
+            new_seed: None,
+            new_time: None,
+        };
+        let replay = replay::legacy::Replay0 { start_time: 4, start_seed: 5, frames: vec![frame] };
+
+        let mut old = data[..assets_start].to_vec();
+        old.extend(bincode::serialize(&assets).unwrap());
+        old.extend_from_slice(&data[assets_end..replay_start]);
+        old.extend(bincode::serialize(&replay).unwrap());
+        old.extend_from_slice(&data[replay_end..]);
+        old
+    }
 }
@@ -0,0 +1,352 @@
+// Tools for finding inputs and seeds automatically: a GML expression which is checked after each frame, and searches
+// which play lots of input sequences or seeds from the same savestate and report which of them made it true.
+// Searches don't touch the replay, and whatever runs them is expected to put the game back how it was afterwards.
+
+use crate::{
+    game::{savestate, Game, SaveState, SceneChange},
+    gml::{rand::Random, runtime::Node, Compiler, Context},
+    instance::Instance,
+};
+use shared::{
+    input::Button,
+    message::{self, InputSequence, RandomCall, SearchOutcome},
+};
+use std::{
+    ops::RangeInclusive,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// How long a seed search runs at a time in a recording session, between handling messages from the controller.
+pub const SEED_SEARCH_SLICE: Duration = Duration::from_millis(100);
+
+/// How often a seed search in a recording session tells the controller how far it's got.
+pub const SEED_SEARCH_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A compiled GML expression which is checked between frames.
+pub struct Predicate {
+    source: String,
+    node: Node,
+
+    // How many field names the compiler had when this was compiled. Names it didn't have yet were given the IDs after
+    // those and then forgotten, so the compiler (which is saved in savestates) is left as it was. If the game gives
+    // out more IDs, they might go to other names, so then this has to be compiled again.
+    field_count: usize,
+}
+
+impl Predicate {
+    pub fn new(expression: &str, compiler: &mut Compiler) -> Result<Self, String> {
+        let field_count = compiler.field_count();
+        let node = compiler.compile_expression(expression);
+        compiler.forget_fields_from(field_count);
+        match node {
+            Ok(node) => Ok(Self { source: expression.into(), node, field_count }),
+            Err(e) => Err(format!("compiler error: {}", e)),
+        }
+    }
+
+    /// Compiles the expression again, for after a savestate is loaded. That replaces the compiler, which might
+    /// give the fields and scripts the expression names different IDs than the one it was compiled with.
+    pub fn recompile(&mut self, compiler: &mut Compiler) -> Result<(), String> {
+        *self = Self::new(&self.source, compiler)?;
+        Ok(())
+    }
+}
+
+/// A seed search which is partway through. It's run a few seeds at a time with Game::continue_seed_search,
+/// so whatever runs it can still do other things in between, or give up on it.
+pub struct SeedSearch {
+    state: SaveState,
+    assets_hash: u64,
+    predicate: String,
+    seeds: RangeInclusive<i32>,
+    total: usize,
+    frames: usize,
+    outcomes: Vec<SearchOutcome>,
+}
+
+impl SeedSearch {
+    pub fn new(state: SaveState, predicate: String, seeds: RangeInclusive<i32>, frames: usize) -> Result<Self, String> {
+        let total = message::seed_count(&seeds);
+        if total > message::MAX_SEARCH_SEEDS {
+            return Err(format!("can't try {} seeds, as a search can try at most {}", total, message::MAX_SEARCH_SEEDS))
+        }
+        let assets_hash = savestate::hash_assets(&state.assets);
+        Ok(Self { state, assets_hash, predicate, seeds, total, frames, outcomes: Vec::new() })
+    }
+
+    /// How many seeds have been tried so far, and how many there are altogether.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.outcomes.len(), self.total)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcomes.len() == self.total
+    }
+
+    /// The outcomes of the seeds which have been tried so far, in order.
+    pub fn into_outcomes(self) -> Vec<SearchOutcome> {
+        self.outcomes
+    }
+}
+
+/// Works out what the next few calls of a random function would give, starting from a seed.
+pub fn preview_random(seed: i32, call: RandomCall, count: usize) -> Vec<f64> {
+    let mut rand = Random::with_seed(seed);
+    (0..count)
+        .map(|_| match call {
+            RandomCall::Random(bound) => rand.next(bound),
+            RandomCall::Irandom(bound) => f64::from(rand.next_int(bound as _)),
+        })
+        .collect()
+}
+
+impl Game {
+    /// Evaluates a predicate as a dummy instance, returning whether it's true.
+    pub fn check_predicate(&mut self, predicate: &mut Predicate) -> Result<bool, String> {
+        if predicate.field_count != self.compiler.field_count() {
+            predicate.recompile(&mut self.compiler)?;
+        }
+        let dummy = self.instance_list.insert_dummy(Instance::new_dummy(None));
+        let mut context = Context {
+            this: dummy,
+            other: dummy,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: self.instance_list.get(dummy).object_index.get(),
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        };
+        let result = self.eval(&predicate.node, &mut context);
+        self.instance_list.remove_dummy(dummy);
+        result.map(|value| value.is_truthy()).map_err(|e| format!("runtime error: {}", e))
+    }
+
+    /// Like check_predicate, but also reports whether checking it changed anything about the game's state,
+    /// in the same way as execute_console_checked.
+    pub fn check_predicate_checked(&mut self, predicate: &mut Predicate) -> (Result<bool, String>, bool) {
+        let before = match self.state_fingerprint() {
+            Ok(hash) => hash,
+            Err(e) => return (Err(e), false),
+        };
+        let result = self.check_predicate(predicate);
+        match self.state_fingerprint() {
+            Ok(after) => (result, after != before),
+            Err(e) => (result.and(Err(e)), true),
+        }
+    }
+
+    /// Plays each sequence from the savestate, stopping each one as soon as the predicate is true.
+    /// The game is left however the last sequence left it.
+    pub fn search(
+        &mut self,
+        state: &SaveState,
+        predicate: &str,
+        sequences: &[InputSequence],
+    ) -> Result<Vec<SearchOutcome>, String> {
+        let assets_hash = savestate::hash_assets(&state.assets);
+        sequences.iter().map(|sequence| self.try_sequence(state, assets_hash, predicate, None, sequence)).collect()
+    }
+
+    /// Tries the next seeds of a seed search until it's finished or it's past the deadline. Each seed is set after
+    /// loading the savestate, then the search's number of frames are run without changing any inputs, stopping as
+    /// soon as the predicate is true. The game is left however the last seed left it.
+    pub fn continue_seed_search(&mut self, search: &mut SeedSearch, deadline: Instant) -> Result<(), String> {
+        let sequence = InputSequence { frames: vec![Vec::new(); search.frames] };
+        while let Some(seed) = search.seeds.next() {
+            let outcome =
+                self.try_sequence(&search.state, search.assets_hash, &search.predicate, Some(seed), &sequence)?;
+            search.outcomes.push(outcome);
+            if Instant::now() >= deadline {
+                break
+            }
+        }
+        Ok(())
+    }
+
+    // Loads the savestate and plays a sequence from it. The predicate is compiled after loading the savestate,
+    // since that replaces the compiler, and an error is only returned if it doesn't compile.
+    // The savestate's assets, whose hash is given, are only copied if the last sequence changed the game's ones,
+    // since they're most of a savestate and hardly any games change them.
+    fn try_sequence(
+        &mut self,
+        state: &SaveState,
+        assets_hash: u64,
+        predicate: &str,
+        seed: Option<i32>,
+        sequence: &InputSequence,
+    ) -> Result<SearchOutcome, String> {
+        let mut state = state.clone();
+        if savestate::hash_assets(&self.assets) == assets_hash {
+            state.assets = Rc::new(std::mem::take(&mut self.assets));
+        }
+        if let Err(error) = state.load_into(self) {
+            return Ok(SearchOutcome::Failed { error })
+        }
+        let mut predicate = Predicate::new(predicate, &mut self.compiler)?;
+        if let Some(seed) = seed {
+            self.rand.set_seed(seed);
+        }
+        Ok(match self.run_sequence(&mut predicate, sequence) {
+            Ok(Some(frames)) => SearchOutcome::Satisfied { frames },
+            Ok(None) => SearchOutcome::Unsatisfied,
+            Err(error) => SearchOutcome::Failed { error },
+        })
+    }
+
+    // Runs a sequence's frames until the predicate is true, returning how many frames that took
+    fn run_sequence(&mut self, predicate: &mut Predicate, sequence: &InputSequence) -> Result<Option<usize>, String> {
+        let buttons = sequence.buttons();
+        for (i, held) in sequence.frames.iter().enumerate() {
+            let on_frame = |e: &dyn std::fmt::Display| format!("on frame {}: {}", i, e);
+
+            // Only buttons which aren't already the right way are pressed or released, like a real player would
+            for button in buttons.iter() {
+                let hold = held.contains(button);
+                match *button {
+                    Button::Key(key) if hold != self.input_manager.key_check((key as u8).into()) => {
+                        if hold {
+                            self.input_manager.key_press(key)
+                        } else {
+                            self.input_manager.key_release(key)
+                        }
+                    },
+                    Button::Mouse(button) if hold != self.input_manager.mouse_check(button) => {
+                        if hold {
+                            self.input_manager.mouse_press(button)
+                        } else {
+                            self.input_manager.mouse_release(button)
+                        }
+                    },
+                    _ => (),
+                }
+            }
+            self.input_manager.mouse_update_previous();
+
+            self.frame().map_err(|e| on_frame(&e))?;
+            match self.scene_change {
+                Some(SceneChange::Room(id)) => self.load_room(id).map_err(|e| on_frame(&e))?,
+                Some(SceneChange::Restart) | Some(SceneChange::End) => self.restart().map_err(|e| on_frame(&e))?,
+                None => (),
+            }
+            self.stored_events.clear();
+
+            if self.check_predicate(predicate).map_err(|e| on_frame(&e))? {
+                return Ok(Some(i + 1))
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        external::{CallConv, DLLValueType, DefineInfo},
+        Replay,
+    };
+    use shared::input::Key;
+
+    #[test]
+    fn search() {
+        let mut game = Game::new_sandbox();
+        let state = SaveState::from(&game, Replay::new(0, 0));
+        let predicate = "keyboard_check(vk_right) && !keyboard_check(vk_left)";
+        let (left, right) = (Button::Key(Key::Left), Button::Key(Key::Right));
+        let sequences = [
+            InputSequence { frames: vec![vec![left], vec![left, right], vec![right]] },
+            InputSequence { frames: vec![vec![left], vec![]] },
+        ];
+        assert_eq!(
+            game.search(&state, predicate, &sequences),
+            Ok(vec![SearchOutcome::Satisfied { frames: 3 }, SearchOutcome::Unsatisfied])
+        );
+        assert!(game.search(&state, "1 +", &sequences).is_err());
+    }
+
+    #[test]
+    fn seed_search() {
+        let mut game = Game::new_sandbox();
+        let state = SaveState::from(&game, Replay::new(0, 0));
+        let mut search = SeedSearch::new(state, "random_get_seed() == 4".into(), 3..=5, 2).unwrap();
+
+        // with a deadline which has already passed, it only tries one seed at a time
+        game.continue_seed_search(&mut search, Instant::now()).unwrap();
+        assert_eq!(search.progress(), (1, 3));
+        game.continue_seed_search(&mut search, Instant::now() + Duration::from_secs(60)).unwrap();
+        assert!(search.is_finished());
+        assert_eq!(search.into_outcomes(), [
+            SearchOutcome::Unsatisfied,
+            SearchOutcome::Satisfied { frames: 1 },
+            SearchOutcome::Unsatisfied,
+        ]);
+
+        let state = SaveState::from(&game, Replay::new(0, 0));
+        assert!(SeedSearch::new(state, "true".into(), i32::MIN..=i32::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn failed_load() {
+        let mut game = Game::new_sandbox();
+        let mut state = SaveState::from(&game, Replay::new(0, 0));
+        // an external function which can't be defined again, since it has too many arguments
+        state.externals.push(Some(DefineInfo {
+            dll_name: "a.dll".into(),
+            fn_name: "f".into(),
+            call_conv: CallConv::Cdecl,
+            res_type: DLLValueType::Real,
+            arg_types: vec![DLLValueType::Real; 16],
+        }));
+        let mut search = SeedSearch::new(state, "true".into(), 1..=2, 1).unwrap();
+        game.continue_seed_search(&mut search, Instant::now() + Duration::from_secs(60)).unwrap();
+        let outcomes = search.into_outcomes();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| matches!(outcome, SearchOutcome::Failed { .. })));
+    }
+
+    #[test]
+    fn recompile_predicate() {
+        let mut game = Game::new_sandbox();
+        let state = SaveState::from(&game, Replay::new(0, 0));
+        game.execute_console("global.b = 2", None).unwrap();
+        let mut predicate = Predicate::new("global.a == 1", &mut game.compiler).unwrap();
+
+        // after loading, "a" gets the field ID "b" had, so the old node looks at the wrong field
+        state.load_into(&mut game).unwrap();
+        game.execute_console("global.a = 1", None).unwrap();
+        assert_ne!(game.check_predicate(&mut predicate), Ok(true));
+        predicate.recompile(&mut game.compiler).unwrap();
+        assert_eq!(game.check_predicate(&mut predicate), Ok(true));
+    }
+
+    #[test]
+    fn predicate_leaves_no_trace() {
+        let mut game = Game::new_sandbox();
+        let mut predicate = Predicate::new("global.c == 1", &mut game.compiler).unwrap();
+        assert_eq!(game.compiler.find_field_id("c"), None);
+
+        // "d" gets the ID "c" was given while compiling, so the predicate has to be compiled again to not see it
+        game.execute_console("global.d = 1", None).unwrap();
+        assert_ne!(game.check_predicate(&mut predicate), Ok(true));
+        game.execute_console("global.c = 1", None).unwrap();
+        assert_eq!(game.check_predicate(&mut predicate), Ok(true));
+
+        assert_eq!(game.check_predicate_checked(&mut predicate), (Ok(true), false));
+        let mut predicate = Predicate::new("instance_create(0, 0, 0) > 0", &mut game.compiler).unwrap();
+        assert_eq!(game.check_predicate_checked(&mut predicate), (Ok(true), true));
+    }
+
+    #[test]
+    fn random_preview() {
+        let mut rand = Random::with_seed(12345);
+        let expected = [rand.next(1.0), rand.next(1.0)];
+        assert_eq!(preview_random(12345, RandomCall::Random(1.0), 2), expected);
+        let mut rand = Random::with_seed(12345);
+        assert_eq!(preview_random(12345, RandomCall::Irandom(6), 1), [f64::from(rand.next_int(6))]);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::game::Game;
+use shared::input::Key;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The key which toggles turbo while playing, if the turbo hotkey is enabled. It's never passed on to the game then.
+pub const TURBO_KEY: Key = Key::F12;
+
+/// Settings for running the game faster than its room speed.
+/// None of these change what happens in the game, including the spoofed clock, only how quickly it happens.
+pub struct Speed {
+    /// Runs frames as fast as possible instead of waiting for the next one to be due.
+    pub turbo: bool,
+
+    /// Doesn't show frames in the window while running fast, since that's slow and often waits for vsync.
+    pub skip_present: bool,
+
+    /// Runs fast until this frame, then goes back to normal speed.
+    pub fast_forward_to: Option<usize>,
+
+    /// Lets TURBO_KEY toggle turbo while playing or watching a replay. This is off unless a speed option was
+    /// asked for, since otherwise games which use that key would never get it.
+    pub hotkey: bool,
+
+    /// The number of frames which have been run.
+    frame: usize,
+
+    /// The number of frames which took longer than the room speed allows, while not running fast.
+    pub lag_frames: usize,
+}
+
+impl Speed {
+    pub fn new() -> Self {
+        Self { turbo: false, skip_present: false, fast_forward_to: None, hotkey: false, frame: 0, lag_frames: 0 }
+    }
+
+    /// Whether the current frame should be run without waiting for it.
+    pub fn is_fast(&self) -> bool {
+        self.turbo || self.fast_forward_to.map_or(false, |frame| self.frame < frame)
+    }
+
+    /// Whether the current frame should be shown in the window.
+    pub fn should_present(&self) -> bool {
+        !(self.skip_present && self.is_fast())
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    // Shows the frame in the window, unless frames are being skipped to run faster.
+    // The draw queue is flushed either way, so the game can still read back what it drew.
+    pub fn present(&mut self) {
+        if self.speed.should_present() {
+            self.renderer.present();
+        } else {
+            self.renderer.flush_queue();
+        }
+    }
+
+    // Advances the spoofed clock by a frame and waits until the next frame is due, unless running fast.
+    // time_now is when the current frame was due.
+    pub fn limit_frame_rate(&mut self, time_now: &mut Instant) {
+        let duration = Duration::new(0, 1_000_000_000u32 / self.room_speed);
+        if let Some(t) = self.spoofed_time_nanos.as_mut() {
+            *t += duration.as_nanos();
+        }
+
+        if self.speed.is_fast() {
+            *time_now = Instant::now();
+        } else {
+            let diff = Instant::now().duration_since(*time_now);
+            if let Some(time) = duration.checked_sub(diff) {
+                thread::sleep(time);
+                *time_now += duration;
+            } else {
+                *time_now = Instant::now();
+                self.speed.lag_frames += 1;
+            }
+        }
+
+        self.speed.frame += 1;
+        if self.speed.fast_forward_to == Some(self.speed.frame) {
+            println!("Fast-forwarded to frame {}", self.speed.frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_forward() {
+        let mut speed = Speed::new();
+        speed.fast_forward_to = Some(2);
+        speed.skip_present = true;
+        assert!(speed.is_fast() && !speed.should_present());
+        speed.frame = 1;
+        assert!(speed.is_fast());
+        speed.frame = 2;
+        assert!(!speed.is_fast() && speed.should_present());
+        speed.turbo = true;
+        assert!(speed.is_fast() && !speed.should_present());
+    }
+}
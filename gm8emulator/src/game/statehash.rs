@@ -0,0 +1,331 @@
+// A hash of the parts of the game's state that GML can observe, for checking whether two playbacks of a replay went
+// the same way. Unlike a savestate, this has to come out the same in every process and on every platform, so it
+// never hashes anything in the order of a HashMap, and field IDs are hashed by name since they're numbered in the
+// order the compiler first saw them, which can differ when code is compiled lazily.
+// It's taken every frame, so it doesn't allocate or sort anything. The entries of a HashMap are each hashed on their
+// own and the results added together, which comes out the same whatever order they're stored in.
+
+use crate::{
+    game::{replay, Game, GetAsset},
+    gml::{ds::DataStructureManager, Value},
+    instance::{Field, Instance},
+    math::Real,
+    util::Fnv1a,
+};
+use serde::Serialize;
+use shared::types::ID;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hasher,
+};
+
+/// Where a replay first stopped matching the hashes recorded in it.
+pub struct Desync {
+    /// The frame after which the state didn't match.
+    pub frame: usize,
+
+    /// A description of each instance which didn't match, in order of ID.
+    pub instances: Vec<String>,
+}
+
+impl Game {
+    // Hashes the room, RNG, instances, tiles, globals and data structures.
+    pub fn state_hash(&self) -> u64 {
+        self.hash_state(None)
+    }
+
+    // Hashes the state as above, and also gets the hash of each instance which exists, by ID.
+    pub fn state_and_instance_hashes(&self) -> (u64, BTreeMap<ID, u64>) {
+        let mut instances = BTreeMap::new();
+        let state = self.hash_state(Some(&mut instances));
+        (state, instances)
+    }
+
+    // Gets the hash of each instance which exists, by ID, without hashing the rest of the state.
+    fn instance_hashes(&self) -> BTreeMap<ID, u64> {
+        let mut instances = BTreeMap::new();
+        let mut iter = self.instance_list.iter_by_insertion();
+        let mut inactive = self.instance_list.iter_inactive();
+        while let Some(handle) = iter.next(&self.instance_list).or_else(|| inactive.next(&self.instance_list)) {
+            let instance = self.instance_list.get(handle);
+            instances.insert(instance.id.get(), self.instance_hash(instance));
+        }
+        instances
+    }
+
+    // Checks the game against the hash recorded for a frame of a replay, after that frame has run.
+    // `expected` holds the recorded instance hashes as of the previous frame, and gets brought up to date.
+    pub fn check_frame_hash(
+        &self,
+        frame: &replay::Frame,
+        frame_index: usize,
+        expected: &mut BTreeMap<ID, u64>,
+    ) -> Option<Desync> {
+        let hash = match &frame.hash {
+            Some(hash) => hash,
+            None => {
+                expected.clear();
+                return None
+            },
+        };
+        hash.apply(expected);
+        if hash.state == self.state_hash() {
+            return None
+        }
+
+        let current = self.instance_hashes();
+        let mut ids = expected.keys().chain(current.keys()).copied().collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let instances = ids
+            .into_iter()
+            .filter_map(|id| {
+                let problem = match (expected.get(&id), current.get(&id)) {
+                    (Some(_), None) => "should exist but doesn't",
+                    (None, Some(_)) => "exists but shouldn't",
+                    (Some(a), Some(b)) if a != b => "is different",
+                    _ => return None,
+                };
+                Some(format!("instance {}{} {}", id, self.describe_instance(id), problem))
+            })
+            .collect();
+        Some(Desync { frame: frame_index, instances })
+    }
+
+    // Names an instance's object and position, if it exists, for desync reports
+    fn describe_instance(&self, id: ID) -> String {
+        match self.instance_list.get_by_instid(id).map(|handle| self.instance_list.get(handle)) {
+            Some(instance) => {
+                let object_name = match self.assets.objects.get_asset(instance.object_index.get()) {
+                    Some(object) => object.name.as_ref(),
+                    None => "<deleted object>",
+                };
+                format!(" ({}, at {}, {})", object_name, instance.x.get(), instance.y.get())
+            },
+            None => String::new(),
+        }
+    }
+
+    fn hash_state(&self, mut instances: Option<&mut BTreeMap<ID, u64>>) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write_i32(self.room_id);
+        hasher.write_i32(self.rand.seed());
+        hasher.write_i32(self.last_instance_id);
+        hasher.write_i32(self.score);
+        hasher.write_i32(self.lives);
+        hash_real(&mut hasher, self.health);
+
+        let mut iter = self.instance_list.iter_by_insertion();
+        let mut inactive = self.instance_list.iter_inactive();
+        while let Some(handle) = iter.next(&self.instance_list).or_else(|| inactive.next(&self.instance_list)) {
+            let instance = self.instance_list.get(handle);
+            let hash = self.instance_hash(instance);
+            hasher.write_u64(hash);
+            if let Some(instances) = instances.as_mut() {
+                instances.insert(instance.id.get(), hash);
+            }
+        }
+
+        let mut iter = self.tile_list.iter_by_insertion();
+        while let Some(handle) = iter.next(&self.tile_list) {
+            hash_serialized(&mut hasher, self.tile_list.get(handle));
+        }
+
+        self.hash_fields(&mut hasher, &self.globals.fields);
+        hash_unordered(&mut hasher, self.globals.vars.iter(), |hasher, (var, field)| {
+            hasher.write_u32(*var as u32);
+            hash_field(hasher, field);
+        });
+
+        hash_ds(&mut hasher, &self.stacks, |hasher, stack| hash_values(hasher, stack.iter()));
+        hash_ds(&mut hasher, &self.queues, |hasher, queue| hash_values(hasher, queue.iter()));
+        hash_ds(&mut hasher, &self.lists, |hasher, list| hash_values(hasher, list.iter()));
+        hash_ds(&mut hasher, &self.maps, |hasher, map| {
+            hash_values(hasher, map.keys.iter());
+            hash_values(hasher, map.values.iter());
+        });
+        hash_ds(&mut hasher, &self.priority_queues, |hasher, queue| {
+            hash_values(hasher, queue.priorities.iter());
+            hash_values(hasher, queue.values.iter());
+        });
+        hash_ds(&mut hasher, &self.grids, |hasher, grid| {
+            hasher.write_u64(grid.width() as u64);
+            hasher.write_u64(grid.height() as u64);
+            for x in 0..grid.width() {
+                for y in 0..grid.height() {
+                    hash_value(hasher, grid.get(x, y));
+                }
+            }
+        });
+        hasher.finish()
+    }
+
+    // Hashes a single instance's variables, fields and alarms.
+    // The bounding box is left out since it's a cache which is only updated when something asks for it.
+    pub fn instance_hash(&self, instance: &Instance) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write_u8(instance.state.get() as u8);
+        hasher.write_i32(instance.id.get());
+        hasher.write_i32(instance.object_index.get());
+        hasher.write_u8(instance.solid.get().into());
+        hasher.write_u8(instance.visible.get().into());
+        hasher.write_u8(instance.persistent.get().into());
+        hasher.write_i32(instance.sprite_index.get());
+        hasher.write_i32(instance.image_blend.get());
+        hasher.write_i32(instance.mask_index.get());
+        hasher.write_i32(instance.path_index.get());
+        hasher.write_i32(instance.path_endaction.get());
+        hasher.write_i32(instance.timeline_index.get());
+        hasher.write_u8(instance.timeline_running.get().into());
+        hasher.write_u8(instance.timeline_loop.get().into());
+        for real in &[
+            &instance.depth,
+            &instance.image_alpha,
+            &instance.image_index,
+            &instance.image_speed,
+            &instance.image_xscale,
+            &instance.image_yscale,
+            &instance.image_angle,
+            &instance.direction,
+            &instance.friction,
+            &instance.gravity,
+            &instance.gravity_direction,
+            &instance.hspeed,
+            &instance.vspeed,
+            &instance.speed,
+            &instance.x,
+            &instance.y,
+            &instance.xprevious,
+            &instance.yprevious,
+            &instance.xstart,
+            &instance.ystart,
+            &instance.path_position,
+            &instance.path_positionprevious,
+            &instance.path_speed,
+            &instance.path_scale,
+            &instance.path_orientation,
+            &instance.path_xstart,
+            &instance.path_ystart,
+            &instance.path_pointspeed,
+            &instance.timeline_speed,
+            &instance.timeline_position,
+        ] {
+            hash_real(&mut hasher, real.get());
+        }
+
+        self.hash_fields(&mut hasher, &instance.fields.borrow());
+        hash_unordered(&mut hasher, instance.alarms.borrow().iter(), |hasher, (alarm, time)| {
+            hasher.write_u32(*alarm);
+            hasher.write_i32(*time);
+        });
+        hasher.finish()
+    }
+
+    fn hash_fields(&self, hasher: &mut Fnv1a, fields: &HashMap<usize, Field>) {
+        hash_unordered(hasher, fields.iter(), |hasher, (id, field)| {
+            hasher.write(self.compiler.field_name(*id).unwrap_or_default().as_bytes());
+            hasher.write_u8(0);
+            hash_field(hasher, field);
+        });
+    }
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "desynced on frame {}", self.frame)?;
+        if self.instances.is_empty() {
+            write!(f, " (no instances differ, so something else did)")?;
+        }
+        for instance in self.instances.iter() {
+            write!(f, "\n  {}", instance)?;
+        }
+        Ok(())
+    }
+}
+
+fn hash_field(hasher: &mut Fnv1a, field: &Field) {
+    match field {
+        Field::Single(value) => {
+            hasher.write_u8(0);
+            hash_value(hasher, value);
+        },
+        Field::Array(array) => {
+            hasher.write_u8(1);
+            hash_unordered(hasher, array.iter(), |hasher, (index, value)| {
+                hasher.write_u32(*index);
+                hash_value(hasher, value);
+            });
+        },
+    }
+}
+
+// Hashes each item separately with the given function, then hashes the count and the sum of the hashes
+fn hash_unordered<T>(hasher: &mut Fnv1a, items: impl Iterator<Item = T>, mut hash: impl FnMut(&mut Fnv1a, T)) {
+    let (mut count, mut sum) = (0u64, 0u64);
+    for item in items {
+        let mut item_hasher = Fnv1a::default();
+        hash(&mut item_hasher, item);
+        count += 1;
+        sum = sum.wrapping_add(item_hasher.finish());
+    }
+    hasher.write_u64(count);
+    hasher.write_u64(sum);
+}
+
+// Hashes every data structure which exists in a manager, with its ID, in order of ID
+fn hash_ds<T>(hasher: &mut Fnv1a, manager: &DataStructureManager<T>, mut hash: impl FnMut(&mut Fnv1a, &T)) {
+    for (id, ds) in manager.iter() {
+        hasher.write_i32(id);
+        hash(hasher, ds);
+    }
+    hasher.write_i32(-1);
+}
+
+fn hash_values<'a>(hasher: &mut Fnv1a, values: impl ExactSizeIterator<Item = &'a Value>) {
+    hasher.write_u64(values.len() as u64);
+    for value in values {
+        hash_value(hasher, value);
+    }
+}
+
+fn hash_value(hasher: &mut Fnv1a, value: &Value) {
+    match value {
+        Value::Real(real) => {
+            hasher.write_u8(0);
+            hash_real(hasher, *real);
+        },
+        Value::Str(string) => {
+            hasher.write_u8(1);
+            hasher.write_u64(string.as_ref().len() as u64);
+            hasher.write(string.as_ref().as_bytes());
+        },
+    }
+}
+
+fn hash_real(hasher: &mut Fnv1a, real: Real) {
+    hasher.write_u64(f64::from(real).to_bits());
+}
+
+fn hash_serialized(hasher: &mut Fnv1a, value: &impl Serialize) {
+    // writing to a hasher can't fail
+    let _ = bincode::serialize_into(hasher, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+
+    #[test]
+    fn field_order() {
+        // the fields get different IDs in each game, but that shouldn't change the hash
+        let mut a = Game::new_sandbox();
+        let mut b = Game::new_sandbox();
+        a.execute_console("global.foo = 1; global.bar[2] = \"x\"", None).unwrap();
+        b.execute_console("global.bar[2] = \"x\"; global.foo = 1", None).unwrap();
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        b.execute_console("global.foo = 2", None).unwrap();
+        assert_ne!(a.state_hash(), b.state_hash());
+    }
+}
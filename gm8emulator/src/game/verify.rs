@@ -0,0 +1,79 @@
+// Plays a replay back as fast as possible without showing anything, then reports where it ended up.
+// This is for checking that changes to the emulator haven't broken existing replays, so it's meant to be run
+// on a headless game from a script, and it stops as soon as the replay stops matching the hashes stored in it.
+
+use crate::game::{statehash::Desync, Game, PlayType, Replay, SceneChange};
+use std::{collections::BTreeMap, time::Instant};
+
+/// Where a replay ended up after being played back by Game::verify.
+pub struct Verification {
+    /// The number of frames which were run.
+    pub frames: usize,
+
+    /// The RNG seed after the last frame.
+    pub seed: i32,
+
+    /// The room the game was in after the last frame.
+    pub room_id: i32,
+
+    /// Hash of the game's state after the last frame. See Game::state_hash.
+    pub state_hash: u64,
+
+    /// The first frame whose state didn't match the hash stored in the replay, if any.
+    /// Playback stops at this frame, since everything after it is expected to differ.
+    pub desync: Option<Desync>,
+}
+
+impl Game {
+    // Plays back every frame of a replay, ignoring the window and the frame limiter, checking any hashes in it.
+    // Returns an error if the game hits a runtime error before the replay ends.
+    pub fn verify(&mut self, replay: &Replay) -> Result<Verification, Box<dyn std::error::Error>> {
+        self.rand.set_seed(replay.start_seed);
+        self.spoofed_time_nanos = Some(replay.start_time);
+        self.play_type = PlayType::Replay;
+        self.speed.turbo = true;
+        self.speed.skip_present = true;
+
+        let mut frame_count: usize = 0;
+        let mut desync = None;
+        let mut instance_hashes = BTreeMap::new();
+        let mut time_now = Instant::now();
+        while let Some(frame) = replay.get_frame(frame_count) {
+            let on_frame = |e: &dyn std::fmt::Display| format!("on frame {}: {}", frame_count, e);
+
+            self.input_manager.mouse_update_previous();
+            self.apply_replay_frame(frame);
+            self.frame().map_err(|e| on_frame(&e))?;
+            let end = match self.scene_change {
+                Some(SceneChange::Room(id)) => {
+                    self.load_room(id).map_err(|e| on_frame(&e))?;
+                    false
+                },
+                Some(SceneChange::Restart) => {
+                    self.restart().map_err(|e| on_frame(&e))?;
+                    false
+                },
+                Some(SceneChange::End) => {
+                    self.run_game_end_events().map_err(|e| on_frame(&e))?;
+                    true
+                },
+                None => false,
+            };
+            desync = self.check_frame_hash(frame, frame_count, &mut instance_hashes);
+            self.limit_frame_rate(&mut time_now);
+            frame_count += 1;
+
+            if end || desync.is_some() {
+                break
+            }
+        }
+
+        Ok(Verification {
+            frames: frame_count,
+            seed: self.rand.seed(),
+            room_id: self.room_id,
+            state_hash: self.state_hash(),
+            desync,
+        })
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    game::{Game, GetAsset},
+    gml::{
+        compiler::{mappings, Compiler},
+        Context, InstanceVariable,
+    },
+    instance::Instance,
+};
+use shared::message::{WatchTarget, WatchedVariable, Watchpoint, WatchpointHit};
+
+/// A variable being watched, resolved from its name.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Watched {
+    Field(usize),
+    Variable(InstanceVariable),
+}
+
+impl Watched {
+    /// Looks up a variable by name. Names which aren't builtin variables are taken to be fields,
+    /// which have to appear somewhere in the game's code. The compiler is left alone, since it's part of the game's
+    /// state and watching a variable shouldn't change that.
+    pub fn from_name(name: &str, compiler: &Compiler) -> Result<Self, String> {
+        match mappings::get_instance_variable_by_name(name) {
+            Some(InstanceVariable::AsyncLoad) => Err("async_load can't be watched".into()),
+            Some(var) => Ok(Watched::Variable(*var)),
+            None => match compiler.find_field_id(name) {
+                Some(field_id) => Ok(Watched::Field(field_id)),
+                None => Err(format!("unknown variable {}", name)),
+            },
+        }
+    }
+}
+
+/// The set of active watchpoints, and any hits which haven't been collected yet.
+pub struct Watchpoints {
+    list: Vec<(Watchpoint, Watched)>,
+    hits: Vec<WatchpointHit>,
+
+    /// The frame number reported with each hit, which recording mode sets from the replay before each frame.
+    pub frame: usize,
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self { list: Vec::new(), hits: Vec::new(), frame: 0 }
+    }
+
+    /// Replaces the list of watchpoints, looking up each variable name.
+    /// Nothing is changed if any of the variables can't be watched.
+    pub fn set(&mut self, watchpoints: Vec<Watchpoint>, compiler: &Compiler) -> Result<(), String> {
+        let mut list = Vec::with_capacity(watchpoints.len());
+        for watchpoint in watchpoints {
+            let watched = Watched::from_name(&watchpoint.variable, compiler)?;
+            if let Watched::Variable(var) = watched {
+                if var.is_read_only() {
+                    return Err(format!("{} is read-only", watchpoint.variable))
+                }
+            }
+            list.push((watchpoint, watched));
+        }
+        self.list = list;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Takes all the hits recorded since the last call.
+    pub fn take_hits(&mut self) -> Vec<WatchpointHit> {
+        std::mem::take(&mut self.hits)
+    }
+}
+
+impl Default for Watchpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The variables whose values are sent to the control panel with every update, looked up from their names.
+/// Unlike watchpoints, these can be read-only variables.
+pub struct WatchList(Vec<(WatchedVariable, Watched)>);
+
+impl WatchList {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Replaces the list. Nothing is changed if any of the variables can't be watched.
+    /// Builtin variables which belong to an instance, like x, have to be given one.
+    pub fn set(&mut self, variables: Vec<WatchedVariable>, compiler: &Compiler) -> Result<(), String> {
+        let mut list = Vec::with_capacity(variables.len());
+        for variable in variables {
+            let watched = Watched::from_name(&variable.variable, compiler)?;
+            if let (None, Watched::Variable(var)) = (variable.instance, watched) {
+                if !var.is_global() {
+                    return Err(format!("{} isn't a global variable, so it needs an instance", variable.variable))
+                }
+            }
+            list.push((variable, watched));
+        }
+        self.0 = list;
+        Ok(())
+    }
+}
+
+impl Default for WatchList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A write to a watched variable which is about to happen, holding the value from before the write.
+pub struct PendingWrite {
+    watchpoints: Vec<usize>,
+    instance: usize,
+    watched: Watched,
+    array_index: u32,
+    old_value: String,
+}
+
+impl Game {
+    /// Checks whether a variable on an instance is being watched, before writing to it.
+    /// If it is, pass the result to watch_end after the write, which logs a hit if the value changed.
+    pub fn watch_begin(
+        &self,
+        instance: usize,
+        watched: Watched,
+        array_index: u32,
+        context: &Context,
+    ) -> Option<PendingWrite> {
+        if self.watchpoints.is_empty() {
+            return None
+        }
+        let inst = self.instance_list.get(instance);
+        let (id, object_index) = (inst.id.get(), inst.object_index.get());
+        let watchpoints = (0..self.watchpoints.list.len())
+            .filter(|&i| {
+                let (watchpoint, w) = &self.watchpoints.list[i];
+                *w == watched
+                    && match watchpoint.target {
+                        WatchTarget::Instance(target) => target == id,
+                        WatchTarget::Object(target) => match self.assets.objects.get_asset(target) {
+                            Some(object) => object.children.borrow().contains(&object_index),
+                            None => false,
+                        },
+                    }
+            })
+            .collect::<Vec<_>>();
+        if watchpoints.is_empty() {
+            return None
+        }
+        let old_value = self.watched_value(instance, watched, array_index, context);
+        Some(PendingWrite { watchpoints, instance, watched, array_index, old_value })
+    }
+
+    /// Finishes a watched write, storing a hit for each watchpoint it matched if the variable's value was changed.
+    pub fn watch_end(&mut self, write: PendingWrite, context: &Context) {
+        let new_value = self.watched_value(write.instance, write.watched, write.array_index, context);
+        if new_value == write.old_value {
+            return
+        }
+        let source = match self.assets.objects.get_asset(context.event_object) {
+            Some(object) => format!(
+                "object {} event {},{} action {}",
+                object.name, context.event_type, context.event_number, context.event_action
+            ),
+            None => "<deleted object>".into(),
+        };
+        let instance = self.instance_list.get(write.instance).id.get();
+        for index in write.watchpoints {
+            let (watchpoint, _) = &self.watchpoints.list[index];
+            let hit = WatchpointHit {
+                frame: self.watchpoints.frame,
+                instance,
+                variable: watchpoint.variable.clone(),
+                array_index: write.array_index,
+                old_value: write.old_value.clone(),
+                new_value: new_value.clone(),
+                source: source.clone(),
+                pause: watchpoint.pause,
+            };
+            self.watchpoints.hits.push(hit);
+        }
+    }
+
+    /// Gets the current value of everything on a watch list as strings, in order.
+    pub fn watch_list_values(&mut self, list: &WatchList) -> Vec<String> {
+        if list.0.is_empty() {
+            return Vec::new()
+        }
+        // Builtin globals like score are read through an instance, so give them one which can't have changed them
+        let dummy = self.instance_list.insert_dummy(Instance::new_dummy(None));
+        let values = list
+            .0
+            .iter()
+            .map(|(variable, watched)| match (variable.instance, watched) {
+                (Some(id), _) => match self.instance_list.get_by_instid(id) {
+                    Some(instance) => {
+                        self.watched_value(instance, *watched, variable.array_index, &self.watch_context(instance))
+                    },
+                    None => "<no instance>".into(),
+                },
+                (None, Watched::Field(field_id)) => {
+                    match self.globals.fields.get(field_id).and_then(|f| f.get(variable.array_index)) {
+                        Some(value) => value.to_string(),
+                        None => "<uninitialized>".into(),
+                    }
+                },
+                // WatchList::set only lets these be builtin globals
+                (None, Watched::Variable(_)) => {
+                    self.watched_value(dummy, *watched, variable.array_index, &self.watch_context(dummy))
+                },
+            })
+            .collect();
+        self.instance_list.remove_dummy(dummy);
+        values
+    }
+
+    // Makes a context for reading variables on an instance outside of any event
+    fn watch_context(&self, instance: usize) -> Context {
+        Context {
+            this: instance,
+            other: instance,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: self.instance_list.get(instance).object_index.get(),
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        }
+    }
+
+    // Gets the current value of a watched variable as a string
+    fn watched_value(&self, instance: usize, watched: Watched, array_index: u32, context: &Context) -> String {
+        let value = match watched {
+            Watched::Field(field_id) => {
+                self.instance_list.get(instance).fields.borrow().get(&field_id).and_then(|f| f.get(array_index))
+            },
+            Watched::Variable(var) => self.get_instance_var(instance, &var, array_index, context).ok(),
+        };
+        match value {
+            Some(value) => value.to_string(),
+            None => "<uninitialized>".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_list_instances() {
+        let compiler = Compiler::new();
+        let watch = |instance, variable: &str| WatchedVariable { instance, variable: variable.into(), array_index: 0 };
+        let mut list = WatchList::new();
+        assert!(list.set(vec![watch(None, "score"), watch(Some(100001), "x")], &compiler).is_ok());
+        assert!(list.set(vec![watch(None, "score"), watch(None, "x")], &compiler).is_err());
+        assert_eq!(list.0.len(), 2);
+    }
+}
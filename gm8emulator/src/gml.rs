@@ -1,9 +1,11 @@
 pub mod compiler;
 pub mod context;
 pub mod datetime;
+pub mod decompiler;
 pub mod ds;
 pub mod file;
 pub mod kernel;
+pub mod lint;
 pub mod rand;
 pub mod runtime;
 pub mod value;
@@ -213,3 +215,149 @@ pub enum InstanceVariable {
     DisplayAa,
     AsyncLoad,
 }
+
+impl InstanceVariable {
+    /// Whether this variable can never be assigned to from GML. Writing to one of these is a runtime error.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::ArgumentCount
+                | Self::ArgumentRelative
+                | Self::AsyncLoad
+                | Self::BackgroundHeight
+                | Self::BackgroundWidth
+                | Self::BboxBottom
+                | Self::BboxLeft
+                | Self::BboxRight
+                | Self::BboxTop
+                | Self::BrowserHeight
+                | Self::BrowserWidth
+                | Self::CurrentDay
+                | Self::CurrentHour
+                | Self::CurrentMinute
+                | Self::CurrentMonth
+                | Self::CurrentSecond
+                | Self::CurrentTime
+                | Self::CurrentWeekday
+                | Self::CurrentYear
+                | Self::DebugMode
+                | Self::DisplayAa
+                | Self::EventAction
+                | Self::EventNumber
+                | Self::EventObject
+                | Self::EventType
+                | Self::Fps
+                | Self::GameId
+                | Self::GamemakerPro
+                | Self::GamemakerRegistered
+                | Self::GamemakerVersion
+                | Self::Id
+                | Self::ImageNumber
+                | Self::InstanceCount
+                | Self::InstanceId
+                | Self::MouseX
+                | Self::MouseY
+                | Self::ObjectIndex
+                | Self::OsBrowser
+                | Self::OsDevice
+                | Self::OsType
+                | Self::OsVersion
+                | Self::PathIndex
+                | Self::ProgramDirectory
+                | Self::RoomFirst
+                | Self::RoomHeight
+                | Self::RoomLast
+                | Self::RoomWidth
+                | Self::SecureMode
+                | Self::SpriteHeight
+                | Self::SpriteWidth
+                | Self::SpriteXoffset
+                | Self::SpriteYoffset
+                | Self::TempDirectory
+                | Self::ViewCurrent
+                | Self::WorkingDirectory
+        )
+    }
+
+    /// Whether this variable is the same whichever instance reads it, rather than belonging to the instance
+    /// or to the event it's running.
+    pub fn is_global(&self) -> bool {
+        !matches!(
+            self,
+            Self::Alarm
+                | Self::Argument
+                | Self::Argument0
+                | Self::Argument1
+                | Self::Argument2
+                | Self::Argument3
+                | Self::Argument4
+                | Self::Argument5
+                | Self::Argument6
+                | Self::Argument7
+                | Self::Argument8
+                | Self::Argument9
+                | Self::Argument10
+                | Self::Argument11
+                | Self::Argument12
+                | Self::Argument13
+                | Self::Argument14
+                | Self::Argument15
+                | Self::ArgumentCount
+                | Self::ArgumentRelative
+                | Self::BboxBottom
+                | Self::BboxLeft
+                | Self::BboxRight
+                | Self::BboxTop
+                | Self::Depth
+                | Self::Direction
+                | Self::EventAction
+                | Self::EventNumber
+                | Self::EventObject
+                | Self::EventType
+                | Self::Friction
+                | Self::Gravity
+                | Self::GravityDirection
+                | Self::Hspeed
+                | Self::Id
+                | Self::ImageAlpha
+                | Self::ImageAngle
+                | Self::ImageBlend
+                | Self::ImageIndex
+                | Self::ImageNumber
+                | Self::ImageSingle
+                | Self::ImageSpeed
+                | Self::ImageXscale
+                | Self::ImageYscale
+                | Self::MaskIndex
+                | Self::ObjectIndex
+                | Self::PathEndAction
+                | Self::PathIndex
+                | Self::PathOrientation
+                | Self::PathPosition
+                | Self::PathPositionprevious
+                | Self::PathScale
+                | Self::PathSpeed
+                | Self::Persistent
+                | Self::Solid
+                | Self::Speed
+                | Self::SpriteHeight
+                | Self::SpriteIndex
+                | Self::SpriteWidth
+                | Self::SpriteXoffset
+                | Self::SpriteYoffset
+                | Self::TimelineIndex
+                | Self::TimelineLoop
+                | Self::TimelinePosition
+                | Self::TimelineRunning
+                | Self::TimelineSpeed
+                | Self::Visible
+                | Self::Vspeed
+                | Self::X
+                | Self::Xprevious
+                | Self::Xstart
+                | Self::Y
+                | Self::Yprevious
+                | Self::Ystart
+        )
+    }
+}
@@ -431,7 +431,7 @@ impl Compiler {
             Operator::AssignBitwiseAnd => Some(BinaryOperator::BitwiseAnd),
             Operator::AssignBitwiseOr => Some(BinaryOperator::BitwiseOr),
             Operator::AssignBitwiseXor => Some(BinaryOperator::BitwiseXor),
-            _ => unreachable!("Invalid assignment operator: {}", binary_expr.op),
+            op => return Instruction::RuntimeError { error: gml::Error::InvalidBinaryOperator(op) },
         };
 
         let value = self.compile_ast_expr(&binary_expr.right, locals);
@@ -629,4 +629,40 @@ impl Compiler {
     pub fn get_field_name(&self, id: usize) -> Option<String> {
         self.fields.get(id).map(String::clone)
     }
+
+    /// Get a field name by its ID without cloning it.
+    pub fn field_name(&self, id: usize) -> Option<&str> {
+        self.fields.get(id).map(String::as_str)
+    }
+
+    /// Get how many field names have been given IDs.
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Forget the names of the fields given IDs since there were `count` of them, so those IDs can go to other names.
+    pub fn forget_fields_from(&mut self, count: usize) {
+        self.fields.truncate(count);
+    }
+
+    /// Get a field's ID by its name, without giving it one if it doesn't have one yet.
+    pub fn find_field_id(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|x| x == name)
+    }
+
+    /// Get the name of a user-defined constant by its ID.
+    pub fn get_user_constant_name(&self, id: usize) -> Option<&str> {
+        self.user_constant_names.iter().find(|(_, i)| **i == id).map(|(name, _)| name.as_str())
+    }
+
+    /// Get the name of a script by its ID.
+    pub fn get_script_name(&self, id: usize) -> Option<&str> {
+        self.script_names.iter().find(|(_, i)| **i == id).map(|(name, _)| name.as_str())
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -9,6 +9,10 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+/// How deeply code and expressions can be nested before the parser gives up on them.
+/// No real game comes close to this, but without a limit, malformed code could overflow the stack.
+const MAX_DEPTH: usize = 512;
+
 #[derive(Debug, PartialEq)]
 pub struct AST<'a>(Vec<Expr<'a>>);
 
@@ -250,7 +254,7 @@ impl<'a> AST<'a> {
 
         loop {
             // Get the first token from the iterator, or exit the loop if there are no more
-            match AST::read_line(&mut lex) {
+            match AST::read_line(&mut lex, 0) {
                 Ok(Some(expr)) => expressions.push(expr),
                 Ok(None) => break,
                 Err(e) => return Err(e),
@@ -262,11 +266,15 @@ impl<'a> AST<'a> {
 
     pub fn expression(source: &'a str) -> Result<Expr<'a>, Error> {
         let mut lex = Lexer::new(source).peekable();
-        let expr = AST::read_binary_tree(&mut lex, None, false)?;
+        let expr = AST::read_binary_tree(&mut lex, None, false, 0)?;
         Ok(expr)
     }
 
-    fn read_line(lex: &mut Peekable<Lexer<'a>>) -> Result<Option<Expr<'a>>, Error> {
+    fn read_line(lex: &mut Peekable<Lexer<'a>>, depth: usize) -> Result<Option<Expr<'a>>, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::new("Code is nested too deeply".to_string()))
+        }
+
         let token = loop {
             match lex.next() {
                 Some(Token::Separator(Separator::Semicolon)) => continue,
@@ -328,24 +336,24 @@ impl<'a> AST<'a> {
                     },
 
                     Keyword::Do => {
-                        let body = AST::read_line(lex)?
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'do' keyword".to_string()))?;
                         expect_token!(lex.next(), Keyword(Keyword::Until));
-                        let cond = AST::read_binary_tree(lex, None, false)?;
+                        let cond = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         Ok(Some(Expr::DoUntil(Box::new(DoUntilExpr { cond, body }))))
                     },
 
                     Keyword::If => {
-                        let cond = AST::read_binary_tree(lex, None, false)?;
+                        let cond = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         if lex.peek() == Some(&Token::Separator(Separator::Then)) {
                             lex.next();
                         }
-                        let body = AST::read_line(lex)?
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'if' condition".to_string()))?;
                         let else_body = if lex.peek() == Some(&Token::Keyword(Keyword::Else)) {
                             lex.next(); // consume 'else'
                             Some(
-                                AST::read_line(lex)?
+                                AST::read_line(lex, depth + 1)?
                                     .ok_or_else(|| Error::new("Unexpected EOF after 'else' keyword".to_string()))?,
                             )
                         } else {
@@ -356,59 +364,59 @@ impl<'a> AST<'a> {
 
                     Keyword::For => {
                         expect_token!(lex.next(), Separator(Separator::ParenLeft));
-                        let start = AST::read_line(lex)?
+                        let start = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF during 'for' params".to_string()))?;
                         if lex.peek() == Some(&Token::Separator(Separator::Semicolon)) {
                             lex.next();
                         }
-                        let cond = AST::read_binary_tree(lex, None, false)?;
+                        let cond = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         if lex.peek() == Some(&Token::Separator(Separator::Semicolon)) {
                             lex.next();
                         }
-                        let step = AST::read_line(lex)?
+                        let step = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF during 'for' params".to_string()))?;
                         while lex.peek() == Some(&Token::Separator(Separator::Semicolon)) {
                             lex.next();
                         }
                         expect_token!(lex.next(), Separator(Separator::ParenRight));
-                        let body = AST::read_line(lex)?
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'for' params".to_string()))?;
                         Ok(Some(Expr::For(Box::new(ForExpr { start, cond, step, body }))))
                     },
 
                     Keyword::Repeat => {
-                        let count = AST::read_binary_tree(lex, None, false)?;
-                        let body = AST::read_line(lex)?
+                        let count = AST::read_binary_tree(lex, None, false, depth + 1)?;
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'repeat' condition".to_string()))?;
                         Ok(Some(Expr::Repeat(Box::new(RepeatExpr { count, body }))))
                     },
 
                     Keyword::Switch => {
-                        let input = AST::read_binary_tree(lex, None, false)?;
-                        let body = AST::read_line(lex)?
+                        let input = AST::read_binary_tree(lex, None, false, depth + 1)?;
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'switch' condition".to_string()))?;
                         Ok(Some(Expr::Switch(Box::new(SwitchExpr { input, body }))))
                     },
 
                     Keyword::With => {
-                        let target = AST::read_binary_tree(lex, None, false)?;
-                        let body = AST::read_line(lex)?
+                        let target = AST::read_binary_tree(lex, None, false, depth + 1)?;
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'with' condition".to_string()))?;
                         Ok(Some(Expr::With(Box::new(WithExpr { target, body }))))
                     },
 
                     Keyword::While => {
-                        let cond = AST::read_binary_tree(lex, None, false)?;
+                        let cond = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         if lex.peek() == Some(&Token::Keyword(Keyword::Do)) {
                             lex.next();
                         }
-                        let body = AST::read_line(lex)?
+                        let body = AST::read_line(lex, depth + 1)?
                             .ok_or_else(|| Error::new("Unexpected EOF after 'while' condition".to_string()))?;
                         Ok(Some(Expr::While(Box::new(WhileExpr { cond, body }))))
                     },
 
                     Keyword::Case => {
-                        let expr = AST::read_binary_tree(lex, None, false)?;
+                        let expr = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         expect_token!(lex.next(), Separator(Separator::Colon));
                         Ok(Some(Expr::Case(Box::new(expr))))
                     },
@@ -425,7 +433,7 @@ impl<'a> AST<'a> {
                     Keyword::Exit => Ok(Some(Expr::Exit)),
 
                     Keyword::Return => {
-                        let val = AST::read_binary_tree(lex, None, false)?;
+                        let val = AST::read_binary_tree(lex, None, false, depth + 1)?;
                         Ok(Some(Expr::Return(Box::new(val))))
                     },
 
@@ -442,9 +450,9 @@ impl<'a> AST<'a> {
                 };
                 match next_token {
                     Token::Separator(ref sep) if *sep == Separator::ParenLeft => {
-                        Ok(Some(AST::read_function_call(lex, id)?))
+                        Ok(Some(AST::read_function_call(lex, id, depth)?))
                     },
-                    _ => Ok(Some(AST::read_binary_tree(lex, Some(token), true)?)),
+                    _ => Ok(Some(AST::read_binary_tree(lex, Some(token), true, depth + 1)?)),
                 }
             },
 
@@ -459,7 +467,7 @@ impl<'a> AST<'a> {
                                     lex.next();
                                     break Ok(Some(Expr::Group(inner_expressions)))
                                 },
-                                _ => match AST::read_line(lex) {
+                                _ => match AST::read_line(lex, depth + 1) {
                                     Ok(Some(e)) => inner_expressions.push(e),
                                     Ok(None) => break Err(Error::new("Unclosed brace at EOF".to_string())),
                                     Err(e) => break Err(e),
@@ -471,7 +479,7 @@ impl<'a> AST<'a> {
                     // An assignment may start with an open-parenthesis, eg: (1).x = 400;
                     Separator::ParenLeft => {
                         let binary_tree =
-                            AST::read_binary_tree(lex, Some(Token::Separator(Separator::ParenLeft)), true)?;
+                            AST::read_binary_tree(lex, Some(Token::Separator(Separator::ParenLeft)), true, depth + 1)?;
                         Ok(Some(binary_tree))
                     },
 
@@ -495,8 +503,9 @@ impl<'a> AST<'a> {
         lex: &mut Peekable<Lexer<'a>>,
         first_token: Option<Token<'a>>, // Sometimes we've already parsed the first token, so it should be put here.
         expect_assignment: bool,        // Do we expect the first op to be an assignment?
+        depth: usize,
     ) -> Result<Expr<'a>, Error> {
-        let (val, op) = AST::read_binary_tree_recursive(lex, first_token, expect_assignment, 0)?;
+        let (val, op) = AST::read_binary_tree_recursive(lex, first_token, expect_assignment, 0, depth)?;
         if let Some(stray_op) = op {
             Err(Error::new(format!("read_binary_tree has stray operator: {:?}", stray_op)))
         } else {
@@ -510,9 +519,10 @@ impl<'a> AST<'a> {
         expect_assignment: bool,        // Do we expect the first op to be an assignment?
         lowest_prec: u8,                // We are not allowed to go below this operator precedence in this tree.
                                         // If we do, we'll return the next op.
+        depth: usize,
     ) -> Result<(Expr<'a>, Option<Operator>), Error> {
         // Get the first expression before any operators
-        let mut lhs = AST::read_btree_expression(lex, first_token)?;
+        let mut lhs = AST::read_btree_expression(lex, first_token, depth)?;
 
         // Check if the next token is an operator
         let next_token = lex.peek();
@@ -542,7 +552,7 @@ impl<'a> AST<'a> {
                         // We're allowed to use the next operator. Let's read an RHS to put on after it.
                         // We limit this tree to current precedence + 1 to prevent it using operators of our
                         // current precedence.  This way, 1/2/3 is correctly built as (1/2)/3 rather than 1/(2/3).
-                        let (rhs, next_op) = AST::read_binary_tree_recursive(lex, None, false, precedence + 1)?;
+                        let (rhs, next_op) = AST::read_binary_tree_recursive(lex, None, false, precedence + 1, depth)?;
                         if let Some(next_op) = next_op {
                             // There's another operator even after the RHS.
                             if let Some(next_prec) = AST::get_op_precedence(&next_op) {
@@ -576,7 +586,8 @@ impl<'a> AST<'a> {
                             break Err(Error::new(format!("Invalid operator {:?} found, expected evaluable", op)))
                         } else {
                             // No need to do precedence on an assignment, so just grab RHS and return
-                            let (rhs, stray_op) = AST::read_binary_tree_recursive(lex, None, false, lowest_prec)?;
+                            let (rhs, stray_op) =
+                                AST::read_binary_tree_recursive(lex, None, false, lowest_prec, depth)?;
                             break if let Some(op) = stray_op {
                                 Err(Error::new(format!("Stray operator {:?} in expression", op)))
                             } else {
@@ -596,11 +607,19 @@ impl<'a> AST<'a> {
         }
     }
 
-    fn read_btree_expression(lex: &mut Peekable<Lexer<'a>>, first_token: Option<Token<'a>>) -> Result<Expr<'a>, Error> {
+    fn read_btree_expression(
+        lex: &mut Peekable<Lexer<'a>>,
+        first_token: Option<Token<'a>>,
+        depth: usize,
+    ) -> Result<Expr<'a>, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::new("Expression is nested too deeply".to_string()))
+        }
+
         // Get first token and match it
         let mut lhs = match if first_token.is_some() { first_token } else { lex.next() } {
             Some(Token::Separator(ref sep)) if *sep == Separator::ParenLeft => {
-                let binary_tree = AST::read_binary_tree(lex, None, false)?;
+                let binary_tree = AST::read_binary_tree(lex, None, false, depth + 1)?;
                 if lex.next() != Some(Token::Separator(Separator::ParenRight)) {
                     return Err(Error::new("Unclosed parenthesis in binary tree".to_string()))
                 } else {
@@ -610,14 +629,14 @@ impl<'a> AST<'a> {
             Some(Token::Operator(op)) => {
                 if op == Operator::Add || op == Operator::Subtract || op == Operator::Not || op == Operator::Complement
                 {
-                    Expr::Unary(Box::new(UnaryExpr { op, child: AST::read_btree_expression(lex, None)? }))
+                    Expr::Unary(Box::new(UnaryExpr { op, child: AST::read_btree_expression(lex, None, depth + 1)? }))
                 } else {
                     return Err(Error::new(format!("Invalid unary operator {:?} in expression", op)))
                 }
             },
             Some(Token::Identifier(t)) => {
                 if lex.peek() == Some(&Token::Separator(Separator::ParenLeft)) {
-                    AST::read_function_call(lex, t)?
+                    AST::read_function_call(lex, t, depth)?
                 } else {
                     Expr::LiteralIdentifier(t)
                 }
@@ -639,7 +658,7 @@ impl<'a> AST<'a> {
                         lex.next();
                     } else {
                         loop {
-                            let dim = AST::read_binary_tree(lex, None, false)?;
+                            let dim = AST::read_binary_tree(lex, None, false, depth + 1)?;
                             dimensions.push(dim);
                             match lex.next() {
                                 Some(Token::Separator(Separator::BracketRight)) => break,
@@ -686,7 +705,11 @@ impl<'a> AST<'a> {
         Ok(lhs)
     }
 
-    fn read_function_call(lex: &mut Peekable<Lexer<'a>>, function_name: &'a str) -> Result<Expr<'a>, Error> {
+    fn read_function_call(
+        lex: &mut Peekable<Lexer<'a>>,
+        function_name: &'a str,
+        depth: usize,
+    ) -> Result<Expr<'a>, Error> {
         expect_token!(lex.next(), Separator(Separator::ParenLeft));
 
         let mut params = Vec::new();
@@ -694,7 +717,7 @@ impl<'a> AST<'a> {
             lex.next();
         } else {
             loop {
-                let param = AST::read_binary_tree(lex, None, false)?;
+                let param = AST::read_binary_tree(lex, None, false, depth + 1)?;
                 params.push(param);
                 match lex.next() {
                     Some(Token::Separator(Separator::ParenRight)) => break,
@@ -1392,4 +1415,28 @@ mod tests {
         // expression with extra code after it - extra code should be dropped
         assert_eq!(AST::expression("0; a=1; game_end()").unwrap(), Expr::LiteralReal(0.0));
     }
+
+    #[test]
+    fn comments() {
+        // comments are skipped, even if there are a lot of them in a row
+        assert_eq!(*AST::new("a = 1 // b = 2\n/* c = 3 */ d /**/ = 4").unwrap(), *AST::new("a = 1 d = 4").unwrap());
+        assert_ast(&"// comment\n".repeat(100000), Some(vec![]));
+        assert_ast(&"/**/".repeat(100000), Some(vec![]));
+    }
+
+    #[test]
+    fn nested_too_deep() {
+        // deeply nested code should be an error, not a stack overflow
+        // games compile their code on the main thread, so this gets as much stack as that does
+        let parse = || {
+            assert!(AST::new(&format!("a = {}1", "(".repeat(100000))).is_err());
+            assert!(AST::new(&"{".repeat(100000)).is_err());
+            assert!(AST::new(&"if a ".repeat(100000)).is_err());
+            assert!(AST::expression(&"-".repeat(100000)).is_err());
+            assert!(AST::expression(&"f(".repeat(100000)).is_err());
+            assert!(AST::expression(&"a[".repeat(100000)).is_err());
+            assert_ast(&format!("a = {}1{}", "(".repeat(100), ")".repeat(100)), None);
+        };
+        std::thread::Builder::new().stack_size(8 << 20).spawn(parse).unwrap().join().unwrap();
+    }
 }
@@ -27,7 +27,7 @@ impl<'a> Lexer<'a> {
         self.line_hint
     }
 
-    /// Fast-forwards the internal iterator to the next token, skipping over whitespace.
+    /// Fast-forwards the internal iterator to the next token, skipping over whitespace and comments.
     /// Returns how many lines (LF) were skipped in the process.
     fn fast_forward(&mut self) -> usize {
         let mut lines_skipped: usize = 0;
@@ -39,6 +39,36 @@ impl<'a> Lexer<'a> {
                     }
                     self.iter.next();
                 },
+                Some(&(i, b'/')) => match self.src.as_bytes().get(i + 1) {
+                    // single line comments
+                    Some(b'/') => {
+                        while let Some(&(_, ch)) = self.iter.peek() {
+                            match ch {
+                                b'\n' | b'\r' => break,
+                                _ => {
+                                    self.iter.next();
+                                },
+                            }
+                        }
+                    },
+
+                    // multi-line comments
+                    Some(b'*') => {
+                        self.iter.next();
+                        self.iter.next();
+                        let mut star = false;
+                        while let Some((_, ch)) = self.iter.next() {
+                            match ch {
+                                b'/' if star => break,
+                                b'\n' => lines_skipped += 1,
+                                _ => (),
+                            }
+                            star = ch == b'*';
+                        }
+                    },
+
+                    _ => break lines_skipped,
+                },
                 _ => break lines_skipped,
             }
         }
@@ -272,20 +302,6 @@ impl<'a> Iterator for Lexer<'a> {
 
                             Operator::Assign => Operator::Equal,
 
-                            // single line comments
-                            Operator::Divide => {
-                                self.iter.next();
-                                while let Some(&(_, ch)) = self.iter.peek() {
-                                    match ch {
-                                        b'\n' | b'\r' => break,
-                                        _ => {
-                                            self.iter.next();
-                                        },
-                                    }
-                                }
-                                return self.next()
-                            },
-
                             _ => return Some(Token::Operator(op)),
                         };
                         self.iter.next(); // consume ch2
@@ -315,25 +331,6 @@ impl<'a> Iterator for Lexer<'a> {
                         };
                         self.iter.next(); // consume ch2
                         Token::Operator(eq_combo)
-                    } else if op == Operator::Divide && ch2 == b'*' {
-                        // multi-line comments
-
-                        self.iter.next();
-                        while let Some(&(_, ch)) = self.iter.peek() {
-                            match ch {
-                                b'*' => {
-                                    self.iter.next();
-                                    if let Some(&(_, b'/')) = self.iter.peek() {
-                                        self.iter.next();
-                                        break
-                                    }
-                                },
-                                _ => {
-                                    self.iter.next();
-                                },
-                            }
-                        }
-                        return self.next()
                     } else if op == Operator::LessThan && ch2 == b'>' {
                         // <> is the same as != (let's call it a diamond)
 
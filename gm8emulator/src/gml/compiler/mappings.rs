@@ -3,6 +3,7 @@ use crate::{
     gml::{self, Context, InstanceVariable, Value},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub mod constants {
     pub const ANSI_CHARSET: f64 = 0.0f64;
@@ -325,7 +326,7 @@ pub mod constants {
 }
 
 /// Enum of all kernel functions
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Function {
     DisplayGetWidth,
     DisplayGetHeight,
@@ -2897,6 +2898,17 @@ impl Function {
         };
         f(game, context, args)
     }
+
+    /// Gets the GML name of this function, as it would be written in code.
+    pub fn name(&self) -> &'static str {
+        FUNCTION_NAMES.with(|names| names[self])
+    }
+}
+
+thread_local! {
+    /// The reverse of FUNCTIONS, for Function::name.
+    static FUNCTION_NAMES: HashMap<Function, &'static str> =
+        FUNCTIONS.iter().map(|(name, function, _)| (*function, *name)).collect();
 }
 
 /// Standard numerical constants for GML
@@ -4679,3 +4691,15 @@ pub const FUNCTIONS: [(&str, Function, bool); 1281] = [
     ("d3d_model_wall", Function::D3DModelWall, false),
     ("d3d_model_floor", Function::D3DModelFloor, false),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_names() {
+        for (name, function, _) in FUNCTIONS.iter() {
+            assert_eq!(function.name(), *name);
+        }
+    }
+}
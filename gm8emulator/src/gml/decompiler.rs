@@ -0,0 +1,555 @@
+use crate::{
+    action::{self, Tree},
+    game::Game,
+    gml::{
+        self,
+        compiler::{mappings, Compiler},
+        runtime::{ArrayAccessor, BinaryOperator, InstanceIdentifier, Instruction, Node, ReturnType, UnaryOperator},
+        InstanceVariable, Value,
+    },
+};
+use gm8exe::asset::etc::CodeAction;
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const INDENT: &str = "    ";
+
+/// Turns compiled instructions back into readable GML, resolving field, constant, script and function names.
+/// The output reflects what the compiler produced, not the original source: for example, `x += 1`
+/// will come out as `x = x + 1`, and constant expressions will already be folded.
+pub struct Decompiler<'a> {
+    compiler: &'a Compiler,
+
+    /// Local fields found while writing the current code body, so they can be declared at the top.
+    locals: RefCell<BTreeSet<usize>>,
+}
+
+impl<'a> Decompiler<'a> {
+    pub fn new(compiler: &'a Compiler) -> Self {
+        Self { compiler, locals: RefCell::new(BTreeSet::new()) }
+    }
+
+    /// Decompiles a full code body, such as a script, starting with a `var` line if it uses any locals.
+    pub fn code(&self, instructions: &[Instruction]) -> String {
+        self.code_indented(instructions, 0)
+    }
+
+    /// Decompiles a single expression.
+    pub fn expression(&self, node: &Node) -> String {
+        let mut out = String::new();
+        self.write_node(&mut out, node);
+        out
+    }
+
+    /// Writes out a drag-n-drop action tree, one action per line, with each action's index, library and action
+    /// id. Code bodies and the contents of questions and repeat blocks are indented beneath their action.
+    pub fn actions(&self, tree: &Tree) -> String {
+        let mut out = String::new();
+        self.write_actions(&mut out, tree.actions(), 0);
+        out
+    }
+
+    fn code_indented(&self, instructions: &[Instruction], indent: usize) -> String {
+        self.locals.borrow_mut().clear();
+        let mut body = String::new();
+        self.write_instructions(&mut body, instructions, indent);
+        let locals = self.locals.borrow();
+        if locals.is_empty() {
+            body
+        } else {
+            let names = locals.iter().map(|id| self.field_name(*id)).collect::<Vec<_>>().join(", ");
+            format!("{}var {};\n{}", INDENT.repeat(indent), names, body)
+        }
+    }
+
+    fn write_actions(&self, out: &mut String, actions: &[action::Action], indent: usize) {
+        for action in actions {
+            out.push_str(&INDENT.repeat(indent));
+            out.push_str(&format!("[{}] lib {} action {}", action.index, action.lib_id, action.action_id));
+            match action.target {
+                Some(gml::SELF) => out.push_str(" applies to self"),
+                Some(gml::OTHER) => out.push_str(" applies to other"),
+                Some(target) => out.push_str(&format!(" applies to object {}", target)),
+                None => (),
+            }
+            if action.relative {
+                out.push_str(" (relative)");
+            }
+            if action.invert_condition {
+                out.push_str(" (not)");
+            }
+
+            match &action.body {
+                action::Body::Normal { args, body, if_else } => {
+                    match body {
+                        action::GmlBody::Function(function) => {
+                            out.push_str(": ");
+                            out.push_str(function.name());
+                            self.write_args(out, args);
+                            out.push('\n');
+                        },
+                        action::GmlBody::Code(code) => {
+                            if !args.is_empty() {
+                                out.push_str(": code");
+                                self.write_args(out, args);
+                            }
+                            out.push('\n');
+                            out.push_str(&self.code_indented(code, indent + 1));
+                        },
+                    }
+                    if let Some((if_body, else_body)) = if_else {
+                        out.push_str(&INDENT.repeat(indent));
+                        out.push_str("then\n");
+                        self.write_actions(out, if_body, indent + 1);
+                        if !else_body.is_empty() {
+                            out.push_str(&INDENT.repeat(indent));
+                            out.push_str("else\n");
+                            self.write_actions(out, else_body, indent + 1);
+                        }
+                    }
+                },
+                action::Body::Repeat { count, body } => {
+                    out.push_str(": repeat ");
+                    self.write_node(out, count);
+                    out.push('\n');
+                    self.write_actions(out, body, indent + 1);
+                },
+                action::Body::Exit => out.push_str(": exit\n"),
+            }
+        }
+    }
+
+    fn write_instructions(&self, out: &mut String, instructions: &[Instruction], indent: usize) {
+        let mut iter = instructions.iter().peekable();
+        while let Some(instruction) = iter.next() {
+            // "return x" compiles to a SetReturnValue followed by an Exit, so turn that back into one line
+            if let Instruction::SetReturnValue { value } = instruction {
+                if let Some(Instruction::Return { return_type: ReturnType::Exit }) = iter.peek() {
+                    iter.next();
+                    out.push_str(&INDENT.repeat(indent));
+                    out.push_str("return ");
+                    self.write_node(out, value);
+                    out.push_str(";\n");
+                    continue
+                }
+            }
+            self.write_instruction(out, instruction, indent);
+        }
+    }
+
+    fn write_block(&self, out: &mut String, instructions: &[Instruction], indent: usize) {
+        out.push_str("{\n");
+        self.write_instructions(out, instructions, indent + 1);
+        out.push_str(&INDENT.repeat(indent));
+        out.push('}');
+    }
+
+    fn write_instruction(&self, out: &mut String, instruction: &Instruction, indent: usize) {
+        out.push_str(&INDENT.repeat(indent));
+        match instruction {
+            Instruction::SetField { accessor, value } => {
+                self.write_owner(out, &accessor.owner, Some(accessor.index));
+                out.push_str(&self.field_name(accessor.index));
+                self.write_array_accessor(out, &accessor.array);
+                out.push_str(" = ");
+                self.write_node(out, value);
+                out.push(';');
+            },
+            Instruction::SetVariable { accessor, value } => {
+                self.write_owner(out, &accessor.owner, None);
+                out.push_str(variable_name(&accessor.var));
+                self.write_array_accessor(out, &accessor.array);
+                out.push_str(" = ");
+                self.write_node(out, value);
+                out.push(';');
+            },
+            Instruction::EvalExpression { node } => {
+                self.write_node(out, node);
+                out.push(';');
+            },
+            Instruction::IfElse { cond, if_body, else_body } => {
+                out.push_str("if ");
+                self.write_node(out, cond);
+                out.push(' ');
+                self.write_block(out, if_body, indent);
+                if !else_body.is_empty() {
+                    out.push_str(" else ");
+                    self.write_block(out, else_body, indent);
+                }
+            },
+            Instruction::LoopUntil { cond, body } => {
+                out.push_str("do ");
+                self.write_block(out, body, indent);
+                out.push_str(" until ");
+                self.write_node(out, cond);
+                out.push(';');
+            },
+            Instruction::LoopWhile { cond, body } => {
+                out.push_str("while ");
+                self.write_node(out, cond);
+                out.push(' ');
+                self.write_block(out, body, indent);
+            },
+            Instruction::LoopFor { cond, body, step } => {
+                // The initializer was compiled as a separate instruction before this one
+                let mut step_str = String::new();
+                self.write_instructions(&mut step_str, step, 0);
+                out.push_str("for (; ");
+                self.write_node(out, cond);
+                out.push_str("; ");
+                out.push_str(step_str.trim_end().trim_end_matches(';'));
+                out.push_str(") ");
+                self.write_block(out, body, indent);
+            },
+            Instruction::Return { return_type } => out.push_str(match return_type {
+                ReturnType::Normal => "// return",
+                ReturnType::Continue => "continue;",
+                ReturnType::Break => "break;",
+                ReturnType::Exit => "exit;",
+            }),
+            Instruction::Repeat { count, body } => {
+                out.push_str("repeat ");
+                self.write_node(out, count);
+                out.push(' ');
+                self.write_block(out, body, indent);
+            },
+            Instruction::SetReturnValue { value } => {
+                out.push_str("// set return value: ");
+                self.write_node(out, value);
+            },
+            Instruction::Switch { input, cases, default, body } => {
+                out.push_str("switch ");
+                self.write_node(out, input);
+                out.push_str(" {\n");
+                // Labels split the body into runs of instructions, which are written out together so that
+                // multi-instruction statements such as "return x" are still recognised inside a case
+                let mut labels = cases.iter().map(|(_, pos)| *pos).chain(*default).collect::<Vec<_>>();
+                labels.push(0);
+                labels.push(body.len());
+                labels.sort_unstable();
+                labels.dedup();
+                for (i, start) in labels.iter().copied().enumerate() {
+                    for (case, _) in cases.iter().filter(|(_, pos)| *pos == start) {
+                        out.push_str(&INDENT.repeat(indent + 1));
+                        out.push_str("case ");
+                        self.write_node(out, case);
+                        out.push_str(":\n");
+                    }
+                    if *default == Some(start) {
+                        out.push_str(&INDENT.repeat(indent + 1));
+                        out.push_str("default:\n");
+                    }
+                    if let Some(end) = labels.get(i + 1) {
+                        self.write_instructions(out, &body[start..*end], indent + 2);
+                    }
+                }
+                out.push_str(&INDENT.repeat(indent));
+                out.push('}');
+            },
+            Instruction::With { target, body } => {
+                out.push_str("with ");
+                self.write_node(out, target);
+                out.push(' ');
+                self.write_block(out, body, indent);
+            },
+            Instruction::GlobalVar { fields } => {
+                out.push_str("globalvar ");
+                out.push_str(&fields.iter().map(|id| self.field_name(*id)).collect::<Vec<_>>().join(", "));
+                out.push(';');
+            },
+            Instruction::RuntimeError { error } => out.push_str(&format!("// runtime error: {}", error)),
+        }
+        out.push('\n');
+    }
+
+    fn write_node(&self, out: &mut String, node: &Node) {
+        match node {
+            Node::Literal { value } => write_value(out, value),
+            Node::Constant { constant_id } => match self.compiler.get_user_constant_name(*constant_id) {
+                Some(name) => out.push_str(name),
+                None => out.push_str(&format!("/* constant {} */", constant_id)),
+            },
+            Node::Function { args, function } => {
+                out.push_str(function.name());
+                self.write_args(out, args);
+            },
+            Node::Script { args, script_id } => {
+                match self.compiler.get_script_name(*script_id) {
+                    Some(name) => out.push_str(name),
+                    None => out.push_str(&format!("/* script {} */", script_id)),
+                }
+                self.write_args(out, args);
+            },
+            Node::Field { accessor } => {
+                self.write_owner(out, &accessor.owner, Some(accessor.index));
+                out.push_str(&self.field_name(accessor.index));
+                self.write_array_accessor(out, &accessor.array);
+            },
+            Node::Variable { accessor } => {
+                self.write_owner(out, &accessor.owner, None);
+                out.push_str(variable_name(&accessor.var));
+                self.write_array_accessor(out, &accessor.array);
+            },
+            Node::Binary { left, right, operator } => {
+                out.push('(');
+                self.write_node(out, left);
+                out.push(' ');
+                out.push_str(binary_operator_str(*operator));
+                out.push(' ');
+                self.write_node(out, right);
+                out.push(')');
+            },
+            Node::Unary { child, operator } => {
+                out.push_str(unary_operator_str(*operator));
+                self.write_node(out, child);
+            },
+            Node::RuntimeError { error } => out.push_str(&format!("/* runtime error: {} */", error)),
+        }
+    }
+
+    fn write_args(&self, out: &mut String, args: &[Node]) {
+        out.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i != 0 {
+                out.push_str(", ");
+            }
+            self.write_node(out, arg);
+        }
+        out.push(')');
+    }
+
+    /// Writes the "owner." part of a variable, if it has one. Local fields are noted for the `var` line instead.
+    fn write_owner(&self, out: &mut String, owner: &InstanceIdentifier, field: Option<usize>) {
+        match owner {
+            InstanceIdentifier::Unknown => (),
+            InstanceIdentifier::Own => out.push_str("self."),
+            InstanceIdentifier::Other => out.push_str("other."),
+            InstanceIdentifier::Global => out.push_str("global."),
+            InstanceIdentifier::Local => match field {
+                Some(id) => {
+                    self.locals.borrow_mut().insert(id);
+                },
+                None => out.push_str("(-7)."),
+            },
+            InstanceIdentifier::Expression(node) => {
+                let mut expr = String::new();
+                self.write_node(&mut expr, node);
+                if expr.starts_with('(') || expr.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    out.push_str(&expr);
+                } else {
+                    out.push('(');
+                    out.push_str(&expr);
+                    out.push(')');
+                }
+                out.push('.');
+            },
+        }
+    }
+
+    fn write_array_accessor(&self, out: &mut String, accessor: &ArrayAccessor) {
+        match accessor {
+            ArrayAccessor::None => (),
+            ArrayAccessor::Single(index) => {
+                out.push('[');
+                self.write_node(out, index);
+                out.push(']');
+            },
+            ArrayAccessor::Double(index1, index2) => {
+                out.push('[');
+                self.write_node(out, index1);
+                out.push_str(", ");
+                self.write_node(out, index2);
+                out.push(']');
+            },
+        }
+    }
+
+    fn field_name(&self, id: usize) -> String {
+        self.compiler.get_field_name(id).unwrap_or_else(|| format!("/* field {} */", id))
+    }
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Real(r) => out.push_str(&r.to_string()),
+        // GML has no escape sequences, so use whichever quote mark the string doesn't contain
+        Value::Str(s) if s.as_ref().contains('"') => {
+            out.push('\'');
+            out.push_str(s.as_ref());
+            out.push('\'');
+        },
+        Value::Str(s) => {
+            out.push('"');
+            out.push_str(s.as_ref());
+            out.push('"');
+        },
+    }
+}
+
+fn variable_name(var: &InstanceVariable) -> &'static str {
+    mappings::INSTANCE_VARIABLES.iter().find(|(_, v)| v == var).map(|(name, _)| *name).unwrap_or("?")
+}
+
+fn binary_operator_str(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "+",
+        BinaryOperator::And => "&&",
+        BinaryOperator::BitwiseAnd => "&",
+        BinaryOperator::BitwiseOr => "|",
+        BinaryOperator::BinaryShiftLeft => "<<",
+        BinaryOperator::BinaryShiftRight => ">>",
+        BinaryOperator::BitwiseXor => "^",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Equal => "==",
+        BinaryOperator::GreaterThan => ">",
+        BinaryOperator::GreaterThanOrEqual => ">=",
+        BinaryOperator::IntDivide => "div",
+        BinaryOperator::LessThan => "<",
+        BinaryOperator::LessThanOrEqual => "<=",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Modulo => "mod",
+        BinaryOperator::NotEqual => "!=",
+        BinaryOperator::Or => "||",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Xor => "^^",
+    }
+}
+
+fn unary_operator_str(operator: UnaryOperator) -> &'static str {
+    match operator {
+        UnaryOperator::Neg => "-",
+        UnaryOperator::Not => "!",
+        UnaryOperator::Complement => "~",
+    }
+}
+
+/// Compiles every piece of GML in a game and writes it all out as annotated text files in the given directory,
+/// with one file per object, timeline, script, room and trigger. Code which fails to compile is noted in the file.
+pub fn dump_game(assets: &gm8exe::GameAssets, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut compiler = Game::create_compiler(assets);
+
+    // Turns an asset name into something that's always safe to use as a filename
+    fn file_name(name: &str, index: usize, extension: &str) -> String {
+        let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        format!("{}_{}.{}", index, name, extension)
+    }
+
+    // Compiles and decompiles a piece of code, or gives a comment explaining why it didn't compile
+    fn dump_code(compiler: &mut Compiler, source: &str) -> String {
+        match compiler.compile(source) {
+            Ok(code) => Decompiler::new(compiler).code(&code),
+            Err(e) => format!("// compiler error: {}\n", e.message),
+        }
+    }
+
+    // Same thing, but for a list of drag-n-drop actions
+    fn dump_actions(compiler: &mut Compiler, actions: &[CodeAction]) -> String {
+        match Tree::from_list(actions, compiler) {
+            Ok(tree) => Decompiler::new(compiler).actions(&tree),
+            Err(e) => format!("// compiler error: {}\n", e),
+        }
+    }
+
+    let subdir = |name: &str| -> std::io::Result<PathBuf> {
+        let path = dir.join(name);
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    };
+
+    let objects_dir = subdir("objects")?;
+    for (i, object) in assets.objects.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i, x))) {
+        let mut out = format!("// object {} ({})\n", object.name, i);
+        for (event_type, event_list) in object.events.iter().enumerate() {
+            for (event_number, actions) in event_list {
+                out += &format!("\n// event {},{}\n", event_type, event_number);
+                out += &dump_actions(&mut compiler, actions);
+            }
+        }
+        fs::write(objects_dir.join(file_name(&object.name, i, "txt")), out)?;
+    }
+
+    let timelines_dir = subdir("timelines")?;
+    for (i, timeline) in assets.timelines.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i, x))) {
+        let mut out = format!("// timeline {} ({})\n", timeline.name, i);
+        for (moment, actions) in timeline.moments.iter() {
+            out += &format!("\n// moment {}\n", moment);
+            out += &dump_actions(&mut compiler, actions);
+        }
+        fs::write(timelines_dir.join(file_name(&timeline.name, i, "txt")), out)?;
+    }
+
+    let scripts_dir = subdir("scripts")?;
+    for (i, script) in assets.scripts.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i, x))) {
+        let out = format!("// script {} ({})\n", script.name, i) + &dump_code(&mut compiler, &script.source);
+        fs::write(scripts_dir.join(file_name(&script.name, i, "gml")), out)?;
+    }
+
+    let rooms_dir = subdir("rooms")?;
+    for (i, room) in assets.rooms.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i, x))) {
+        let mut out = format!("// room {} ({})\n\n// creation code\n", room.name, i);
+        out += &dump_code(&mut compiler, &room.creation_code);
+        for instance in room.instances.iter().filter(|x| !x.creation_code.is_empty()) {
+            out += &format!("\n// creation code of instance {}\n", instance.id);
+            out += &dump_code(&mut compiler, &instance.creation_code);
+        }
+        fs::write(rooms_dir.join(file_name(&room.name, i, "gml")), out)?;
+    }
+
+    let triggers_dir = subdir("triggers")?;
+    for (i, trigger) in assets.triggers.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i, x))) {
+        let out = format!("// trigger {} ({})\n", trigger.name, i) + &dump_code(&mut compiler, &trigger.condition);
+        fs::write(triggers_dir.join(file_name(&trigger.name, i, "gml")), out)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompile_code() {
+        let mut compiler = Compiler::new();
+        let code = compiler.compile("var a; a = 1; if a == 2 { global.b[3] = \"x\" } else exit; return a + 3").unwrap();
+        assert_eq!(
+            Decompiler::new(&compiler).code(&code),
+            "var a;\na = 1;\nif (a == 2) {\n    global.b[3] = \"x\";\n} else {\n    exit;\n}\nreturn (a + 3);\n",
+        );
+    }
+
+    #[test]
+    fn decompile_switch() {
+        let mut compiler = Compiler::new();
+        let code = compiler.compile("var a; switch a { case 1: return 2; case 2: case 3: a = 4; break; default: exit }");
+        assert_eq!(
+            Decompiler::new(&compiler).code(&code.unwrap()),
+            "var a;\nswitch a {\n    case 1:\n        return 2;\n    case 2:\n    case 3:\n        a = 4;\n        \
+             break;\n    default:\n        exit;\n}\n",
+        );
+    }
+
+    #[test]
+    fn decompile_for() {
+        let mut compiler = Compiler::new();
+        let code = compiler.compile("var a, i; for (i = 0; i < 3; i = i + 1) { a = a + i }").unwrap();
+        assert_eq!(
+            Decompiler::new(&compiler).code(&code),
+            "var i, a;\ni = 0;\nfor (; (i < 3); i = (i + 1)) {\n    a = (a + i);\n}\n",
+        );
+    }
+
+    #[test]
+    fn decompile_with() {
+        let mut compiler = Compiler::new();
+        let code = compiler.compile("with obj_player { x = 1; other.y = 2 }").unwrap();
+        assert_eq!(
+            Decompiler::new(&compiler).code(&code),
+            "with obj_player {\n    x = 1;\n    other.y = 2;\n}\n",
+        );
+    }
+}
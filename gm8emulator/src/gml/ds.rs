@@ -55,6 +55,11 @@ impl<T> DataStructureManager<T> {
         }
     }
 
+    /// Iterates over every structure which exists, in order of ID.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &T)> {
+        self.table.iter().enumerate().filter_map(|(id, entry)| entry.as_ref().map(|x| (id as i32, x)))
+    }
+
     pub fn get(&self, id: i32) -> Result<&T> {
         match self.table.get(id as usize) {
             Some(entry) => entry.as_ref().ok_or(Error::NonexistentStructure(id)),
@@ -80,6 +85,12 @@ impl<T> DataStructureManager<T> {
     }
 }
 
+impl<T> Default for DataStructureManager<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Map {
     // Returns the index associated with the given key, or None if there is none.
     pub fn get_index(&self, key: &Value, precision: Real) -> Option<usize> {
@@ -329,7 +329,7 @@ impl Game {
     }
 
     pub fn screen_refresh(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        self.renderer.present();
+        self.present();
         Ok(Default::default())
     }
 
@@ -6530,13 +6530,8 @@ impl Game {
         if let Some(var) = mappings::get_instance_variable_by_name(identifier.as_ref()) {
             self.set_instance_var(context.this, var, index, value, context)?;
         } else {
-            let mut fields = self.instance_list.get(context.this).fields.borrow_mut();
             let field_id = self.compiler.get_field_id(identifier.as_ref());
-            if let Some(field) = fields.get_mut(&field_id) {
-                field.set(index, value);
-            } else {
-                fields.insert(field_id, Field::new(index, value));
-            }
+            self.set_instance_field(context.this, field_id, index, value, context);
         }
         Ok(Default::default())
     }
@@ -0,0 +1,319 @@
+use crate::{
+    action::{self, Tree},
+    game::Game,
+    gml::{
+        self,
+        compiler::Compiler,
+        runtime::{ArrayAccessor, FieldAccessor, InstanceIdentifier, Instruction, Node, VariableAccessor},
+        InstanceVariable, Value,
+    },
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display},
+};
+
+// KERNEL_INFO: (name, implemented, expected arg count) for every function in kernel.rs
+include!(concat!(env!("OUT_DIR"), "/_kernel_info.rs"));
+
+/// Kernel functions which take the name of a variable as their first argument, and write to it.
+const VARIABLE_SETTERS: [&str; 2] = ["variable_global_set", "variable_local_set"];
+
+/// A single problem found by the linter.
+pub struct Report {
+    /// Where the problem was found, for example "script scr_jump".
+    pub location: String,
+    pub kind: Kind,
+}
+
+/// The kinds of problem the linter can report.
+pub enum Kind {
+    /// Source code which failed to compile at all.
+    CompileError(String),
+    /// A call to a kernel function which is still a stub in the emulator.
+    UnimplementedFunction(&'static str),
+    /// A call to a function which doesn't exist.
+    UnknownFunction(String),
+    /// A call to a kernel function with the wrong number of arguments.
+    WrongArgumentCount { function: &'static str, expected: usize, got: usize },
+    /// An assignment to a read-only builtin variable.
+    ReadOnlyVariable(InstanceVariable),
+    /// A field which is read somewhere, but never assigned to anywhere in the game.
+    UnwrittenField(String),
+    /// Any other error which the compiler has deferred to runtime.
+    RuntimeError(gml::Error),
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.location)?;
+        match &self.kind {
+            Kind::CompileError(e) => write!(f, "compiler error: {}", e),
+            Kind::UnimplementedFunction(name) => write!(f, "call to unimplemented function {}", name),
+            Kind::UnknownFunction(name) => write!(f, "call to unknown function {}", name),
+            Kind::WrongArgumentCount { function, expected, got } => {
+                write!(f, "wrong argument count for {} (expected: {}, got: {})", function, expected, got)
+            },
+            Kind::ReadOnlyVariable(v) => write!(f, "assignment to {}", gml::Error::ReadOnlyVariable(*v)),
+            Kind::UnwrittenField(name) => write!(f, "variable {} is read but never written", name),
+            Kind::RuntimeError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Walks compiled GML, collecting reports about anything which would go wrong at runtime.
+pub struct Linter {
+    compiler: Compiler,
+    location: String,
+    reports: Vec<Report>,
+    fields_read: BTreeMap<usize, String>,
+    fields_written: HashSet<usize>,
+}
+
+impl Linter {
+    pub fn new(compiler: Compiler) -> Self {
+        Self {
+            compiler,
+            location: String::new(),
+            reports: Vec::new(),
+            fields_read: BTreeMap::new(),
+            fields_written: HashSet::new(),
+        }
+    }
+
+    /// Compiles and checks a piece of GML code.
+    pub fn check_code(&mut self, location: String, source: &str) {
+        self.location = location;
+        match self.compiler.compile(source) {
+            Ok(instructions) => self.check_instructions(&instructions),
+            Err(e) => self.report(Kind::CompileError(e.message)),
+        }
+    }
+
+    /// Compiles and checks a single GML expression.
+    pub fn check_expression(&mut self, location: String, source: &str) {
+        self.location = location;
+        match self.compiler.compile_expression(source) {
+            Ok(node) => self.check_node(&node),
+            Err(e) => self.report(Kind::CompileError(e.message)),
+        }
+    }
+
+    /// Compiles and checks a list of drag-n-drop actions.
+    pub fn check_actions(&mut self, location: String, actions: &[gm8exe::asset::etc::CodeAction]) {
+        self.location = location;
+        match Tree::from_list(actions, &mut self.compiler) {
+            Ok(tree) => self.check_action_slice(tree.actions()),
+            Err(e) => self.report(Kind::CompileError(e)),
+        }
+    }
+
+    /// Finishes checking, returning all the reports in the order they were found.
+    /// Fields which were read but never written are reported last, in order of field id.
+    pub fn finish(mut self) -> Vec<Report> {
+        for (field_id, location) in std::mem::take(&mut self.fields_read) {
+            if !self.fields_written.contains(&field_id) {
+                let name = self.compiler.get_field_name(field_id).unwrap_or_else(|| field_id.to_string());
+                self.reports.push(Report { location, kind: Kind::UnwrittenField(name) });
+            }
+        }
+        self.reports
+    }
+
+    fn report(&mut self, kind: Kind) {
+        self.reports.push(Report { location: self.location.clone(), kind });
+    }
+
+    fn check_action_slice(&mut self, actions: &[action::Action]) {
+        for action in actions {
+            match &action.body {
+                action::Body::Normal { args, body, if_else } => {
+                    args.iter().for_each(|node| self.check_node(node));
+                    match body {
+                        action::GmlBody::Function(function) => self.check_function_call(*function, args),
+                        action::GmlBody::Code(instructions) => self.check_instructions(instructions),
+                    }
+                    if let Some((if_body, else_body)) = if_else {
+                        self.check_action_slice(if_body);
+                        self.check_action_slice(else_body);
+                    }
+                },
+                action::Body::Repeat { count, body } => {
+                    self.check_node(count);
+                    self.check_action_slice(body);
+                },
+                action::Body::Exit => (),
+            }
+        }
+    }
+
+    fn check_instructions(&mut self, instructions: &[Instruction]) {
+        for instruction in instructions {
+            match instruction {
+                Instruction::SetField { accessor, value } => {
+                    self.check_field_accessor(accessor, false);
+                    self.check_node(value);
+                },
+                Instruction::SetVariable { accessor, value } => {
+                    if accessor.var.is_read_only() {
+                        self.report(Kind::ReadOnlyVariable(accessor.var));
+                    }
+                    self.check_variable_accessor(accessor);
+                    self.check_node(value);
+                },
+                Instruction::EvalExpression { node } => self.check_node(node),
+                Instruction::IfElse { cond, if_body, else_body } => {
+                    self.check_node(cond);
+                    self.check_instructions(if_body);
+                    self.check_instructions(else_body);
+                },
+                Instruction::LoopUntil { cond, body } | Instruction::LoopWhile { cond, body } => {
+                    self.check_node(cond);
+                    self.check_instructions(body);
+                },
+                Instruction::LoopFor { cond, body, step } => {
+                    self.check_node(cond);
+                    self.check_instructions(body);
+                    self.check_instructions(step);
+                },
+                Instruction::Return { .. } => (),
+                Instruction::Repeat { count, body } => {
+                    self.check_node(count);
+                    self.check_instructions(body);
+                },
+                Instruction::SetReturnValue { value } => self.check_node(value),
+                Instruction::Switch { input, cases, body, .. } => {
+                    self.check_node(input);
+                    cases.iter().for_each(|(node, _)| self.check_node(node));
+                    self.check_instructions(body);
+                },
+                Instruction::With { target, body } => {
+                    self.check_node(target);
+                    self.check_instructions(body);
+                },
+                Instruction::GlobalVar { .. } => (),
+                Instruction::RuntimeError { error } => self.check_runtime_error(error),
+            }
+        }
+    }
+
+    fn check_node(&mut self, node: &Node) {
+        match node {
+            Node::Literal { .. } | Node::Constant { .. } => (),
+            Node::Function { args, function } => {
+                args.iter().for_each(|node| self.check_node(node));
+                self.check_function_call(*function, args);
+            },
+            Node::Script { args, .. } => args.iter().for_each(|node| self.check_node(node)),
+            Node::Field { accessor } => self.check_field_accessor(accessor, true),
+            Node::Variable { accessor } => self.check_variable_accessor(accessor),
+            Node::Binary { left, right, .. } => {
+                self.check_node(left);
+                self.check_node(right);
+            },
+            Node::Unary { child, .. } => self.check_node(child),
+            Node::RuntimeError { error } => self.check_runtime_error(error),
+        }
+    }
+
+    fn check_field_accessor(&mut self, accessor: &FieldAccessor, read: bool) {
+        if read {
+            if !self.fields_read.contains_key(&accessor.index) {
+                self.fields_read.insert(accessor.index, self.location.clone());
+            }
+        } else {
+            self.fields_written.insert(accessor.index);
+        }
+        self.check_array_accessor(&accessor.array);
+        self.check_instance_identifier(&accessor.owner);
+    }
+
+    fn check_variable_accessor(&mut self, accessor: &VariableAccessor) {
+        self.check_array_accessor(&accessor.array);
+        self.check_instance_identifier(&accessor.owner);
+    }
+
+    fn check_array_accessor(&mut self, accessor: &ArrayAccessor) {
+        match accessor {
+            ArrayAccessor::None => (),
+            ArrayAccessor::Single(index) => self.check_node(index),
+            ArrayAccessor::Double(index1, index2) => {
+                self.check_node(index1);
+                self.check_node(index2);
+            },
+        }
+    }
+
+    fn check_instance_identifier(&mut self, identifier: &InstanceIdentifier) {
+        if let InstanceIdentifier::Expression(node) = identifier {
+            self.check_node(node);
+        }
+    }
+
+    fn check_function_call(&mut self, function: gml::Function, args: &[Node]) {
+        let name = function.name();
+        if let Some((_, implemented, arg_count)) = KERNEL_INFO.iter().find(|(n, _, _)| *n == name) {
+            if !implemented {
+                self.report(Kind::UnimplementedFunction(name));
+            }
+            if let Some(expected) = *arg_count {
+                if expected != args.len() {
+                    self.report(Kind::WrongArgumentCount { function: name, expected, got: args.len() });
+                }
+            }
+        }
+
+        // Setting a variable by name counts as writing to it, if we can tell which one it is
+        if VARIABLE_SETTERS.contains(&name) {
+            if let Some(Node::Literal { value: Value::Str(field_name) }) = args.first() {
+                let field_id = self.compiler.get_field_id(field_name.as_ref());
+                self.fields_written.insert(field_id);
+            }
+        }
+    }
+
+    fn check_runtime_error(&mut self, error: &gml::Error) {
+        match error {
+            gml::Error::UnknownFunction(name) => self.report(Kind::UnknownFunction(name.clone())),
+            error => self.report(Kind::RuntimeError(error.clone())),
+        }
+    }
+}
+
+/// Compiles every piece of GML in a game and checks it for problems, without running anything.
+pub fn lint_game(assets: &gm8exe::GameAssets) -> Vec<Report> {
+    let mut linter = Linter::new(Game::create_compiler(assets));
+
+    for constant in &assets.constants {
+        linter.check_expression(format!("constant {}", constant.name), &constant.expression);
+    }
+    for object in assets.objects.iter().flatten() {
+        for (i, event_list) in object.events.iter().enumerate() {
+            for (sub, actions) in event_list {
+                linter.check_actions(format!("object {} event {},{}", object.name, i, sub), actions);
+            }
+        }
+    }
+    for timeline in assets.timelines.iter().flatten() {
+        for (moment, actions) in timeline.moments.iter() {
+            linter.check_actions(format!("timeline {} moment {}", timeline.name, moment), actions);
+        }
+    }
+    for script in assets.scripts.iter().flatten() {
+        linter.check_code(format!("script {}", script.name), &script.source);
+    }
+    for room in assets.rooms.iter().flatten() {
+        linter.check_code(format!("room {} creation code", room.name), &room.creation_code);
+        for instance in room.instances.iter() {
+            linter.check_code(
+                format!("room {} creation code of instance {}", room.name, instance.id),
+                &instance.creation_code,
+            );
+        }
+    }
+    for trigger in assets.triggers.iter().flatten() {
+        linter.check_code(format!("trigger {}", trigger.name), &trigger.condition);
+    }
+
+    linter.finish()
+}
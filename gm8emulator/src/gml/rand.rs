@@ -85,3 +85,9 @@ impl Random {
         ((ls.wrapping_mul(lb)) >> 32) as _
     }
 }
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     asset,
-    game::{Game, GetAsset, SceneChange, Version},
+    game::{watchpoint::Watched, Game, GetAsset, SceneChange, Version},
     gml::{
         self,
         compiler::{mappings, mappings::constants as gml_constants, token::Operator},
@@ -317,21 +317,21 @@ impl Game {
                 match target {
                     Target::Single(None) => (),
                     Target::Single(Some(instance)) => {
-                        self.set_instance_field(instance, accessor.index, array_index, value);
+                        self.set_instance_field(instance, accessor.index, array_index, value, context);
                     },
                     Target::Objects(index) => {
                         if let Some(Some(object)) = self.assets.objects.get(index as usize) {
                             let ids = object.children.clone();
                             let mut iter = self.instance_list.iter_by_identity(ids);
                             while let Some(instance) = iter.next(&self.instance_list) {
-                                self.set_instance_field(instance, accessor.index, array_index, value.clone());
+                                self.set_instance_field(instance, accessor.index, array_index, value.clone(), context);
                             }
                         }
                     },
                     Target::All => {
                         let mut iter = self.instance_list.iter_by_insertion();
                         while let Some(instance) = iter.next(&self.instance_list) {
-                            self.set_instance_field(instance, accessor.index, array_index, value.clone());
+                            self.set_instance_field(instance, accessor.index, array_index, value.clone(), context);
                         }
                     },
                     Target::Global => {
@@ -814,12 +814,25 @@ impl Game {
     }
 
     // Set a field on an instance
-    fn set_instance_field(&self, instance: usize, field_id: usize, array_index: u32, value: Value) {
-        let mut fields = self.instance_list.get(instance).fields.borrow_mut();
-        if let Some(field) = fields.get_mut(&field_id) {
-            field.set(array_index, value)
-        } else {
-            fields.insert(field_id, Field::new(array_index, value));
+    pub fn set_instance_field(
+        &mut self,
+        instance: usize,
+        field_id: usize,
+        array_index: u32,
+        value: Value,
+        context: &Context,
+    ) {
+        let watch = self.watch_begin(instance, Watched::Field(field_id), array_index, context);
+        {
+            let mut fields = self.instance_list.get(instance).fields.borrow_mut();
+            if let Some(field) = fields.get_mut(&field_id) {
+                field.set(array_index, value)
+            } else {
+                fields.insert(field_id, Field::new(array_index, value));
+            }
+        }
+        if let Some(watch) = watch {
+            self.watch_end(watch, context);
         }
     }
 
@@ -1163,6 +1176,23 @@ impl Game {
         array_index: u32,
         value: Value,
         context: &mut Context,
+    ) -> gml::Result<()> {
+        let watch = self.watch_begin(instance_handle, Watched::Variable(*var), array_index, context);
+        self.write_instance_var(instance_handle, var, array_index, value, context)?;
+        if let Some(watch) = watch {
+            self.watch_end(watch, context);
+        }
+        Ok(())
+    }
+
+    // Set an instance variable on an instance, without checking watchpoints
+    fn write_instance_var(
+        &mut self,
+        instance_handle: usize,
+        var: &InstanceVariable,
+        array_index: u32,
+        value: Value,
+        context: &mut Context,
     ) -> gml::Result<()> {
         let instance = self.instance_list.get(instance_handle);
 
@@ -286,14 +286,14 @@ impl Value {
 
     pub fn shl(self, rhs: Self) -> gml::Result<Self> {
         match (self, rhs) {
-            (Self::Real(lhs), Self::Real(rhs)) => Ok(Self::Real((lhs.round() << rhs.round()).into())),
+            (Self::Real(lhs), Self::Real(rhs)) => Ok(Self::Real(lhs.round().wrapping_shl(rhs.round() as u32).into())),
             (x, y) => invalid_op!(BinaryShiftLeft, x, y),
         }
     }
 
     pub fn shr(self, rhs: Self) -> gml::Result<Self> {
         match (self, rhs) {
-            (Self::Real(lhs), Self::Real(rhs)) => Ok(Self::Real((lhs.round() >> rhs.round()).into())),
+            (Self::Real(lhs), Self::Real(rhs)) => Ok(Self::Real(lhs.round().wrapping_shr(rhs.round() as u32).into())),
             (x, y) => invalid_op!(BinaryShiftRight, x, y),
         }
     }
@@ -490,4 +490,14 @@ mod tests {
         let b = Value::Str("owo".to_string().into());
         let _ = a.add(b).unwrap();
     }
+
+    #[test]
+    fn op_shift() {
+        // Shift amounts wrap around like they do on x86
+        let real = |x: f64| Value::Real(Real::from(x));
+        assert!(real(1.0).shl(real(4.0)).unwrap().almost_equals(&real(16.0)));
+        assert!(real(1.0).shl(real(33.0)).unwrap().almost_equals(&real(2.0)));
+        assert!(real(-8.0).shr(real(34.0)).unwrap().almost_equals(&real(-2.0)));
+        assert!(real(5.0).shl(real(-1.0)).unwrap().almost_equals(&real(i32::MIN.into())));
+    }
 }
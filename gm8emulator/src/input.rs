@@ -387,4 +387,10 @@ impl InputManager {
     // }
 }
 
+impl Default for InputManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // TODO: VK_ANYKEY, VK_NOKEY, VK_LALT, VK_RALT...
@@ -411,6 +411,12 @@ impl InstanceList {
     }
 }
 
+impl Default for InstanceList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Deserialize)]
 pub struct TileList {
     chunks: ChunkList<Tile>,
@@ -474,6 +480,12 @@ impl TileList {
     }
 }
 
+impl Default for TileList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Serialize for ChunkList<T>
 where
     T: Serialize,
@@ -0,0 +1,15 @@
+// The emulator lives in a library so that things other than main.rs, like the fuzz targets, can use it.
+
+#![feature(asm, seek_convenience, with_options)]
+#![allow(dead_code)] // Shut up.
+
+pub mod action;
+pub mod asset;
+pub mod game;
+pub mod gml;
+pub mod input;
+pub mod instance;
+pub mod instancelist;
+pub mod math;
+pub mod tile;
+pub mod util;
@@ -1,20 +1,6 @@
-#![feature(asm, seek_convenience, with_options)]
-#![allow(dead_code)] // Shut up.
-
-mod action;
-mod asset;
-mod game;
-mod gml;
-mod input;
-mod instance;
-mod instancelist;
-mod math;
-mod tile;
-mod util;
-
+use gm8emulator::{game, gml, util};
 use std::{
     env, fs,
-    io::{BufReader, Write},
     path::{Path, PathBuf},
     process,
 };
@@ -46,9 +32,21 @@ fn xmain() -> i32 {
     opts.optflag("t", "singlethread", "parse gamedata synchronously");
     opts.optflag("v", "verbose", "enables verbose logging");
     opts.optflag("r", "realtime", "disables clock spoofing");
+    opts.optflag("c", "no-cache", "disables caching of compiled game code");
+    opts.optflag("l", "lint", "checks the game's code for problems without running it");
+    opts.optflag("i", "console", "reads lines of GML from stdin and runs them between frames");
+    opts.optflag("u", "turbo", "runs the game as fast as possible, without a frame limiter (toggle with F12)");
+    opts.optflag("k", "skip-frames", "doesn't show frames in the window while running in turbo or fast-forwarding");
+    opts.optflag("e", "verify", "plays the replay (-f) headlessly in turbo, then prints where it ended up");
+    opts.optflag("o", "input-display", "shows the frame, room, lag and held buttons while playing a replay (-f)");
+    opts.optflag("x", "headless", "runs without a window, for when the game is controlled by a tool like gm8search");
     opts.optopt("p", "port", "port to open for external game control (default 15560)", "PORT");
     opts.optopt("n", "project-name", "name of TAS project to create or load", "NAME");
-    opts.optopt("f", "replay-file", "path to savestate file to replay", "FILE");
+    opts.optopt("f", "replay-file", "path to savestate, .gmtas, .gmtxt or libTAS .ltm file to replay", "FILE");
+    opts.optopt("w", "convert-replay", "writes the replay (-f) to a .gmtas, .gmtxt or .ltm file, then exits", "FILE");
+    opts.optopt("b", "rewind", "keeps a state every N frames while recording, up to COUNT (default 10,30)", "N,COUNT");
+    opts.optopt("g", "fast-forward", "runs in turbo until the given frame, then plays normally", "FRAME");
+    opts.optopt("d", "decompile", "writes the game's compiled code as text files to a directory", "DIR");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(matches) => matches,
@@ -74,6 +72,35 @@ fn xmain() -> i32 {
     let multithread = !matches.opt_present("t");
     let spoof_time = !matches.opt_present("r");
     let verbose = matches.opt_present("v");
+    let use_compile_cache = !matches.opt_present("c");
+    let lint = matches.opt_present("l");
+    let console = matches.opt_present("i");
+    let turbo = matches.opt_present("u");
+    let skip_frames = matches.opt_present("k");
+    let verify = matches.opt_present("e");
+    let headless = matches.opt_present("x");
+    let input_display = matches.opt_present("o");
+    let fast_forward_to = match matches.opt_str("g").map(|x| x.parse::<usize>()).transpose() {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("invalid frame provided: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+    let rewind = match matches.opt_str("b") {
+        Some(arg) => {
+            let mut parts = arg.splitn(2, ',').map(|x| x.trim().parse::<usize>());
+            match (parts.next(), parts.next()) {
+                (Some(Ok(interval)), Some(Ok(capacity))) => game::Rewind::new(interval, capacity),
+                _ => {
+                    eprintln!("invalid rewind settings provided, expected N,COUNT: {}", arg);
+                    return EXIT_FAILURE
+                },
+            }
+        },
+        None => game::Rewind::new(10, 30),
+    };
+    let decompile_dir = matches.opt_str("d");
     let port = match matches.opt_str("p").map(|x| x.parse::<u16>()).transpose() {
         Ok(p) => p,
         Err(e) => {
@@ -88,27 +115,20 @@ fn xmain() -> i32 {
         p.push(name);
         p
     });
-    let replay = matches.opt_str("f").map(|filename| {
-        let mut filepath = PathBuf::from(&filename);
-        match filepath.extension().and_then(|x| x.to_str()) {
-            Some("bin") => {
-                let f = fs::File::open(&filepath).unwrap();
-                let replay = bincode::deserialize_from::<_, game::SaveState>(BufReader::new(f)).unwrap().into_replay();
-                filepath.set_extension("gmtas");
-                fs::File::create(&filepath).unwrap().write_all(&bincode::serialize(&replay).unwrap()).unwrap();
-                replay
-            },
-
-            Some("gmtas") => {
-                bincode::deserialize_from::<_, game::Replay>(BufReader::new(fs::File::open(&filepath).unwrap()))
-                    .unwrap()
-            },
-
-            _ => {
-                panic!("Unknown filetype for -f, expected '.bin' or '.gmtas'");
-            },
-        }
-    });
+    let replay_path = matches.opt_str("f").map(PathBuf::from);
+    if verify && replay_path.is_none() {
+        eprintln!("--verify needs a replay file (-f)");
+        return EXIT_FAILURE
+    }
+    if input_display && replay_path.is_none() {
+        eprintln!("--input-display needs a replay file (-f)");
+        return EXIT_FAILURE
+    }
+    let convert_path = matches.opt_str("w").map(PathBuf::from);
+    if convert_path.is_some() && replay_path.is_none() {
+        eprintln!("--convert-replay needs a replay file (-f)");
+        return EXIT_FAILURE
+    }
     let input = {
         if matches.free.len() == 1 {
             &matches.free[0]
@@ -135,6 +155,36 @@ fn xmain() -> i32 {
         println!("loading '{}'...", input);
     }
 
+    // The exe is hashed before it's read, since reading it might modify the data in-place
+    let exe_hash = util::fnv1a(&file);
+
+    let replay = match replay_path.map(|path| load_replay(path, exe_hash)).transpose() {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("failed to load replay: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+    if let (Some(path), Some(replay)) = (convert_path, &replay) {
+        return match replay.save_to_file(&path, exe_hash) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("failed to write replay: {}", e);
+                EXIT_FAILURE
+            },
+        }
+    }
+
+    // Compiled code is cached under the hash of the exe
+    let compile_cache_path = if use_compile_cache {
+        let mut p = env::current_dir().expect("std::env::current_dir() failed");
+        p.push("cache");
+        p.push(format!("{:016x}.bin", exe_hash));
+        Some(p)
+    } else {
+        None
+    };
+
     #[rustfmt::skip]
     let assets = gm8exe::reader::from_exe(
         &mut file,                              // mut exe: AsRef<[u8]>
@@ -154,6 +204,25 @@ fn xmain() -> i32 {
         },
     };
 
+    if lint {
+        let reports = gml::lint::lint_game(&assets);
+        for report in &reports {
+            println!("{}", report);
+        }
+        println!("{} problem(s) found", reports.len());
+        return if reports.is_empty() { EXIT_SUCCESS } else { EXIT_FAILURE }
+    }
+
+    if let Some(dir) = decompile_dir {
+        return match gml::decompiler::dump_game(&assets, Path::new(&dir)) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(e) => {
+                eprintln!("failed to decompile game: {}", e);
+                EXIT_FAILURE
+            },
+        }
+    }
+
     let absolute_path = match file_path.canonicalize() {
         Ok(p) => p,
         Err(e) => {
@@ -171,18 +240,50 @@ fn xmain() -> i32 {
         None
     };
 
-    let mut components = match game::Game::launch(assets, absolute_path, time_nanos) {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Failed to launch game: {}", e);
-            return EXIT_FAILURE
-        },
-    };
+    let mut components =
+        match game::Game::launch(assets, absolute_path, exe_hash, time_nanos, compile_cache_path, verify || headless) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("Failed to launch game: {}", e);
+                return EXIT_FAILURE
+            },
+        };
+    components.speed.turbo = turbo;
+    components.speed.skip_present = skip_frames;
+    components.speed.fast_forward_to = fast_forward_to;
+    components.speed.hotkey = turbo || skip_frames || fast_forward_to.is_some();
+    if input_display {
+        components.input_display = Some(game::InputDisplay::new());
+    }
+
+    if let (true, Some(replay)) = (verify, &replay) {
+        if !replay.tampered_frames().is_empty() {
+            println!("warning: replay was tampered with before frame(s) {:?}", replay.tampered_frames());
+        }
+        return match components.verify(replay) {
+            Ok(result) => {
+                println!("frames: {}", result.frames);
+                println!("seed: {}", result.seed);
+                println!("room_id: {}", result.room_id);
+                println!("hash: {:016x}", result.state_hash);
+                if let Some(desync) = result.desync {
+                    println!("{}", desync);
+                    EXIT_FAILURE
+                } else {
+                    EXIT_SUCCESS
+                }
+            },
+            Err(err) => {
+                println!("Runtime error {}", err);
+                EXIT_FAILURE
+            },
+        }
+    }
 
     if let Err(err) = if let Some(path) = project_path {
-        components.record(path, port)
+        components.record(path, port, rewind)
     } else {
-        if let Some(replay) = replay { components.replay(replay) } else { components.run() }
+        if let Some(replay) = replay { components.replay(replay, console) } else { components.run(console) }
     } {
         println!("Runtime error: {}", err);
         EXIT_FAILURE
@@ -190,3 +291,18 @@ fn xmain() -> i32 {
         EXIT_SUCCESS
     }
 }
+
+// Loads a replay from a .gmtas, .gmtxt or .ltm file, or from a savestate,
+// in which case it's also saved as a .gmtas next to it
+fn load_replay(mut path: PathBuf, exe_hash: u64) -> Result<game::Replay, Box<dyn std::error::Error>> {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("bin") => {
+            let replay = game::SaveState::load_from_file(&path, exe_hash)?.into_replay();
+            path.set_extension("gmtas");
+            replay.save_to_file(&path, exe_hash)?;
+            Ok(replay)
+        },
+        Some("gmtas") | Some("gmtxt") | Some("ltm") => game::Replay::load_from_file(&path, exe_hash),
+        _ => Err("unknown filetype, expected '.bin', '.gmtas', '.gmtxt' or '.ltm'".into()),
+    }
+}
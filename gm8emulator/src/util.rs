@@ -31,9 +31,52 @@ pub fn rotate_around(x: &mut f64, y: &mut f64, center_x: f64, center_y: f64, sin
     *y = y_new;
 }
 
+/// A 64-bit FNV-1a hasher. Unlike std's DefaultHasher, its output is the same on every platform and Rust version,
+/// so hashes made with it are safe to store on disk.
+#[derive(Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xCBF29CE484222325)
+    }
+}
+
+impl std::hash::Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100000001B3);
+        }
+    }
+}
+
+/// Lets things be serialized straight into the hasher.
+impl std::io::Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        std::hash::Hasher::write(self, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes a block of data with 64-bit FNV-1a.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = Fnv1a::default();
+    hasher.write(data);
+    hasher.finish()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{bgra2rgba, ieee_round, rgba2bgra};
+    use super::{bgra2rgba, fnv1a, ieee_round, rgba2bgra};
 
     #[test]
     fn bgra_rgba() {
@@ -61,4 +104,11 @@ mod tests {
             assert_eq!(ieee_round(i as f64 + 0.5) % 2, 0);
         }
     }
+
+    #[test]
+    fn fnv() {
+        assert_eq!(fnv1a(b""), 0xCBF29CE484222325);
+        assert_eq!(fnv1a(b"a"), 0xAF63DC4C8601EC8C);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171F73967E8);
+    }
 }
@@ -0,0 +1,57 @@
+// Arrays, including 2D arrays and how unset elements behave.
+
+#test set and get
+a[3] = 5
+return a[3]
+#expect 5
+
+#test elements below a set one are zero
+a[3] = 5
+return a[1]
+#expect 0
+
+#test elements above the highest set one don't exist
+a[3] = 5
+return a[4]
+#error
+
+#test a variable is index 0 of an array
+a = 7
+return a[0]
+#expect 7
+
+#test index 0 of an array is the variable
+a[0] = 4
+return a
+#expect 4
+
+#test arrays can mix types
+a[0] = "x"
+a[1] = 2
+return a[0] + string(a[1])
+#expect "x2"
+
+#test 2d set and get
+a[2, 3] = 9
+return a[2, 3]
+#expect 9
+
+#test 2d elements before a set one in the same row are zero
+a[1, 5] = 1
+return a[1, 2]
+#expect 0
+
+#test negative index
+a[-1] = 1
+#error
+
+#test global arrays
+global.g[2] = 1
+return global.g[0]
+#expect 0
+
+#test arrays on other instances
+inst = instance_create(0, 0, obj_other)
+inst.values[1] = 3
+return inst.values[1]
+#expect 3
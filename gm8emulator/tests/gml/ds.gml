@@ -0,0 +1,144 @@
+// Data structures: ds_list, ds_stack, ds_queue, ds_map, ds_priority and ds_grid.
+
+#test ids start at 0 and are reused
+a = ds_list_create()
+b = ds_list_create()
+ds_list_destroy(a)
+c = ds_list_create()
+return string(a) + string(b) + string(c)
+#expect "010"
+
+#test each type has its own ids
+l = ds_list_create()
+m = ds_map_create()
+return m
+#expect 0
+
+#test list add and find
+l = ds_list_create()
+ds_list_add(l, 5)
+ds_list_add(l, "x")
+return string(ds_list_size(l)) + ds_list_find_value(l, 1)
+#expect "2x"
+
+#test list find_index
+l = ds_list_create()
+ds_list_add(l, 5)
+ds_list_add(l, 6)
+return ds_list_find_index(l, 6)
+#expect 1
+
+#test list find_index not found
+l = ds_list_create()
+ds_list_add(l, 5)
+return ds_list_find_index(l, 7)
+#expect -1
+
+#test list find_index allows for rounding errors
+l = ds_list_create()
+ds_list_add(l, 0.3)
+return ds_list_find_index(l, 0.1 + 0.2)
+#expect 0
+
+#test ds_set_precision
+ds_set_precision(0.5)
+l = ds_list_create()
+ds_list_add(l, 1)
+return ds_list_find_index(l, 1.3)
+#expect 0
+
+#test list sort puts reals before strings
+l = ds_list_create()
+ds_list_add(l, "b")
+ds_list_add(l, 3)
+ds_list_add(l, "a")
+ds_list_add(l, 1)
+ds_list_sort(l, true)
+return string(ds_list_find_value(l, 0)) + string(ds_list_find_value(l, 1)) + ds_list_find_value(l, 2) + ds_list_find_value(l, 3)
+#expect "13ab"
+
+#test list sort descending
+l = ds_list_create()
+ds_list_add(l, 1)
+ds_list_add(l, 3)
+ds_list_add(l, 2)
+ds_list_sort(l, false)
+return ds_list_find_value(l, 0)
+#expect 3
+
+#test using a destroyed list
+l = ds_list_create()
+ds_list_destroy(l)
+return ds_list_size(l)
+#error
+
+#test stack is last in first out
+s = ds_stack_create()
+ds_stack_push(s, 1)
+ds_stack_push(s, 2)
+ds_stack_push(s, 3)
+first = ds_stack_pop(s)
+return first * 10 + ds_stack_top(s)
+#expect 32
+
+#test queue is first in first out
+q = ds_queue_create()
+ds_queue_enqueue(q, 1)
+ds_queue_enqueue(q, 2)
+return ds_queue_dequeue(q)
+#expect 1
+
+#test map find_value
+m = ds_map_create()
+ds_map_add(m, "a", 1)
+ds_map_add(m, 10, "x")
+return string(ds_map_find_value(m, "a")) + ds_map_find_value(m, 10)
+#expect "1x"
+
+#test map missing key gives 0
+m = ds_map_create()
+ds_map_add(m, "a", 1)
+return ds_map_find_value(m, "b")
+#expect 0
+
+#test map exists
+m = ds_map_create()
+ds_map_add(m, "a", 1)
+return ds_map_exists(m, "a") * 10 + ds_map_exists(m, "b")
+#expect 10
+
+#test map keys are sorted
+m = ds_map_create()
+ds_map_add(m, "b", 2)
+ds_map_add(m, "c", 3)
+ds_map_add(m, "a", 1)
+first = ds_map_find_first(m)
+return first + ds_map_find_next(m, first)
+#expect "ab"
+
+#test priority find_max
+p = ds_priority_create()
+ds_priority_add(p, "a", 5)
+ds_priority_add(p, "b", 1)
+ds_priority_add(p, "c", 3)
+return ds_priority_find_max(p)
+#expect "a"
+
+#test priority delete_min
+p = ds_priority_create()
+ds_priority_add(p, "a", 5)
+ds_priority_add(p, "b", 1)
+ds_priority_add(p, "c", 3)
+return ds_priority_delete_min(p)
+#expect "b"
+
+#test grid set and get
+g = ds_grid_create(3, 3)
+ds_grid_set(g, 1, 2, "x")
+return ds_grid_get(g, 1, 2)
+#expect "x"
+
+#test grid cells start at 0
+g = ds_grid_create(3, 3)
+return ds_grid_get(g, 2, 2)
+#expect 0
@@ -0,0 +1,122 @@
+// Arithmetic, bitwise, logical and comparison operators.
+// The format of these files is described in src/game/conformance.rs.
+
+#test addition
+return 2 + 3
+#expect 5
+
+#test precedence
+return 2 + 3 * 4 - 10 / 5
+#expect 12
+
+#test subtraction is left-associative
+return 10 - 2 - 3
+#expect 5
+
+#test real division
+return 7 / 2
+#expect 3.5
+
+#test div
+return 7 div 2
+#expect 3
+
+#test mod
+return 7 mod 3
+#expect 1
+
+#test mod on fractions
+return 5.5 mod 2
+#expect 1.5
+
+#test mod keeps the sign of the dividend
+return -7 mod 3
+#expect -1
+
+#test bitwise and
+return 12 & 10
+#expect 8
+
+#test bitwise or
+return 12 | 10
+#expect 14
+
+#test bitwise xor
+return 12 ^ 10
+#expect 6
+
+#test shift left
+return 1 << 4
+#expect 16
+
+#test shift right
+return 256 >> 4
+#expect 16
+
+#test complement
+return ~5
+#expect -6
+
+#test 0.5 is true
+return 0.5 && 1
+#expect 1
+
+#test anything below 0.5 is false
+return 0.49 || 0
+#expect 0
+
+#test logical xor
+return 1 ^^ 1
+#expect 0
+
+#test not
+return !0.4
+#expect 1
+
+#test true plus true
+return true + true
+#expect 2
+
+#test equality allows for rounding errors
+return 0.1 + 0.2 == 0.3
+#expect 1
+
+#test equality isn't too lenient
+return 1 == 1.0000001
+#expect 0
+
+#test single equals compares in an expression
+a = 3
+return a = 3
+#expect 1
+
+#test not equal
+return 1 != 2
+#expect 1
+
+#test less than or equal
+return 2 <= 2
+#expect 1
+
+#test string comparison
+return "abc" < "abd"
+#expect 1
+
+#test string equality is case sensitive
+return "a" == "A"
+#expect 0
+
+#test compound assignment
+x = 3
+x *= 4
+x -= 2
+return x
+#expect 10
+
+#test adding a string and a real
+return "5" + 5
+#error
+
+#test subtracting strings
+return "a" - "b"
+#error
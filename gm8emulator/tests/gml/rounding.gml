@@ -0,0 +1,74 @@
+// Rounding of reals, both by the rounding functions and when a function needs an integer argument.
+// GM8 rounds halfway cases to the nearest even number.
+
+#test round down to even
+return round(2.5)
+#expect 2
+
+#test round up to even
+return round(3.5)
+#expect 4
+
+#test round negative to even
+return round(-2.5)
+#expect -2
+
+#test round a half
+return round(0.5)
+#expect 0
+
+#test round above a half
+return round(2.6)
+#expect 3
+
+#test floor negative
+return floor(-1.5)
+#expect -2
+
+#test ceil negative
+return ceil(-1.5)
+#expect -1
+
+#test frac
+return frac(3.75)
+#expect 0.75
+
+#test frac negative
+return frac(-3.75)
+#expect -0.75
+
+#test integer arguments round down to even
+return chr(66.5)
+#expect "B"
+
+#test integer arguments round up to even
+return chr(65.5)
+#expect "B"
+
+#test string_copy rounds its index
+return string_copy("hello", 2.5, 2)
+#expect "el"
+
+#test bitwise operators round their operands
+return (2.5 | 0) * 10 + (3.5 | 0)
+#expect 24
+
+#test shift by a fraction
+return 1 << 2.5
+#expect 4
+
+#test multiplication error is within tolerance
+return 0.1 * 3 == 0.3
+#expect 1
+
+#test sqrt
+return sqrt(16)
+#expect 4
+
+#test power
+return power(2, 10)
+#expect 1024
+
+#test abs
+return abs(-3)
+#expect 3
@@ -0,0 +1,95 @@
+// Converting between strings and reals, and basic string functions.
+
+#test concatenation
+return "a" + "b"
+#expect "ab"
+
+#test appending with +=
+s = "a"
+s += "b"
+return s
+#expect "ab"
+
+#test string of a whole number
+return string(5)
+#expect "5"
+
+#test string of a negative whole number
+return string(-2)
+#expect "-2"
+
+#test string of a large whole number
+return string(1000000)
+#expect "1000000"
+
+#test string of a fraction has two decimal places
+return string(0.5)
+#expect "0.50"
+
+#test string of a fraction is rounded
+return string(2 / 3)
+#expect "0.67"
+
+#test string of a repeating fraction
+return string(1 / 3)
+#expect "0.33"
+
+#test string of a string
+return string("x")
+#expect "x"
+
+#test string of true
+return string(true)
+#expect "1"
+
+#test building a message
+return "Score: " + string(10)
+#expect "Score: 10"
+
+#test real of a string
+return real("3.25")
+#expect 3.25
+
+#test real of a real
+return real(7)
+#expect 7
+
+#test is_string
+return is_string("a") + is_string(1)
+#expect 1
+
+#test is_real
+return is_real("a") + is_real(1)
+#expect 1
+
+#test string_length
+return string_length("hello")
+#expect 5
+
+#test string_copy
+return string_copy("hello", 2, 3)
+#expect "ell"
+
+#test string_pos
+return string_pos("l", "hello")
+#expect 3
+
+#test string_pos not found
+return string_pos("z", "hello")
+#expect 0
+
+#test string_upper
+return string_upper("MiXed")
+#expect "MIXED"
+
+#test string_repeat
+return string_repeat("ab", 3)
+#expect "ababab"
+
+#test chr
+return chr(65)
+#expect "A"
+
+#test ord
+return ord("a")
+#expect 97
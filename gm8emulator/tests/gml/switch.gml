@@ -0,0 +1,92 @@
+// Switch statements, which fall through into the next case without a break.
+
+#test fallthrough
+r = ""
+switch (2) {
+    case 1: r += "a"
+    case 2: r += "b"
+    case 3: r += "c"
+        break
+    default: r += "d"
+}
+return r
+#expect "bc"
+
+#test default
+r = ""
+switch (9) {
+    case 1: r += "a"
+        break
+    default: r += "d"
+}
+return r
+#expect "d"
+
+#test no match and no default
+r = "none"
+switch (9) {
+    case 1: r = "a"
+}
+return r
+#expect "none"
+
+#test default falls through too
+r = ""
+switch (5) {
+    case 1: r += "a"
+    default: r += "d"
+    case 2: r += "b"
+}
+return r
+#expect "db"
+
+#test multiple labels
+r = ""
+switch (1) {
+    case 1:
+    case 2: r = "low"
+        break
+    case 3: r = "high"
+}
+return r
+#expect "low"
+
+#test strings
+r = 0
+switch ("b") {
+    case "a": r = 1
+        break
+    case "b": r = 2
+        break
+}
+return r
+#expect 2
+
+#test a string doesn't match a real
+r = "none"
+switch ("1") {
+    case 1: r = "real"
+        break
+    default: r = "default"
+}
+return r
+#expect "default"
+
+#test case labels can be expressions
+r = 0
+switch (2) {
+    case 1 + 1: r = 1
+}
+return r
+#expect 1
+
+#test break in a switch doesn't leave the loop around it
+r = 0
+for (i = 0; i < 3; i += 1) {
+    switch (i) {
+        case 1: break
+        default: r += 1
+    }
+}
+return r
+#expect 2
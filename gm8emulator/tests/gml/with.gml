@@ -0,0 +1,95 @@
+// The with statement, and what self and other refer to inside it.
+
+#test with an object includes its children
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_child)
+instance_create(0, 0, obj_other)
+global.count = 0
+with (obj_parent) global.count += 1
+return global.count
+#expect 2
+
+#test with a child object doesn't include the parent
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_child)
+global.count = 0
+with (obj_child) global.count += 1
+return global.count
+#expect 1
+
+#test with all
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_child)
+instance_create(0, 0, obj_other)
+global.count = 0
+with (all) global.count += 1
+return global.count
+#expect 3
+
+#test with noone does nothing
+instance_create(0, 0, obj_parent)
+global.count = 0
+with (noone) global.count += 1
+return global.count
+#expect 0
+
+#test with an object that has no instances
+instance_create(0, 0, obj_parent)
+global.count = 0
+with (obj_other) global.count += 1
+return global.count
+#expect 0
+
+#test with an instance id
+inst = instance_create(0, 0, obj_other)
+with (inst) x = 5
+return inst.x
+#expect 5
+
+#test self is the instance inside with
+inst = instance_create(0, 0, obj_other)
+with (inst) global.found = id
+return global.found == inst
+#expect 1
+
+#test other is the caller inside with
+value = 7
+inst = instance_create(0, 0, obj_other)
+with (inst) y = other.value
+return inst.y
+#expect 7
+
+#test other in a nested with
+a = instance_create(0, 0, obj_parent)
+b = instance_create(0, 0, obj_other)
+a.tag = "a"
+b.tag = "b"
+with (obj_parent) with (obj_other) global.result = tag + other.tag
+return global.result
+#expect "ba"
+
+#test break leaves with
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_parent)
+global.count = 0
+with (obj_parent) {
+    global.count += 1
+    break
+}
+return global.count
+#expect 1
+
+#test destroying instances inside with
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_child)
+with (obj_parent) instance_destroy()
+return instance_number(obj_parent)
+#expect 0
+
+#test instance_number counts children
+instance_create(0, 0, obj_parent)
+instance_create(0, 0, obj_child)
+instance_create(0, 0, obj_child)
+return instance_number(obj_parent) * 10 + instance_number(obj_child)
+#expect 32
@@ -1,5 +1,6 @@
 //! Game rendering functionality
 
+mod headless;
 mod opengl;
 
 use crate::{atlas::AtlasBuilder, window::Window};
@@ -54,6 +55,7 @@ pub trait RendererTrait {
     fn draw_sprite(&mut self, tex: &AtlasRef, x: f64, y: f64, xs: f64, ys: f64, ang: f64, col: i32, alpha: f64);
     fn set_view_matrix(&mut self, view: [f32; 16]);
     fn set_viewproj_matrix(&mut self, view: [f32; 16], proj: [f32; 16]);
+    fn get_model_matrix(&self) -> [f32; 16];
     fn set_model_matrix(&mut self, model: [f32; 16]);
     fn mult_model_matrix(&mut self, model: [f32; 16]);
     fn set_projection_ortho(&mut self, x: f64, y: f64, w: f64, h: f64, angle: f64);
@@ -96,6 +98,7 @@ pub trait RendererTrait {
 
     fn get_pixels(&self, x: i32, y: i32, w: i32, h: i32) -> Box<[u8]>;
     fn draw_raw_frame(&mut self, rgb: Box<[u8]>, w: i32, h: i32, clear_colour: Colour);
+    fn set_pixels(&mut self, rgb: Box<[u8]>, w: i32, h: i32);
 
     fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>>;
     fn upload_dynamic_textures(&mut self, textures: &[Option<SavedTexture>]);
@@ -217,6 +220,11 @@ impl Renderer {
         })))
     }
 
+    /// Creates a Renderer which doesn't draw anything, for use with a headless Window.
+    pub fn new_headless(options: &RendererOptions) -> Self {
+        Self(Box::new(headless::RendererImpl::new(options)))
+    }
+
     pub fn max_texture_size(&self) -> u32 {
         self.0.max_texture_size()
     }
@@ -270,6 +278,10 @@ impl Renderer {
         self.0.set_viewproj_matrix(view, proj)
     }
 
+    pub fn get_model_matrix(&self) -> [f32; 16] {
+        self.0.get_model_matrix()
+    }
+
     pub fn set_model_matrix(&mut self, model: [f32; 16]) {
         self.0.set_model_matrix(model)
     }
@@ -379,6 +391,11 @@ impl Renderer {
         self.0.draw_raw_frame(rgb, w, h, clear_colour)
     }
 
+    /// Overwrites the bottom-left of the frame being drawn with the given RGB pixels, without presenting anything.
+    pub fn set_pixels(&mut self, rgb: Box<[u8]>, w: i32, h: i32) {
+        self.0.set_pixels(rgb, w, h)
+    }
+
     pub fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
         self.0.dump_dynamic_textures()
     }
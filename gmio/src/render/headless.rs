@@ -0,0 +1,199 @@
+//! A renderer which doesn't draw anything, for running games without displaying them.
+//! Textures are tracked so that things like surfaces and sprite creation keep working, but their contents aren't.
+
+use crate::{
+    atlas::{AtlasBuilder, AtlasRef},
+    render::{BlendType, RendererOptions, RendererTrait, SavedTexture},
+};
+use shared::types::Colour;
+use std::any::Any;
+
+pub struct RendererImpl {
+    // Size of each texture, or None if it's been deleted
+    textures: Vec<Option<(i32, i32)>>,
+    stock_atlas_count: u32,
+    blend_mode: (BlendType, BlendType),
+    interpolate_pixels: bool,
+}
+
+impl RendererImpl {
+    pub fn new(options: &RendererOptions) -> Self {
+        Self {
+            textures: Vec::new(),
+            stock_atlas_count: 0,
+            blend_mode: (BlendType::SrcAlpha, BlendType::InvSrcAlpha),
+            interpolate_pixels: options.interpolate_pixels,
+        }
+    }
+}
+
+impl RendererTrait for RendererImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        8192
+    }
+
+    fn push_atlases(&mut self, atl: AtlasBuilder) -> Result<(), String> {
+        assert!(self.textures.is_empty(), "atlases should be initialized only once");
+        let (packers, _) = atl.into_inner();
+        self.textures = packers.iter().map(|p| Some(p.size())).collect();
+        self.stock_atlas_count = packers.len() as u32;
+        Ok(())
+    }
+
+    fn upload_sprite(
+        &mut self,
+        _data: Box<[u8]>,
+        width: i32,
+        height: i32,
+        origin_x: i32,
+        origin_y: i32,
+    ) -> Result<AtlasRef, String> {
+        Ok(AtlasRef {
+            origin_x: origin_x as f32 / width as f32,
+            origin_y: origin_y as f32 / height as f32,
+            ..self.create_surface(width, height)?
+        })
+    }
+
+    fn duplicate_sprite(&mut self, atlas_ref: &AtlasRef) -> Result<AtlasRef, String> {
+        self.create_surface(atlas_ref.w, atlas_ref.h)
+    }
+
+    fn delete_sprite(&mut self, atlas_ref: AtlasRef) {
+        if atlas_ref.atlas_id >= self.stock_atlas_count {
+            if let Some(texture) = self.textures.get_mut(atlas_ref.atlas_id as usize) {
+                *texture = None;
+            }
+        }
+    }
+
+    fn set_swap_interval(&self, _n: Option<u32>) -> bool {
+        false
+    }
+
+    fn draw_sprite(
+        &mut self,
+        _tex: &AtlasRef,
+        _x: f64,
+        _y: f64,
+        _xs: f64,
+        _ys: f64,
+        _ang: f64,
+        _col: i32,
+        _alpha: f64,
+    ) {
+    }
+
+    fn set_view_matrix(&mut self, _view: [f32; 16]) {}
+
+    fn set_viewproj_matrix(&mut self, _view: [f32; 16], _proj: [f32; 16]) {}
+
+    fn get_model_matrix(&self) -> [f32; 16] {
+        [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+    }
+
+    fn set_model_matrix(&mut self, _model: [f32; 16]) {}
+
+    fn mult_model_matrix(&mut self, _model: [f32; 16]) {}
+
+    fn set_projection_ortho(&mut self, _x: f64, _y: f64, _w: f64, _h: f64, _angle: f64) {}
+
+    fn set_view(
+        &mut self,
+        _width: u32,
+        _height: u32,
+        _unscaled_width: u32,
+        _unscaled_height: u32,
+        _src_x: i32,
+        _src_y: i32,
+        _src_w: i32,
+        _src_h: i32,
+        _src_angle: f64,
+        _port_x: i32,
+        _port_y: i32,
+        _port_w: i32,
+        _port_h: i32,
+    ) {
+    }
+
+    fn flush_queue(&mut self) {}
+
+    fn present(&mut self) {}
+
+    fn finish(&mut self, _width: u32, _height: u32, _clear_colour: Colour) {}
+
+    fn dump_sprite(&self, atlas_ref: &AtlasRef) -> Box<[u8]> {
+        vec![0; (atlas_ref.w * atlas_ref.h * 4) as usize].into_boxed_slice()
+    }
+
+    fn get_blend_mode(&self) -> (BlendType, BlendType) {
+        self.blend_mode
+    }
+
+    fn set_blend_mode(&mut self, src: BlendType, dst: BlendType) {
+        self.blend_mode = (src, dst);
+    }
+
+    fn get_pixel_interpolation(&self) -> bool {
+        self.interpolate_pixels
+    }
+
+    fn set_pixel_interpolation(&mut self, lerping: bool) {
+        self.interpolate_pixels = lerping;
+    }
+
+    fn get_pixels(&self, _x: i32, _y: i32, w: i32, h: i32) -> Box<[u8]> {
+        vec![0; (w * h * 3) as usize].into_boxed_slice()
+    }
+
+    fn draw_raw_frame(&mut self, _rgb: Box<[u8]>, _w: i32, _h: i32, _clear_colour: Colour) {}
+
+    fn set_pixels(&mut self, _rgb: Box<[u8]>, _w: i32, _h: i32) {}
+
+    fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
+        self.textures
+            .iter()
+            .skip(self.stock_atlas_count as usize)
+            .map(|t| {
+                t.map(|(width, height)| SavedTexture {
+                    width,
+                    height,
+                    pixels: vec![0; width as usize * height as usize * 4].into_boxed_slice(),
+                })
+            })
+            .collect()
+    }
+
+    fn upload_dynamic_textures(&mut self, textures: &[Option<SavedTexture>]) {
+        self.textures.truncate(self.stock_atlas_count as usize);
+        self.textures.extend(textures.iter().map(|t| t.as_ref().map(|t| (t.width, t.height))));
+    }
+
+    fn create_surface(&mut self, w: i32, h: i32) -> Result<AtlasRef, String> {
+        let atlas_id = match self.textures.iter().position(|x| x.is_none()) {
+            Some(id) => {
+                self.textures[id] = Some((w, h));
+                id as u32
+            },
+            None => {
+                self.textures.push(Some((w, h)));
+                self.textures.len() as u32 - 1
+            },
+        };
+        Ok(AtlasRef { atlas_id, x: 0, y: 0, w, h, origin_x: 0.0, origin_y: 0.0 })
+    }
+
+    fn set_target(&mut self, _atlas_ref: &AtlasRef) {}
+
+    fn reset_target(&mut self, _w: i32, _h: i32, _unscaled_w: i32, _unscaled_h: i32) {}
+
+    fn draw_rectangle(&mut self, _x1: f64, _y1: f64, _x2: f64, _y2: f64, _colour: i32, _alpha: f64) {}
+
+    fn draw_rectangle_outline(&mut self, _x1: f64, _y1: f64, _x2: f64, _y2: f64, _colour: i32, _alpha: f64) {}
+
+    fn clear_view(&mut self, _colour: Colour, _alpha: f64) {}
+}
@@ -626,6 +626,15 @@ impl RendererTrait for RendererImpl {
     }
 
     fn draw_raw_frame(&mut self, rgb: Box<[u8]>, w: i32, h: i32, clear_colour: Colour) {
+        self.set_pixels(rgb, w, h);
+        unsafe {
+            self.imp.swap_buffers();
+        }
+        self.draw_queue.clear();
+        self.setup_frame(w as _, h as _, clear_colour);
+    }
+
+    fn set_pixels(&mut self, rgb: Box<[u8]>, w: i32, h: i32) {
         unsafe {
             // store previous texture, upload new texture to gpu
             let mut prev_tex2d = 0;
@@ -659,11 +668,7 @@ impl RendererTrait for RendererImpl {
             gl::BindTexture(gl::TEXTURE_2D, prev_tex2d as GLuint);
             gl::DeleteFramebuffers(1, &fbo);
             gl::DeleteTextures(1, &tex);
-
-            self.imp.swap_buffers();
         }
-        self.draw_queue.clear();
-        self.setup_frame(w as _, h as _, clear_colour);
     }
 
     fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
@@ -1010,6 +1015,10 @@ impl RendererTrait for RendererImpl {
         self.update_matrix();
     }
 
+    fn get_model_matrix(&self) -> [f32; 16] {
+        self.model_matrix
+    }
+
     fn set_model_matrix(&mut self, model: [f32; 16]) {
         self.flush_queue();
         self.model_matrix = model;
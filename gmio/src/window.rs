@@ -1,5 +1,6 @@
 //! Windowing magic.
 
+pub mod headless;
 pub mod win32;
 pub mod xorg;
 
@@ -104,6 +105,11 @@ impl Window {
         Ok(Self(Box::new(platform::WindowImpl::new(builder)?)))
     }
 
+    /// Creates a new Window which is never shown and never produces any events.
+    pub fn new_headless(builder: &WindowBuilder) -> Self {
+        Self(Box::new(headless::WindowImpl::new(builder)))
+    }
+
     /// Returns whether the window requested to be closed.
     pub fn close_requested(&self) -> bool {
         self.0.close_requested()
@@ -218,4 +224,8 @@ impl WindowBuilder {
     pub fn build(&self) -> Result<Window, String> {
         Window::new(self)
     }
+
+    pub fn build_headless(&self) -> Window {
+        Window::new_headless(self)
+    }
 }
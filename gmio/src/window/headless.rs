@@ -0,0 +1,88 @@
+//! A window which doesn't exist, for running games without displaying anything.
+
+use super::{Cursor, Event, Style, WindowBuilder, WindowTrait};
+use std::{any::Any, slice};
+
+pub struct WindowImpl {
+    close_requested: bool,
+    cursor: Cursor,
+    inner_size: (u32, u32),
+    title: String,
+    visible: bool,
+    events: Vec<Event>,
+}
+
+impl WindowImpl {
+    pub fn new(builder: &WindowBuilder) -> Self {
+        Self {
+            close_requested: false,
+            cursor: builder.cursor,
+            inner_size: builder.size,
+            title: builder.title.clone(),
+            visible: false,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl WindowTrait for WindowImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    fn set_close_requested(&mut self, value: bool) {
+        self.close_requested = value
+    }
+
+    fn get_inner_size(&self) -> (u32, u32) {
+        self.inner_size
+    }
+
+    fn process_events<'a>(&'a mut self) -> slice::Iter<'a, Event> {
+        self.events.iter()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.inner_size = (width, height);
+    }
+
+    fn get_pos(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn get_cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+    }
+
+    fn set_style(&mut self, _style: Style) {}
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.into();
+    }
+
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn show_context_menu(&mut self, _options: &[(String, usize)]) {}
+
+    fn window_handle(&self) -> usize {
+        0
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 // WINAPI constants also used by GM8
 pub const VK_ADD: u8 = 0x6B;
@@ -247,6 +248,11 @@ impl Key {
             _ => return None,
         })
     }
+
+    /// Finds a key by its name in this enum, as printed by Debug.
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..=u8::MAX).filter_map(Self::from_winapi).find(|key| format!("{:?}", key) == name)
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -269,3 +275,34 @@ impl MouseButton {
         })
     }
 }
+
+/// Either a key or a mouse button, for things which treat them both the same way.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Button {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl Button {
+    /// Finds a button by name: either a key's name, as for Key::from_name, or LMB, MMB or RMB for the mouse.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "LMB" => Some(Self::Mouse(MouseButton::Left)),
+            "MMB" => Some(Self::Mouse(MouseButton::Middle)),
+            "RMB" => Some(Self::Mouse(MouseButton::Right)),
+            name => Key::from_name(name).map(Self::Key),
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    /// Writes the name which Button::from_name finds this button by.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{:?}", key),
+            Self::Mouse(MouseButton::Left) => write!(f, "LMB"),
+            Self::Mouse(MouseButton::Middle) => write!(f, "MMB"),
+            Self::Mouse(MouseButton::Right) => write!(f, "RMB"),
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{input, types::ID};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io};
+use std::{collections::HashMap, fmt, io, ops::RangeInclusive, str::FromStr};
+
+/// The most seeds one SearchSeeds can try, so a typo in a seed can't make the game run a search for days.
+pub const MAX_SEARCH_SEEDS: usize = 1_000_000;
 
 /// A message sent from the controller to the client.
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +22,22 @@ pub enum Message {
         new_seed: Option<i32>,
     },
 
+    /// Tells the game to advance several frames and then send us an update. The first frame gets the given inputs,
+    /// as with Advance, and on every frame each pattern presses or releases its button as needed.
+    /// Inputs for a button with a pattern are ignored, since the pattern decides whether it's held.
+    /// Advancing stops early if a watchpoint which pauses is hit, or if the predicate is met.
+    AdvanceMany {
+        frames: usize,
+        key_inputs: Vec<(input::Key, bool)>,
+        mouse_inputs: Vec<(input::MouseButton, bool)>,
+        mouse_location: (f64, f64),
+        patterns: Vec<(input::Button, InputPattern)>,
+        keys_requested: Vec<input::Key>,
+        mouse_buttons_requested: Vec<input::MouseButton>,
+        instance_requested: Option<ID>,
+        new_seed: Option<i32>,
+    },
+
     /// Tells the game whether to send MousePosition info (it doesn't by default)
     SetUpdateMouse { update: bool },
 
@@ -32,6 +51,87 @@ pub enum Message {
         mouse_buttons_requested: Vec<input::MouseButton>,
         instance_requested: Option<ID>,
     },
+
+    /// Tells the game to go back to its latest in-memory rewind state from before the current frame,
+    /// and then send us an update
+    Rewind {
+        keys_requested: Vec<input::Key>,
+        mouse_buttons_requested: Vec<input::MouseButton>,
+        instance_requested: Option<ID>,
+    },
+
+    /// Tells the game to run some GML between frames and send back the result.
+    /// The code runs as the given instance, or as a dummy instance with access to globals if None.
+    Execute { code: String, instance: Option<ID> },
+
+    /// Replaces the game's list of watchpoints. An empty list turns them all off.
+    SetWatchpoints { watchpoints: Vec<Watchpoint> },
+
+    /// Replaces the list of variables whose values are sent with every update
+    SetWatchList { variables: Vec<WatchedVariable> },
+
+    /// Asks which of the given buttons are held on up to `count` frames of the replay, starting at `from`
+    GetFrames { from: usize, count: usize, buttons: Vec<input::Button> },
+
+    /// Changes whether a button is held on a frame which has already been recorded. The game goes back to
+    /// the latest in-memory state from before that frame, plays the edited replay forward from there,
+    /// and then sends us an update
+    SetHeld {
+        frame: usize,
+        button: input::Button,
+        held: bool,
+        keys_requested: Vec<input::Key>,
+        mouse_buttons_requested: Vec<input::MouseButton>,
+        instance_requested: Option<ID>,
+    },
+
+    /// Sets a GML expression to check after every frame, as a dummy instance with access to globals.
+    /// Whenever it's true after a frame, the game sends PredicateMet, and AdvanceMany stops early.
+    /// It isn't part of the replay, so it shouldn't change anything. If checking it does change the game, it's turned
+    /// off and the replay is marked as tampered with. None turns it off.
+    SetPredicate { expression: Option<String> },
+
+    /// Tries each input sequence in turn, starting from the given savestate every time, and sends back a
+    /// SearchResult saying which made the predicate true. Afterwards the game goes back to how it was before,
+    /// and nothing is added to the replay.
+    Search { filename: String, predicate: String, sequences: Vec<InputSequence> },
+
+    /// Like Search, but sets each of the seeds after loading the savestate and then runs `frames` frames
+    /// without changing any inputs. The outcomes in the SearchResult are in the same order as the seeds.
+    /// There can be at most MAX_SEARCH_SEEDS seeds. Unlike Search, this runs a few seeds at a time between other
+    /// messages, sending SearchProgress every so often, until it's finished or cancelled.
+    SearchSeeds { filename: String, predicate: String, seeds: RangeInclusive<i32>, frames: usize },
+
+    /// Stops the seed search which is running, if any. It still sends a SearchResult, with the outcomes of the
+    /// seeds it got through.
+    CancelSearch,
+
+    /// Asks what the next `count` calls of a random function would give, starting from the given seed.
+    /// This doesn't change the game's seed.
+    PreviewRandom { seed: i32, count: usize, call: RandomCall },
+
+    /// Turns on or off drawing each instance's bbox and collision mask, paths and views over the game's frames.
+    /// It's only drawn in the window, so the game and the replay never see it.
+    SetOverlay { enabled: bool },
+}
+
+impl Message {
+    /// Parses a line typed into a console into an Execute message.
+    /// The line can start with "@ID" or "@global" to choose where the code runs, otherwise the default is used.
+    pub fn execute_from_console(line: &str, default_instance: Option<ID>) -> Result<Self, String> {
+        let line = line.trim();
+        match line.strip_prefix('@') {
+            Some(rest) => {
+                let (target, code) = rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len()));
+                let instance = match target {
+                    "global" => None,
+                    id => Some(id.parse::<ID>().map_err(|_| format!("invalid instance id: {}", id))?),
+                };
+                Ok(Self::Execute { code: code.trim_start().into(), instance })
+            },
+            None => Ok(Self::Execute { code: line.into(), instance: default_instance }),
+        }
+    }
 }
 
 /// A message sent from the client to the controller.
@@ -45,6 +145,9 @@ pub enum Information {
         frame_count: usize,
         seed: i32,
         instance: Option<InstanceDetails>,
+
+        /// The values of the variables on the watch list, in the same order
+        watch_values: Vec<String>,
     },
 
     /// Tells the controller that a key was pressed while the game window was focused
@@ -64,6 +167,316 @@ pub enum Information {
 
     /// Sends the controller some general info which should be shown to the user
     General { message: String },
+
+    /// Gives the result of an Execute message: either the value the code produced, or an error.
+    /// Tampered means the code changed the game's state, which has been noted in the replay.
+    ExecuteResult { result: Result<String, String>, tampered: bool },
+
+    /// Tells the controller that a watched variable changed during the last frame
+    WatchpointHit { hit: WatchpointHit },
+
+    /// Tells the controller that a SetWatchpoints message couldn't be applied, and why
+    WatchpointError { error: String },
+
+    /// Gives the result of a GetFrames message: for each frame starting at `from`, whether each of the
+    /// requested buttons is held, in the order they were requested
+    Frames { from: usize, held: Vec<Vec<bool>> },
+
+    /// Tells the controller that the predicate was true after the frame with the given frame count
+    PredicateMet { frame_count: usize },
+
+    /// Gives the result of a Search message: how each sequence turned out, in the order they were sent,
+    /// or why the search couldn't be run at all
+    SearchResult { result: Result<Vec<SearchOutcome>, String> },
+
+    /// Says how many of the seeds in a running seed search have been tried so far
+    SearchProgress { done: usize, total: usize },
+
+    /// Gives the result of a PreviewRandom message, along with the seed it started from
+    RandomPreview { seed: i32, values: Vec<f64> },
+}
+
+/// A call to one of GML's random functions, for previewing what it would give.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RandomCall {
+    Random(f64),
+    Irandom(i32),
+}
+
+/// Inputs to try in a search, as the buttons held on each frame. Buttons which appear anywhere in the sequence
+/// are released on the frames they aren't listed for, and any others stay as they were in the savestate.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputSequence {
+    pub frames: Vec<Vec<input::Button>>,
+}
+
+/// How one input sequence in a search turned out.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SearchOutcome {
+    /// The predicate was first true after this many frames of the sequence.
+    Satisfied { frames: usize },
+
+    /// The predicate wasn't true after any frame of the sequence.
+    Unsatisfied,
+
+    /// The game hit an error while running the sequence or checking the predicate.
+    Failed { error: String },
+}
+
+/// A way for a button to be pressed and released by itself over many frames.
+/// Frames are counted from the start of the replay, like the frame count in an update.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputPattern {
+    /// Held on every frame before `until`, then released.
+    Hold { until: usize },
+
+    /// Held for `period` frames and then released for `period` frames, over and over, starting from `start`.
+    Autofire { start: usize, period: usize },
+}
+
+impl InputPattern {
+    /// Whether the button should be held after the inputs of the given frame.
+    pub fn held_on(&self, frame: usize) -> bool {
+        match *self {
+            Self::Hold { until } => frame < until,
+            Self::Autofire { start, period } => (frame.saturating_sub(start) / period.max(1)) % 2 == 0,
+        }
+    }
+}
+
+/// What a watchpoint watches: either a single instance, or every instance of an object and its children.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WatchTarget {
+    Instance(ID),
+    Object(ID),
+}
+
+/// A request to be told whenever a variable changes on some instances.
+/// The variable can be either a builtin instance variable or a user-defined field, and is given by name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Watchpoint {
+    pub target: WatchTarget,
+    pub variable: String,
+
+    /// Whether frame advancing should stop when this watchpoint is hit
+    pub pause: bool,
+}
+
+/// A variable to show the value of in the control panel's watch list.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WatchedVariable {
+    /// The instance to read it from, or None for a global variable.
+    pub instance: Option<ID>,
+    pub variable: String,
+    pub array_index: u32,
+}
+
+/// The details of a watched variable being changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatchpointHit {
+    pub frame: usize,
+    pub instance: ID,
+    pub variable: String,
+    pub array_index: u32,
+    pub old_value: String,
+    pub new_value: String,
+
+    /// The object and event which made the change, for example "object obj_player event 3,0 action 1"
+    pub source: String,
+    pub pause: bool,
+}
+
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    /// Parses a watchpoint in the form "TARGET VARIABLE [pause]", where TARGET is either an instance id,
+    /// or "obj:" followed by an object index.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let target = match words.next() {
+            Some(target) => match target.strip_prefix("obj:") {
+                Some(object) => WatchTarget::Object(object.parse().map_err(|_| format!("invalid object: {}", object))?),
+                None => WatchTarget::Instance(target.parse().map_err(|_| format!("invalid instance id: {}", target))?),
+            },
+            None => return Err("missing watchpoint target".into()),
+        };
+        let variable = words.next().ok_or("missing variable name")?.to_string();
+        let pause = match words.next() {
+            Some("pause") => true,
+            Some(word) => return Err(format!("unexpected {}", word)),
+            None => false,
+        };
+        match words.next() {
+            Some(word) => Err(format!("unexpected {}", word)),
+            None => Ok(Self { target, variable, pause }),
+        }
+    }
+}
+
+impl FromStr for WatchedVariable {
+    type Err = String;
+
+    /// Parses a watched variable in the form "TARGET VARIABLE", where TARGET is either an instance id or "global".
+    /// The variable can be followed by an array index in square brackets, which can be two numbers for a 2D array.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let instance = match words.next() {
+            Some("global") => None,
+            Some(id) => Some(id.parse().map_err(|_| format!("invalid instance id: {}", id))?),
+            None => return Err("missing instance id or \"global\"".into()),
+        };
+        let variable = words.next().ok_or("missing variable name")?;
+        if let Some(word) = words.next() {
+            return Err(format!("unexpected {}", word))
+        }
+        let (variable, array_index) = match variable.strip_suffix(']').and_then(|v| Some(v.split_at(v.find('[')?))) {
+            Some((variable, index)) => {
+                let index = &index[1..];
+                let number = |n: &str| n.parse::<u32>().map_err(|_| format!("invalid array index: {}", index));
+                let array_index = match index.find(',') {
+                    Some(comma) => number(&index[..comma])? * 32000 + number(&index[comma + 1..])?,
+                    None => number(index)?,
+                };
+                (variable, array_index)
+            },
+            None => (variable, 0),
+        };
+        Ok(Self { instance, variable: variable.into(), array_index })
+    }
+}
+
+impl fmt::Display for WatchedVariable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.instance {
+            Some(id) => write!(f, "{}.{}", id, self.variable)?,
+            None => write!(f, "global.{}", self.variable)?,
+        }
+        match self.array_index {
+            0 => Ok(()),
+            index if index >= 32000 => write!(f, "[{},{}]", index / 32000, index % 32000),
+            index => write!(f, "[{}]", index),
+        }
+    }
+}
+
+/// How many seeds there are in a range of seeds, which might be more than fit in an i32.
+pub fn seed_count(seeds: &RangeInclusive<i32>) -> usize {
+    (i64::from(*seeds.end()) - i64::from(*seeds.start()) + 1).max(0) as usize
+}
+
+impl InputSequence {
+    /// The most frames a sequence can have, so a typo in a repeat count can't use up all the memory.
+    /// That's over half an hour at 50 FPS, which is far more than a search could get through anyway.
+    pub const MAX_FRAMES: usize = 100_000;
+
+    /// Every button which is held on at least one frame, in the order they first appear.
+    pub fn buttons(&self) -> Vec<input::Button> {
+        let mut buttons = Vec::new();
+        for button in self.frames.iter().flatten() {
+            if !buttons.contains(button) {
+                buttons.push(*button);
+            }
+        }
+        buttons
+    }
+}
+
+impl FromStr for InputSequence {
+    type Err = String;
+
+    /// Parses a sequence of frames separated by whitespace. Each frame is the names of the buttons held on it,
+    /// as for Button::from_name, joined with "+", or "." if nothing is held. A frame can be followed by "*N"
+    /// to repeat it N times, so "Right*3 Right+Z .*2" is six frames.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut frames = Vec::new();
+        for word in s.split_whitespace() {
+            let (frame, count) = match word.find('*') {
+                Some(star) => {
+                    let count = &word[star + 1..];
+                    (&word[..star], count.parse::<usize>().map_err(|_| format!("invalid repeat count: {}", count))?)
+                },
+                None => (word, 1),
+            };
+            let held = match frame {
+                "." => Vec::new(),
+                frame => frame
+                    .split('+')
+                    .map(|name| input::Button::from_name(name).ok_or_else(|| format!("unknown button: {}", name)))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            if count > Self::MAX_FRAMES - frames.len() {
+                return Err(format!("sequence is longer than {} frames", Self::MAX_FRAMES))
+            }
+            frames.extend(std::iter::repeat_n(held, count));
+        }
+        Ok(Self { frames })
+    }
+}
+
+impl fmt::Display for InputSequence {
+    /// Writes the sequence in the form it's parsed from, with repeated frames joined up.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut frames = self.frames.iter().peekable();
+        let mut first = true;
+        while let Some(held) = frames.next() {
+            let mut count = 1;
+            while frames.peek() == Some(&held) {
+                frames.next();
+                count += 1;
+            }
+            if !first {
+                write!(f, " ")?;
+            }
+            first = false;
+            if held.is_empty() {
+                write!(f, ".")?;
+            }
+            for (i, button) in held.iter().enumerate() {
+                write!(f, "{}{}", if i == 0 { "" } else { "+" }, button)?;
+            }
+            if count > 1 {
+                write!(f, "*{}", count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RandomCall {
+    type Err = String;
+
+    /// Parses a call in the form it's written in GML, such as "random(1)" or "irandom(10)".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (function, argument) = match s.strip_suffix(')').and_then(|s| Some(s.split_at(s.find('(')?))) {
+            Some((function, argument)) => (function.trim(), argument[1..].trim()),
+            None => return Err(format!("expected a call like random(1) or irandom(10), not {}", s)),
+        };
+        match function {
+            "random" => argument.parse().map(Self::Random).map_err(|_| format!("invalid bound: {}", argument)),
+            "irandom" => argument.parse().map(Self::Irandom).map_err(|_| format!("invalid bound: {}", argument)),
+            function => Err(format!("unknown random function: {}", function)),
+        }
+    }
+}
+
+impl fmt::Display for RandomCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Random(bound) => write!(f, "random({})", bound),
+            Self::Irandom(bound) => write!(f, "irandom({})", bound),
+        }
+    }
+}
+
+impl fmt::Display for WatchpointHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "frame {}: instance {} {}", self.frame, self.instance, self.variable)?;
+        if self.array_index != 0 {
+            write!(f, "[{}]", self.array_index)?;
+        }
+        write!(f, " changed from {} to {} in {}", self.old_value, self.new_value, self.source)
+    }
 }
 
 /// The details of an instance sent to the control panel for display.
@@ -141,3 +554,90 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn console_line() {
+        match Message::execute_from_console("@100003 x = 4", None) {
+            Ok(Message::Execute { code, instance }) => assert_eq!((code.as_str(), instance), ("x = 4", Some(100003))),
+            _ => panic!(),
+        }
+        match Message::execute_from_console("@global  score", Some(100003)) {
+            Ok(Message::Execute { code, instance }) => assert_eq!((code.as_str(), instance), ("score", None)),
+            _ => panic!(),
+        }
+        match Message::execute_from_console("  show_debug_message(x)", Some(100003)) {
+            Ok(Message::Execute { code, instance }) => {
+                assert_eq!((code.as_str(), instance), ("show_debug_message(x)", Some(100003)))
+            },
+            _ => panic!(),
+        }
+        assert!(Message::execute_from_console("@abc x", None).is_err());
+    }
+
+    #[test]
+    fn watch_list_syntax() {
+        let variable = "100003 hp".parse::<WatchedVariable>().unwrap();
+        assert_eq!(variable, WatchedVariable { instance: Some(100003), variable: "hp".into(), array_index: 0 });
+        let variable = "global grid[2,5]".parse::<WatchedVariable>().unwrap();
+        assert_eq!((variable.instance, variable.array_index), (None, 64005));
+        assert_eq!(variable.to_string(), "global.grid[2,5]");
+        assert_eq!("100003 alarm[3]".parse::<WatchedVariable>().unwrap().to_string(), "100003.alarm[3]");
+        assert!("100003".parse::<WatchedVariable>().is_err());
+        assert!("me hp".parse::<WatchedVariable>().is_err());
+        assert!("global a[x]".parse::<WatchedVariable>().is_err());
+    }
+
+    #[test]
+    fn input_patterns() {
+        let hold = InputPattern::Hold { until: 12 };
+        assert_eq!((10..14).map(|frame| hold.held_on(frame)).collect::<Vec<_>>(), [true, true, false, false]);
+        let autofire = InputPattern::Autofire { start: 10, period: 2 };
+        let held = (10..17).map(|frame| autofire.held_on(frame)).collect::<Vec<_>>();
+        assert_eq!(held, [true, true, false, false, true, true, false]);
+    }
+
+    #[test]
+    fn input_sequence_syntax() {
+        use input::{Button, Key, MouseButton};
+        let sequence = "Right*2 Right+Z . LMB".parse::<InputSequence>().unwrap();
+        let (right, z, lmb) = (Button::Key(Key::Right), Button::Key(Key::Z), Button::Mouse(MouseButton::Left));
+        assert_eq!(sequence.frames, [vec![right], vec![right], vec![right, z], vec![], vec![lmb]]);
+        assert_eq!(sequence.buttons(), [right, z, lmb]);
+        assert_eq!(sequence.to_string(), "Right*2 Right+Z . LMB");
+        assert_eq!("".parse::<InputSequence>(), Ok(InputSequence::default()));
+        assert!("Right*x".parse::<InputSequence>().is_err());
+        assert!("Right+Nothing".parse::<InputSequence>().is_err());
+        assert!("Z*99999999999".parse::<InputSequence>().is_err());
+        let too_long = format!("Z*{} .", InputSequence::MAX_FRAMES).parse::<InputSequence>();
+        assert_eq!(too_long, Err(format!("sequence is longer than {} frames", InputSequence::MAX_FRAMES)));
+    }
+
+    #[test]
+    fn random_call_syntax() {
+        assert_eq!("random(1)".parse(), Ok(RandomCall::Random(1.0)));
+        assert_eq!(" irandom( 10 ) ".parse(), Ok(RandomCall::Irandom(10)));
+        assert_eq!(RandomCall::Random(0.5).to_string(), "random(0.5)");
+        assert!("irandom(1.5)".parse::<RandomCall>().is_err());
+        assert!("choose(1, 2)".parse::<RandomCall>().is_err());
+        assert!("random".parse::<RandomCall>().is_err());
+    }
+
+    #[test]
+    fn watchpoint_syntax() {
+        assert_eq!(
+            "100003 hspeed".parse(),
+            Ok(Watchpoint { target: WatchTarget::Instance(100003), variable: "hspeed".into(), pause: false })
+        );
+        assert_eq!(
+            " obj:4  hp pause".parse(),
+            Ok(Watchpoint { target: WatchTarget::Object(4), variable: "hp".into(), pause: true })
+        );
+        assert!("obj:x hp".parse::<Watchpoint>().is_err());
+        assert!("100003".parse::<Watchpoint>().is_err());
+        assert!("100003 x now".parse::<Watchpoint>().is_err());
+    }
+}